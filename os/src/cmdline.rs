@@ -0,0 +1,26 @@
+//! The kernel command line, a list of `key` or `key=value` options split by
+//! spaces. Not every platform passes boot arguments on, so the line is set
+//! when the kernel is built with `make run CMDLINE="..."`.
+
+const CMDLINE: &str = match option_env!("KERNEL_CMDLINE") {
+    Some(cmdline) => cmdline,
+    None => "",
+};
+
+/// The whole command line.
+pub fn cmdline() -> &'static str {
+    CMDLINE
+}
+
+/// The value of option `key`, "" if it is given without one, None if it is
+/// not given. The last one wins if it is given more than once.
+pub fn option(key: &str) -> Option<&'static str> {
+    CMDLINE
+        .split_whitespace()
+        .filter_map(|opt| match opt.split_once('=') {
+            Some((k, value)) if k == key => Some(value),
+            None if opt == key => Some(""),
+            _ => None,
+        })
+        .last()
+}
@@ -5,3 +5,13 @@ pub const KERNEL_STACK_SIZE: usize = 4096 * 5;
 pub const KERNEL_HEAP_SIZE: usize = 0x200_0000;
 
 pub const PAGE_SIZE: usize = 0x1000;
+
+/// End of the user part of every address space, the lower half of Sv39
+pub const USER_SPACE_END: usize = 0x40_0000_0000;
+/// mmap places mappings without a fixed address from here on
+pub const USER_MMAP_BASE: usize = 0x10_0000_0000;
+/// The vDSO page, which every user address space maps read-only
+pub const VDSO_BASE: usize = USER_MMAP_BASE - PAGE_SIZE;
+/// The code in the vDSO which Linux signal handlers without a restorer
+/// return to
+pub const SIGRETURN_TRAMPOLINE: usize = VDSO_BASE + 0x200;
@@ -4,7 +4,7 @@ mod ram_blk;
 #[cfg(any(target_arch = "riscv64", target_arch = "aarch64"))]
 mod virtio_blk;
 #[cfg(any(target_arch = "riscv64", target_arch = "aarch64"))]
-pub use virtio_blk::VirtIOBlock;
+pub use virtio_blk::{VirtIOBlock, VirtioHal};
 
 use alloc::sync::Arc;
 use easyfs::BlockDevice;
@@ -1,3 +1,5 @@
 pub mod block;
+pub mod rng;
 
 pub use block::BLOCK_DEVICE;
+pub use rng::RNG_DEVICE;
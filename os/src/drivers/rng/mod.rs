@@ -0,0 +1,26 @@
+#[cfg(any(target_arch = "riscv64", target_arch = "aarch64"))]
+mod virtio_rng;
+
+#[cfg(any(target_arch = "riscv64", target_arch = "aarch64"))]
+pub use virtio_rng::VirtIORngDevice;
+
+use alloc::sync::Arc;
+use lazy_static::*;
+
+/// A hardware source of random bytes
+pub trait RngDevice: Send + Sync {
+    /// Fill `buf` from the device as far as it can. Return how many bytes
+    /// it filled.
+    fn read_entropy(&self, buf: &mut [u8]) -> usize;
+}
+
+#[cfg(any(target_arch = "riscv64", target_arch = "aarch64"))]
+lazy_static! {
+    pub static ref RNG_DEVICE: Option<Arc<dyn RngDevice>> =
+        VirtIORngDevice::probe().map(|rng| Arc::new(rng) as Arc<dyn RngDevice>);
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "loongarch64"))]
+lazy_static! {
+    pub static ref RNG_DEVICE: Option<Arc<dyn RngDevice>> = None;
+}
@@ -0,0 +1,41 @@
+use core::ptr::NonNull;
+
+use super::RngDevice;
+use crate::drivers::block::VirtioHal;
+use crate::sync::UPSafeCell;
+use polyhal::consts::VIRT_ADDR_START;
+use virtio_drivers::device::rng::VirtIORng;
+use virtio_drivers::transport::mmio::{MmioTransport, VirtIOHeader};
+use virtio_drivers::transport::{DeviceType, Transport};
+
+// the slot after the block device, virtio-mmio-bus.1 in QEMU
+#[cfg(target_arch = "riscv64")]
+const VIRTIO1: usize = 0x10002000;
+
+#[cfg(target_arch = "aarch64")]
+const VIRTIO1: usize = 0xa00_0200;
+
+pub struct VirtIORngDevice(UPSafeCell<VirtIORng<VirtioHal, MmioTransport>>);
+
+unsafe impl Sync for VirtIORngDevice {}
+unsafe impl Send for VirtIORngDevice {}
+
+impl RngDevice for VirtIORngDevice {
+    fn read_entropy(&self, buf: &mut [u8]) -> usize {
+        self.0.exclusive_access().request_entropy(buf).unwrap_or(0)
+    }
+}
+
+impl VirtIORngDevice {
+    /// The virtio-rng device at VIRTIO1, None if the slot holds no such
+    /// device.
+    pub fn probe() -> Option<Self> {
+        let header = (VIRTIO1 | VIRT_ADDR_START) as *mut VirtIOHeader;
+        let transport = unsafe { MmioTransport::new(NonNull::new_unchecked(header)) }.ok()?;
+        if transport.device_type() != DeviceType::EntropySource {
+            return None;
+        }
+        let rng = VirtIORng::<VirtioHal, MmioTransport>::new(transport).ok()?;
+        Some(Self(unsafe { UPSafeCell::new(rng) }))
+    }
+}
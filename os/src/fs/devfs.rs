@@ -0,0 +1,137 @@
+//! devfs, the character devices which are mounted at /dev.
+
+use super::vfs::{Inode, NodeKind, SuperBlock};
+use super::{File, FileStat, OpenFlags, S_IFCHR, S_IFDIR};
+use crate::random::{add_user_entropy, fill_random, random_ready};
+use crate::syscall::{Errno, EINTR, EPERM};
+use crate::task::{current_has_interrupting_signal, suspend_current_and_run_next};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+
+/// Every devfs is the same, as there is one set of devices
+pub struct DevFs;
+
+impl SuperBlock for DevFs {
+    fn fs_type(&self) -> &'static str {
+        "devfs"
+    }
+    fn root(&self) -> Arc<dyn Inode> {
+        Arc::new(DevNode::Root)
+    }
+}
+
+enum DevNode {
+    Root,
+    Random,
+    Urandom,
+}
+
+impl Inode for DevNode {
+    fn kind(&self) -> NodeKind {
+        match self {
+            Self::Root => NodeKind::Dir,
+            _ => NodeKind::File,
+        }
+    }
+    fn mode(&self) -> u32 {
+        match self {
+            Self::Root => S_IFDIR | 0o755,
+            _ => S_IFCHR | 0o666,
+        }
+    }
+    fn size(&self) -> usize {
+        0
+    }
+    fn nlink(&self) -> usize {
+        1
+    }
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> usize {
+        0
+    }
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> usize {
+        0
+    }
+    fn clear(&self) {}
+    fn lookup(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        let node = match (self, name) {
+            (Self::Root, "random") => Self::Random,
+            (Self::Root, "urandom") => Self::Urandom,
+            _ => return None,
+        };
+        Some(Arc::new(node))
+    }
+    // the set of devices is fixed
+    fn create(&self, _name: &str, _kind: NodeKind) -> Result<Arc<dyn Inode>, Errno> {
+        Err(EPERM)
+    }
+    fn list(&self) -> Vec<String> {
+        match self {
+            Self::Root => vec![String::from("random"), String::from("urandom")],
+            _ => Vec::new(),
+        }
+    }
+    fn link(&self, _name: &str, _inode: &Arc<dyn Inode>) -> Result<(), Errno> {
+        Err(EPERM)
+    }
+    fn unlink(&self, _name: &str) -> Result<(), Errno> {
+        Err(EPERM)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn open_device(&self, flags: OpenFlags) -> Option<Arc<dyn File + Send + Sync>> {
+        let (readable, writable) = flags.read_write();
+        let blocking = match self {
+            Self::Root => return None,
+            Self::Random => true,
+            Self::Urandom => false,
+        };
+        Some(Arc::new(RandomDevice {
+            readable,
+            writable,
+            blocking,
+        }))
+    }
+}
+
+/// /dev/random, which waits until the random number generator is seeded,
+/// or /dev/urandom, which never waits. Bytes written to either are mixed
+/// into the entropy pool.
+pub struct RandomDevice {
+    readable: bool,
+    writable: bool,
+    blocking: bool,
+}
+
+impl File for RandomDevice {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Errno> {
+        while self.blocking && !random_ready() {
+            if current_has_interrupting_signal() {
+                return Err(EINTR);
+            }
+            suspend_current_and_run_next();
+        }
+        fill_random(buf);
+        Ok(buf.len())
+    }
+    fn write(&self, buf: &mut [u8]) -> usize {
+        add_user_entropy(buf);
+        buf.len()
+    }
+    fn stat(&self) -> FileStat {
+        FileStat {
+            mode: S_IFCHR | 0o666,
+            nlink: 1,
+            size: 0,
+        }
+    }
+}
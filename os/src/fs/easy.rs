@@ -0,0 +1,415 @@
+//! easyfs behind the VFS traits. easyfs has just one flat directory and
+//! can't remove a file from it, so a directory is an easyfs file holding a
+//! table of entries, and the files and directories in it are easyfs files
+//! named `/<id>`, which no path component can be. The table of the root
+//! directory is `/0`. An id whose last name is gone and which nothing has
+//! open any more is emptied and taken again by the next file created.
+//! The packed apps stay plain easyfs files under their own names in the
+//! root directory, which can't be unlinked, linked or renamed.
+
+use super::vfs::{Inode, NodeKind, SuperBlock};
+use crate::drivers::BLOCK_DEVICE;
+use crate::sync::UPSafeCell;
+use crate::syscall::{Errno, EEXIST, ENAMETOOLONG, ENOENT, ENOSPC, EPERM, EXDEV};
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use alloc::vec::Vec;
+use core::any::Any;
+use easyfs::EasyFileSystem;
+use lazy_static::*;
+
+/// Longest name in a directory, the same as in easyfs
+const NAME_LEN: usize = 27;
+/// Bytes of a table entry: the name padded with zeroes, the kind and the id
+const ENTRY_SIZE: usize = 32;
+/// id of the table of the root directory
+const ROOT_ID: u32 = 0;
+/// easyfs name of the file which holds the next new id
+const NEXT_ID: &str = "/ids";
+/// easyfs name of the file which holds the link count of each id
+const LINKS: &str = "/links";
+/// easyfs name of the file which holds the ids which were freed
+const FREE_IDS: &str = "/free";
+
+lazy_static! {
+    /// The one directory of easyfs on the block device
+    static ref ROOT_INODE: Arc<easyfs::Inode> = {
+        let efs = EasyFileSystem::open(BLOCK_DEVICE.clone());
+        Arc::new(EasyFileSystem::root_inode(&efs))
+    };
+    /// The inodes which something has, so that an id has only one at a
+    /// time and is freed when that is dropped, which also removes it here
+    static ref INODES: UPSafeCell<BTreeMap<u32, Weak<EasyInode>>> =
+        unsafe { UPSafeCell::new(BTreeMap::new()) };
+}
+
+pub fn list_apps() {
+    println!("/**** APPS ****");
+    // the files of directories have names with '/'
+    for app in ROOT_INODE.ls().iter().filter(|name| !name.contains('/')) {
+        println!("{}", app);
+    }
+    println!("**************/");
+}
+
+/// easyfs on the block device
+pub struct EasyFs {
+    root: Arc<EasyInode>,
+}
+
+impl EasyFs {
+    pub fn open() -> Self {
+        let table = ROOT_INODE
+            .find(&easyfs_name(ROOT_ID))
+            .or_else(|| ROOT_INODE.create(&easyfs_name(ROOT_ID)))
+            .expect("can't create the root directory table");
+        free_orphans();
+        Self {
+            root: Arc::new(EasyInode {
+                kind: NodeKind::Dir,
+                inode: table,
+                id: Some(ROOT_ID),
+            }),
+        }
+    }
+}
+
+impl SuperBlock for EasyFs {
+    fn fs_type(&self) -> &'static str {
+        "easyfs"
+    }
+    fn root(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+}
+
+/// A file or directory of easyfs
+pub struct EasyInode {
+    kind: NodeKind,
+    /// the file, or the table of the directory
+    inode: Arc<easyfs::Inode>,
+    /// None for a packed app
+    id: Option<u32>,
+}
+
+struct Entry {
+    name: String,
+    kind: NodeKind,
+    id: u32,
+    /// where in the table it is
+    offset: usize,
+}
+
+fn easyfs_name(id: u32) -> String {
+    format!("/{}", id)
+}
+
+/// Everything in `inode`.
+fn read_whole(inode: &easyfs::Inode) -> Vec<u8> {
+    let mut buffer = [0u8; 512];
+    let mut data = Vec::new();
+    loop {
+        let len = inode.read_at(data.len(), &mut buffer);
+        if len == 0 {
+            return data;
+        }
+        data.extend_from_slice(&buffer[..len]);
+    }
+}
+
+/// The size of `inode`, which easyfs does not tell. Probe single bytes for
+/// a bound past the end, then for the end below it.
+fn size_of(inode: &easyfs::Inode) -> usize {
+    let has_byte = |offset: usize| inode.read_at(offset, &mut [0u8]) == 1;
+    let mut bound = 1;
+    while has_byte(bound - 1) {
+        bound *= 2;
+    }
+    // the size is in [bound / 2, bound - 1]
+    let (mut low, mut high) = (bound / 2, bound - 1);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if has_byte(mid) {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// The easyfs file `name`, made if there is none.
+fn meta_file(name: &str) -> Result<Arc<easyfs::Inode>, Errno> {
+    match ROOT_INODE.find(name) {
+        Some(file) => Ok(file),
+        None => ROOT_INODE.create(name).ok_or(ENOSPC),
+    }
+}
+
+/// The `index`th u32 of the easyfs file `name`, None if it is not there.
+fn read_u32(name: &str, index: u32) -> Option<u32> {
+    let mut bytes = [0u8; 4];
+    match ROOT_INODE
+        .find(name)?
+        .read_at(index as usize * 4, &mut bytes)
+    {
+        4 => Some(u32::from_le_bytes(bytes)),
+        _ => None,
+    }
+}
+
+fn write_u32(name: &str, index: u32, value: u32) -> Result<(), Errno> {
+    let file = meta_file(name)?;
+    if file.write_at(index as usize * 4, &value.to_le_bytes()) != 4 {
+        return Err(ENOSPC);
+    }
+    Ok(())
+}
+
+fn free_ids() -> Vec<u32> {
+    ROOT_INODE.find(FREE_IDS).map_or(Vec::new(), |file| {
+        read_whole(&file)
+            .chunks_exact(4)
+            .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+            .collect()
+    })
+}
+
+fn nlink_of(id: u32) -> u32 {
+    read_u32(LINKS, id).unwrap_or(0)
+}
+
+/// An id for a new easyfs file of the tree, a freed one if there is one.
+fn alloc_id() -> Result<u32, Errno> {
+    let mut free = free_ids();
+    if let Some(id) = free.pop() {
+        let file = meta_file(FREE_IDS)?;
+        file.clear();
+        for (i, id) in free.iter().enumerate() {
+            write_u32(FREE_IDS, i as u32, *id)?;
+        }
+        return Ok(id);
+    }
+    // id 0 is the root table
+    let id = read_u32(NEXT_ID, 0).unwrap_or(ROOT_ID + 1);
+    write_u32(NEXT_ID, 0, id + 1)?;
+    Ok(id)
+}
+
+/// Empty the easyfs file of `id` and hand the id out again.
+fn free_id(id: u32) {
+    if let Some(file) = ROOT_INODE.find(&easyfs_name(id)) {
+        file.clear();
+    }
+    // on a full disk the id is lost, and its file stays empty
+    let _ = write_u32(FREE_IDS, free_ids().len() as u32, id);
+}
+
+/// Free what was unlinked but still open when the kernel last stopped.
+fn free_orphans() {
+    let free = free_ids();
+    let next = read_u32(NEXT_ID, 0).unwrap_or(ROOT_ID + 1);
+    for id in ROOT_ID + 1..next {
+        if nlink_of(id) == 0 && !free.contains(&id) {
+            free_id(id);
+        }
+    }
+}
+
+/// The inode of `id`, the same one for as long as anything has it.
+fn inode_of(id: u32, kind: NodeKind) -> Option<Arc<EasyInode>> {
+    if let Some(inode) = INODES.exclusive_access().get(&id).and_then(Weak::upgrade) {
+        return Some(inode);
+    }
+    let inode = Arc::new(EasyInode {
+        kind,
+        inode: ROOT_INODE.find(&easyfs_name(id))?,
+        id: Some(id),
+    });
+    INODES.exclusive_access().insert(id, Arc::downgrade(&inode));
+    Some(inode)
+}
+
+impl EasyInode {
+    fn is_root(&self) -> bool {
+        self.id == Some(ROOT_ID)
+    }
+    fn entries(&self) -> Vec<Entry> {
+        read_whole(&self.inode)
+            .chunks_exact(ENTRY_SIZE)
+            .enumerate()
+            .filter(|(_, raw)| raw[0] != 0)
+            .map(|(i, raw)| {
+                let len = raw[..NAME_LEN]
+                    .iter()
+                    .position(|c| *c == 0)
+                    .unwrap_or(NAME_LEN);
+                Entry {
+                    name: String::from_utf8_lossy(&raw[..len]).into_owned(),
+                    kind: if raw[NAME_LEN] == NodeKind::Dir as u8 {
+                        NodeKind::Dir
+                    } else {
+                        NodeKind::File
+                    },
+                    id: u32::from_le_bytes(raw[NAME_LEN + 1..].try_into().unwrap()),
+                    offset: i * ENTRY_SIZE,
+                }
+            })
+            .collect()
+    }
+    fn entry(&self, name: &str) -> Option<Entry> {
+        self.entries().into_iter().find(|entry| entry.name == name)
+    }
+    /// Write an entry to the table at `offset`.
+    fn write_entry(&self, offset: usize, name: &str, kind: NodeKind, id: u32) -> Result<(), Errno> {
+        if name.len() > NAME_LEN {
+            return Err(ENAMETOOLONG);
+        }
+        let mut raw = [0u8; ENTRY_SIZE];
+        raw[..name.len()].copy_from_slice(name.as_bytes());
+        raw[NAME_LEN] = kind as u8;
+        raw[NAME_LEN + 1..].copy_from_slice(&id.to_le_bytes());
+        if self.inode.write_at(offset, &raw) != ENTRY_SIZE {
+            return Err(ENOSPC);
+        }
+        Ok(())
+    }
+    /// Write an entry to the first free slot of the table.
+    fn add_entry(&self, name: &str, kind: NodeKind, id: u32) -> Result<(), Errno> {
+        if name.len() > NAME_LEN {
+            return Err(ENAMETOOLONG);
+        }
+        let table = read_whole(&self.inode);
+        let offset = table
+            .chunks_exact(ENTRY_SIZE)
+            .position(|slot| slot[0] == 0)
+            .map_or(table.len(), |i| i * ENTRY_SIZE);
+        self.write_entry(offset, name, kind, id)
+    }
+}
+
+impl Drop for EasyInode {
+    fn drop(&mut self) {
+        // the last name went while something had it, which is done now
+        if let Some(id) = self.id {
+            // its weak entry can't be upgraded any more
+            INODES.exclusive_access().remove(&id);
+            if id != ROOT_ID && nlink_of(id) == 0 {
+                free_id(id);
+            }
+        }
+    }
+}
+
+impl Inode for EasyInode {
+    fn kind(&self) -> NodeKind {
+        self.kind
+    }
+    fn size(&self) -> usize {
+        size_of(&self.inode)
+    }
+    // the root directory and the packed apps have one name, but no entry
+    // in the table of link counts
+    fn nlink(&self) -> usize {
+        match self.id {
+            Some(id) if id != ROOT_ID => nlink_of(id) as usize,
+            _ => 1,
+        }
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        self.inode.read_at(offset, buf)
+    }
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        self.inode.write_at(offset, buf)
+    }
+    fn clear(&self) {
+        self.inode.clear();
+    }
+    fn lookup(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        if let Some(entry) = self.entry(name) {
+            let inode = inode_of(entry.id, entry.kind)?;
+            return Some(inode);
+        }
+        if self.is_root() && !name.contains('/') {
+            return Some(Arc::new(EasyInode {
+                kind: NodeKind::File,
+                inode: ROOT_INODE.find(name)?,
+                id: None,
+            }));
+        }
+        None
+    }
+    fn create(&self, name: &str, kind: NodeKind) -> Result<Arc<dyn Inode>, Errno> {
+        if name.len() > NAME_LEN {
+            return Err(ENAMETOOLONG);
+        }
+        if self.lookup(name).is_some() {
+            return Err(EEXIST);
+        }
+        let id = alloc_id()?;
+        let added = meta_file(&easyfs_name(id))
+            .and_then(|_| write_u32(LINKS, id, 1))
+            .and_then(|_| self.add_entry(name, kind, id));
+        if let Err(errno) = added {
+            let _ = write_u32(LINKS, id, 0);
+            free_id(id);
+            return Err(errno);
+        }
+        let inode = inode_of(id, kind).ok_or(ENOSPC)?;
+        Ok(inode)
+    }
+    fn list(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.entries().into_iter().map(|entry| entry.name).collect();
+        if self.is_root() {
+            let apps = ROOT_INODE.ls().into_iter();
+            names.extend(apps.filter(|name| !name.contains('/')));
+        }
+        names
+    }
+    fn link(&self, name: &str, inode: &Arc<dyn Inode>) -> Result<(), Errno> {
+        let target = inode.as_any().downcast_ref::<EasyInode>().ok_or(EXDEV)?;
+        let id = target.id.ok_or(EPERM)?;
+        if self.lookup(name).is_some() {
+            return Err(EEXIST);
+        }
+        self.add_entry(name, target.kind, id)?;
+        write_u32(LINKS, id, nlink_of(id) + 1)
+    }
+    fn unlink(&self, name: &str) -> Result<(), Errno> {
+        let entry = match self.entry(name) {
+            Some(entry) => entry,
+            None if self.lookup(name).is_some() => return Err(EPERM),
+            None => return Err(ENOENT),
+        };
+        // freed when this is dropped, unless something else has it
+        let _inode = inode_of(entry.id, entry.kind);
+        if self.inode.write_at(entry.offset, &[0u8; ENTRY_SIZE]) != ENTRY_SIZE {
+            return Err(ENOSPC);
+        }
+        write_u32(LINKS, entry.id, nlink_of(entry.id).saturating_sub(1))
+    }
+    fn replace(&self, name: &str, inode: &Arc<dyn Inode>) -> Result<(), Errno> {
+        let target = inode.as_any().downcast_ref::<EasyInode>().ok_or(EXDEV)?;
+        let id = target.id.ok_or(EPERM)?;
+        let entry = match self.entry(name) {
+            Some(entry) => entry,
+            None if self.lookup(name).is_some() => return Err(EPERM),
+            None => return Err(ENOENT),
+        };
+        // the slot is rewritten in place, so the table does not grow, and
+        // the link counts of both ids are already in LINKS
+        write_u32(LINKS, id, nlink_of(id) + 1)?;
+        // freed when this is dropped, unless something else has it
+        let _old = inode_of(entry.id, entry.kind);
+        if let Err(errno) = self.write_entry(entry.offset, name, target.kind, id) {
+            let _ = write_u32(LINKS, id, nlink_of(id) - 1);
+            return Err(errno);
+        }
+        write_u32(LINKS, entry.id, nlink_of(entry.id).saturating_sub(1))
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -1,11 +1,13 @@
-use super::File;
-use crate::drivers::BLOCK_DEVICE;
+use super::path::{resolve, resolve_parent};
+use super::vfs::{Inode, NodeKind};
+use super::{File, FileStat};
 use crate::sync::UPSafeCell;
+use crate::syscall::{Errno, EISDIR, ENOENT};
+use crate::task::{current_rlimit, current_task, SignalFlags, SignalInfo, RLIMIT_FSIZE};
+use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use bitflags::*;
-use easyfs::{EasyFileSystem, Inode};
-use lazy_static::*;
 
 pub struct OSInode {
     readable: bool,
@@ -15,11 +17,11 @@ pub struct OSInode {
 
 pub struct OSInodeInner {
     offset: usize,
-    inode: Arc<Inode>,
+    inode: Arc<dyn Inode>,
 }
 
 impl OSInode {
-    pub fn new(readable: bool, writable: bool, inode: Arc<Inode>) -> Self {
+    pub fn new(readable: bool, writable: bool, inode: Arc<dyn Inode>) -> Self {
         Self {
             readable,
             writable,
@@ -42,21 +44,6 @@ impl OSInode {
     }
 }
 
-lazy_static! {
-    pub static ref ROOT_INODE: Arc<Inode> = {
-        let efs = EasyFileSystem::open(BLOCK_DEVICE.clone());
-        Arc::new(EasyFileSystem::root_inode(&efs))
-    };
-}
-
-pub fn list_apps() {
-    println!("/**** APPS ****");
-    for app in ROOT_INODE.ls() {
-        println!("{}", app);
-    }
-    println!("**************/");
-}
-
 bitflags! {
     pub struct OpenFlags: u32 {
         const RDONLY = 0;
@@ -81,27 +68,27 @@ impl OpenFlags {
     }
 }
 
-pub fn open_file(name: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
+/// Open the file at `path` taken from the directory `cwd`. CREATE makes it
+/// if it does not exist and empties it if it does.
+pub fn open_file(cwd: &str, path: &str, flags: OpenFlags) -> Result<Arc<OSInode>, Errno> {
     let (readable, writable) = flags.read_write();
-    if flags.contains(OpenFlags::CREATE) {
-        if let Some(inode) = ROOT_INODE.find(name) {
-            // clear size
-            inode.clear();
-            Some(Arc::new(OSInode::new(readable, writable, inode)))
-        } else {
-            // create file
-            ROOT_INODE
-                .create(name)
-                .map(|inode| Arc::new(OSInode::new(readable, writable, inode)))
-        }
-    } else {
-        ROOT_INODE.find(name).map(|inode| {
-            if flags.contains(OpenFlags::TRUNC) {
-                inode.clear();
+    let dentry = match resolve(cwd, path) {
+        Ok(dentry) => {
+            if dentry.is_dir() {
+                return Err(EISDIR);
             }
-            Arc::new(OSInode::new(readable, writable, inode))
-        })
-    }
+            if flags.intersects(OpenFlags::CREATE | OpenFlags::TRUNC) {
+                dentry.inode.clear();
+            }
+            dentry
+        }
+        Err(ENOENT) if flags.contains(OpenFlags::CREATE) => {
+            let (dir, name) = resolve_parent(cwd, path)?;
+            dir.create(name, NodeKind::File)?
+        }
+        Err(errno) => return Err(errno),
+    };
+    Ok(Arc::new(OSInode::new(readable, writable, dentry.inode)))
 }
 
 impl File for OSInode {
@@ -111,16 +98,72 @@ impl File for OSInode {
     fn writable(&self) -> bool {
         self.writable
     }
-    fn read(&self, buf: &mut [u8]) -> usize {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Errno> {
         let mut inner = self.inner.exclusive_access();
         let rsize = inner.inode.read_at(inner.offset, buf);
         inner.offset += rsize;
-        rsize
+        Ok(rsize)
     }
     fn write(&self, buf: &mut [u8]) -> usize {
         let mut inner = self.inner.exclusive_access();
-        let wsize = inner.inode.write_at(inner.offset, buf);
+        // only the part below RLIMIT_FSIZE is written
+        let limit = current_rlimit(RLIMIT_FSIZE);
+        let len = buf.len().min(limit.saturating_sub(inner.offset));
+        if len == 0 && !buf.is_empty() {
+            let sigxfsz = SignalFlags::SIGXFSZ;
+            let task = current_task().unwrap();
+            let mut task_inner = task.inner_exclusive_access();
+            task_inner.add_signal(sigxfsz, SignalInfo::kernel(sigxfsz.signum(), 0));
+            return 0;
+        }
+        let wsize = inner.inode.write_at(inner.offset, &buf[..len]);
         inner.offset += wsize;
         wsize
     }
+    fn stat(&self) -> FileStat {
+        let inner = self.inner.exclusive_access();
+        FileStat {
+            mode: inner.inode.mode(),
+            nlink: inner.inode.nlink(),
+            size: inner.inode.size(),
+        }
+    }
+}
+
+/// An open directory, which *at syscalls can take paths relative to
+pub struct DirFile {
+    /// absolute path without `.` or `..`
+    path: String,
+    mode: u32,
+}
+
+impl DirFile {
+    pub fn new(path: String, mode: u32) -> Self {
+        Self { path, mode }
+    }
+}
+
+impl File for DirFile {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    fn read(&self, _buf: &mut [u8]) -> Result<usize, Errno> {
+        Ok(0)
+    }
+    fn write(&self, _buf: &mut [u8]) -> usize {
+        0
+    }
+    fn stat(&self) -> FileStat {
+        FileStat {
+            mode: self.mode,
+            nlink: 1,
+            size: 0,
+        }
+    }
+    fn dir_path(&self) -> Option<&str> {
+        Some(&self.path)
+    }
 }
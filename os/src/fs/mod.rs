@@ -1,14 +1,73 @@
+mod devfs;
+mod easy;
 mod inode;
+mod mount;
+mod path;
 mod pipe;
+mod procfs;
 mod stdio;
+mod tmpfs;
+mod vfs;
 
 pub trait File: Send + Sync {
     fn readable(&self) -> bool;
     fn writable(&self) -> bool;
-    fn read(&self, buf: &mut [u8]) -> usize;
+    /// Read into `buf`, failing with EINTR if a signal comes while waiting
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Errno>;
     fn write(&self, buf: &mut [u8]) -> usize;
+    fn stat(&self) -> FileStat;
+    /// Whether the file is a terminal, which answers the tty ioctls
+    fn is_tty(&self) -> bool {
+        false
+    }
+    /// The absolute path of the directory if the file is one, which *at
+    /// syscalls take relative paths from
+    fn dir_path(&self) -> Option<&str> {
+        None
+    }
 }
 
-pub use inode::{list_apps, open_file, OpenFlags};
+// file types in `FileStat::mode`, same values as Linux
+pub const S_IFIFO: u32 = 0o010000;
+pub const S_IFCHR: u32 = 0o020000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+
+/// What fstat reports about a file
+#[derive(Debug, Clone, Copy)]
+pub struct FileStat {
+    /// file type and permission bits
+    pub mode: u32,
+    /// names of the file, 0 once it is unlinked
+    pub nlink: usize,
+    pub size: usize,
+}
+
+pub use easy::list_apps;
+pub use inode::{open_file, DirFile, OpenFlags};
+pub use mount::{mount, umount};
+pub use path::{resolve, resolve_parent};
 pub use pipe::make_pipe;
 pub use stdio::{Stdin, Stdout};
+pub use vfs::{Dentry, NodeKind};
+
+use crate::syscall::{Errno, EISDIR};
+use alloc::sync::Arc;
+
+/// Open the device, directory or file at `path` taken from the directory
+/// `cwd`. A directory can only be opened read-only.
+pub fn open(cwd: &str, path: &str, flags: OpenFlags) -> Result<Arc<dyn File + Send + Sync>, Errno> {
+    if let Ok(dentry) = resolve(cwd, path) {
+        if dentry.is_dir() {
+            if flags != OpenFlags::RDONLY {
+                return Err(EISDIR);
+            }
+            let mode = dentry.inode.mode();
+            return Ok(Arc::new(DirFile::new(dentry.path, mode)));
+        }
+        if let Some(device) = dentry.inode.open_device(flags) {
+            return Ok(device);
+        }
+    }
+    open_file(cwd, path, flags).map(|inode| inode as Arc<dyn File + Send + Sync>)
+}
@@ -0,0 +1,147 @@
+//! The mount table, which puts file systems together into one namespace.
+//! A file system is mounted at an absolute path, and hides what was there
+//! until it is unmounted. easyfs on the block device is mounted at `/`,
+//! devfs at `/dev` and procfs at `/proc` from boot on.
+
+use super::devfs::DevFs;
+use super::easy::EasyFs;
+use super::procfs::ProcFs;
+use super::tmpfs::TmpFs;
+use super::vfs::{Dentry, Inode, SuperBlock};
+use crate::sync::UPSafeCell;
+use crate::syscall::{Errno, EBUSY, EINVAL, ENODEV};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use lazy_static::*;
+use log::info;
+
+struct Mount {
+    /// absolute path without `.` or `..`
+    path: String,
+    sb: Arc<dyn SuperBlock>,
+    /// tells the mounts apart, even of the same file system
+    dev: usize,
+}
+
+lazy_static! {
+    /// Mounts in the order they were made, a later one at the same path
+    /// hides the earlier ones
+    static ref MOUNTS: UPSafeCell<Vec<Mount>> = unsafe {
+        UPSafeCell::new(vec![
+            Mount {
+                path: String::from("/"),
+                sb: Arc::new(EasyFs::open()),
+                dev: 0,
+            },
+            Mount {
+                path: String::from("/dev"),
+                sb: Arc::new(DevFs),
+                dev: 1,
+            },
+            Mount {
+                path: String::from("/proc"),
+                sb: Arc::new(ProcFs),
+                dev: 2,
+            },
+        ])
+    };
+    static ref NEXT_DEV: UPSafeCell<usize> = unsafe { UPSafeCell::new(3) };
+}
+
+/// Whether the absolute `path` is below the directory `dir`.
+fn is_inside(path: &str, dir: &str) -> bool {
+    let rest = match path.strip_prefix(dir) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    !rest.is_empty() && (dir.ends_with('/') || rest.starts_with('/'))
+}
+
+/// The root and dev of the file system mounted last at the absolute
+/// `path`, unless a file system mounted later above it hides it.
+pub fn mounted_at(path: &str) -> Option<(Arc<dyn Inode>, usize)> {
+    let mounts = MOUNTS.exclusive_access();
+    let i = mounts.iter().rposition(|mount| mount.path == path)?;
+    if mounts[i + 1..]
+        .iter()
+        .any(|mount| is_inside(path, &mount.path))
+    {
+        return None;
+    }
+    Some((mounts[i].sb.root(), mounts[i].dev))
+}
+
+/// Whether a file system is mounted below the absolute `path`.
+pub fn mounted_inside(path: &str) -> bool {
+    MOUNTS
+        .exclusive_access()
+        .iter()
+        .any(|mount| is_inside(&mount.path, path))
+}
+
+/// The path and file system type of every mount, in the order they were
+/// made.
+pub fn mount_list() -> Vec<(String, &'static str)> {
+    MOUNTS
+        .exclusive_access()
+        .iter()
+        .map(|mount| (mount.path.clone(), mount.sb.fs_type()))
+        .collect()
+}
+
+pub fn root_dentry() -> Dentry {
+    let (inode, dev) = mounted_at("/").unwrap();
+    Dentry {
+        inode,
+        path: String::from("/"),
+        dev,
+    }
+}
+
+/// A new file system of type `fs_type`. Only the ones which need no
+/// device can be made.
+fn new_fs(fs_type: &str) -> Result<Arc<dyn SuperBlock>, Errno> {
+    match fs_type {
+        "tmpfs" => Ok(Arc::new(TmpFs::new())),
+        "devfs" => Ok(Arc::new(DevFs)),
+        "proc" => Ok(Arc::new(ProcFs)),
+        _ => Err(ENODEV),
+    }
+}
+
+/// Mount a new file system of type `fs_type` on the directory `target`.
+pub fn mount(target: &Dentry, fs_type: &str) -> Result<(), Errno> {
+    let sb = new_fs(fs_type)?;
+    info!("mount: {} at {}", sb.fs_type(), target.path);
+    let mut next_dev = NEXT_DEV.exclusive_access();
+    MOUNTS.exclusive_access().push(Mount {
+        path: target.path.clone(),
+        sb,
+        dev: *next_dev,
+    });
+    *next_dev += 1;
+    Ok(())
+}
+
+/// Unmount the file system mounted last at the absolute `path`. Files
+/// which are still open in it stay usable.
+pub fn umount(path: &str) -> Result<(), Errno> {
+    let mut mounts = MOUNTS.exclusive_access();
+    let i = mounts
+        .iter()
+        .rposition(|mount| mount.path == path)
+        .ok_or(EINVAL)?;
+    // nothing may be mounted inside it, the mounts it hides do not count
+    if path == "/"
+        || mounts[i + 1..]
+            .iter()
+            .any(|mount| is_inside(&mount.path, path))
+    {
+        return Err(EBUSY);
+    }
+    let mount = mounts.remove(i);
+    info!("umount: {} at {}", mount.sb.fs_type(), path);
+    Ok(())
+}
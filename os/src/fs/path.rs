@@ -0,0 +1,73 @@
+//! Path resolution. A path is split at '/', and a relative one starts from
+//! a base directory, given as an absolute path without `.` or `..`.
+//! `.` stays in a directory and `..` goes to its parent, or stays in the
+//! root.
+
+use super::mount::root_dentry;
+use super::vfs::{Dentry, NAME_MAX};
+use crate::syscall::{Errno, EEXIST, ENAMETOOLONG, ENOENT, ENOTDIR};
+use alloc::vec;
+
+/// The components of `path` taken from `base`.
+fn components<'a>(base: &'a str, path: &'a str) -> impl Iterator<Item = &'a str> {
+    let base = if path.starts_with('/') { "" } else { base };
+    base.split('/').chain(path.split('/'))
+}
+
+/// Find `path` taken from `base`, crossing into the file systems mounted
+/// on the way.
+pub fn resolve(base: &str, path: &str) -> Result<Dentry, Errno> {
+    if path.is_empty() {
+        return Err(ENOENT);
+    }
+    // `..` goes back down this stack, so out of a mounted file system too
+    let mut dentries = vec![root_dentry()];
+    for name in components(base, path) {
+        let dentry = dentries.last().unwrap();
+        // only a directory has entries, `.` and `..`, or ends with '/'
+        if !dentry.is_dir() {
+            return Err(ENOTDIR);
+        }
+        match name {
+            "" | "." => {}
+            ".." => {
+                if dentries.len() > 1 {
+                    dentries.pop();
+                }
+            }
+            name => {
+                if name.len() > NAME_MAX {
+                    return Err(ENAMETOOLONG);
+                }
+                let child = dentry.lookup(name).ok_or(ENOENT)?;
+                dentries.push(child);
+            }
+        }
+    }
+    Ok(dentries.pop().unwrap())
+}
+
+/// Find the directory which the last component of `path` taken from
+/// `base` would be in. Return the directory and the name in it. Fail with
+/// EEXIST if the last component is `.` or `..`, or `path` is the root.
+pub fn resolve_parent<'a>(base: &str, path: &'a str) -> Result<(Dentry, &'a str), Errno> {
+    if path.is_empty() {
+        return Err(ENOENT);
+    }
+    let path = path.trim_end_matches('/');
+    let (dir, name) = match path.rfind('/') {
+        Some(i) => (&path[..=i], &path[i + 1..]),
+        None => (".", path),
+    };
+    if matches!(name, "" | "." | "..") {
+        return Err(EEXIST);
+    }
+    if name.len() > NAME_MAX {
+        return Err(ENAMETOOLONG);
+    }
+    let dir = resolve(base, dir)?;
+    if !dir.is_dir() {
+        return Err(ENOTDIR);
+    }
+    Ok((dir, name))
+}
@@ -1,5 +1,6 @@
-use super::File;
+use super::{File, FileStat, S_IFIFO};
 use crate::sync::UPSafeCell;
+use crate::syscall::Errno;
 use alloc::sync::{Arc, Weak};
 
 use crate::task::suspend_current_and_run_next;
@@ -111,7 +112,7 @@ impl File for Pipe {
     fn writable(&self) -> bool {
         self.writable
     }
-    fn read(&self, buf: &mut [u8]) -> usize {
+    fn read(&self, buf: &mut [u8]) -> Result<usize, Errno> {
         assert!(self.readable());
         let want_to_read = buf.len();
         let mut buf_iter = buf.into_iter();
@@ -121,7 +122,7 @@ impl File for Pipe {
             let loop_read = ring_buffer.available_read();
             if loop_read == 0 {
                 if ring_buffer.all_write_ends_closed() {
-                    return already_read;
+                    return Ok(already_read);
                 }
                 drop(ring_buffer);
                 suspend_current_and_run_next();
@@ -132,10 +133,10 @@ impl File for Pipe {
                     *byte_ref = ring_buffer.read_byte();
                     already_read += 1;
                     if already_read == want_to_read {
-                        return want_to_read;
+                        return Ok(want_to_read);
                     }
                 } else {
-                    return already_read;
+                    return Ok(already_read);
                 }
             }
         }
@@ -167,4 +168,11 @@ impl File for Pipe {
             }
         }
     }
+    fn stat(&self) -> FileStat {
+        FileStat {
+            mode: S_IFIFO | 0o600,
+            nlink: 1,
+            size: self.buffer.exclusive_access().available_read(),
+        }
+    }
 }
@@ -0,0 +1,171 @@
+//! procfs, whose files are made up from the state of the kernel whenever
+//! they are read. It is mounted at /proc and holds:
+//!
+//! - `meminfo`, the total and free memory
+//! - `mounts`, the mount table
+//! - `<pid>/status`, the ids and state of each process
+
+use super::mount::mount_list;
+use super::vfs::{Inode, NodeKind, SuperBlock};
+use super::{S_IFDIR, S_IFREG};
+use crate::config::PAGE_SIZE;
+use crate::mm::frame_stats;
+use crate::syscall::{Errno, EPERM};
+use crate::task::{pid2task, process_ids, TaskStatus};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::any::Any;
+
+/// Every procfs is the same, as there is one kernel to show
+pub struct ProcFs;
+
+impl SuperBlock for ProcFs {
+    fn fs_type(&self) -> &'static str {
+        "proc"
+    }
+    fn root(&self) -> Arc<dyn Inode> {
+        Arc::new(ProcNode::Root)
+    }
+}
+
+enum ProcNode {
+    Root,
+    Meminfo,
+    Mounts,
+    /// the directory of the process with this pid
+    Process(usize),
+    Status(usize),
+}
+
+/// Whether `pid` is a live process rather than a thread.
+fn is_process(pid: usize) -> bool {
+    pid2task(pid).map_or(false, |task| task.tgid == pid)
+}
+
+impl ProcNode {
+    /// The text of a file, None once its process is gone.
+    fn contents(&self) -> Option<String> {
+        match self {
+            Self::Meminfo => {
+                let (total, free) = frame_stats();
+                let kb = PAGE_SIZE / 1024;
+                Some(format!(
+                    "MemTotal: {:8} kB\nMemFree:  {:8} kB\n",
+                    total * kb,
+                    free * kb
+                ))
+            }
+            Self::Mounts => Some(
+                mount_list()
+                    .iter()
+                    .map(|(path, fs_type)| format!("{} {} {} rw 0 0\n", fs_type, path, fs_type))
+                    .collect(),
+            ),
+            Self::Status(pid) => {
+                let task = pid2task(*pid)?;
+                let inner = task.inner_exclusive_access();
+                let state = if inner.task_status == TaskStatus::Zombie {
+                    "Z (zombie)"
+                } else if inner.frozen || inner.trace_stopped {
+                    "T (stopped)"
+                } else {
+                    "R (running)"
+                };
+                let ppid = inner
+                    .parent
+                    .as_ref()
+                    .and_then(|parent| parent.upgrade())
+                    .map_or(0, |parent| parent.tgid);
+                let creds = inner.creds;
+                let ids = format!(
+                    "Uid:\t{}\t{}\t{}\nGid:\t{}\t{}\t{}\n",
+                    creds.uid, creds.euid, creds.suid, creds.gid, creds.egid, creds.sgid
+                );
+                Some(format!(
+                    "State:\t{}\nTgid:\t{}\nPid:\t{}\nPPid:\t{}\n{}",
+                    state, task.tgid, pid, ppid, ids
+                ))
+            }
+            Self::Root | Self::Process(_) => None,
+        }
+    }
+}
+
+impl Inode for ProcNode {
+    fn kind(&self) -> NodeKind {
+        match self {
+            Self::Root | Self::Process(_) => NodeKind::Dir,
+            _ => NodeKind::File,
+        }
+    }
+    fn mode(&self) -> u32 {
+        match self.kind() {
+            NodeKind::Dir => S_IFDIR | 0o555,
+            NodeKind::File => S_IFREG | 0o444,
+        }
+    }
+    // as on Linux, the size is not known until the file is read
+    fn size(&self) -> usize {
+        0
+    }
+    fn nlink(&self) -> usize {
+        1
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let contents = match self.contents() {
+            Some(contents) => contents,
+            None => return 0,
+        };
+        let bytes = contents.as_bytes();
+        if offset >= bytes.len() {
+            return 0;
+        }
+        let len = buf.len().min(bytes.len() - offset);
+        buf[..len].copy_from_slice(&bytes[offset..offset + len]);
+        len
+    }
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> usize {
+        0
+    }
+    fn clear(&self) {}
+    fn lookup(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        let node = match (self, name) {
+            (Self::Root, "meminfo") => Self::Meminfo,
+            (Self::Root, "mounts") => Self::Mounts,
+            (Self::Root, pid) => match pid.parse() {
+                Ok(pid) if is_process(pid) => Self::Process(pid),
+                _ => return None,
+            },
+            (Self::Process(pid), "status") if is_process(*pid) => Self::Status(*pid),
+            _ => return None,
+        };
+        Some(Arc::new(node))
+    }
+    // the files follow the kernel, no one makes or removes them
+    fn create(&self, _name: &str, _kind: NodeKind) -> Result<Arc<dyn Inode>, Errno> {
+        Err(EPERM)
+    }
+    fn list(&self) -> Vec<String> {
+        match self {
+            Self::Root => {
+                let mut names = vec![String::from("meminfo"), String::from("mounts")];
+                names.extend(process_ids().iter().map(|pid| pid.to_string()));
+                names
+            }
+            Self::Process(_) => vec![String::from("status")],
+            _ => Vec::new(),
+        }
+    }
+    fn link(&self, _name: &str, _inode: &Arc<dyn Inode>) -> Result<(), Errno> {
+        Err(EPERM)
+    }
+    fn unlink(&self, _name: &str) -> Result<(), Errno> {
+        Err(EPERM)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -1,6 +1,7 @@
 use polyhal::debug_console::DebugConsole;
 
-use super::File;
+use super::{File, FileStat, S_IFCHR};
+use crate::syscall::Errno;
 use crate::task::suspend_current_and_run_next;
 pub struct Stdin;
 
@@ -13,8 +14,11 @@ impl File for Stdin {
     fn writable(&self) -> bool {
         false
     }
-    fn read(&self, user_buf: &mut [u8]) -> usize {
-        assert_eq!(user_buf.len(), 1);
+    /// Read one character, however long `user_buf` is.
+    fn read(&self, user_buf: &mut [u8]) -> Result<usize, Errno> {
+        if user_buf.is_empty() {
+            return Ok(0);
+        }
         // busy loop
         let c: u8;
         loop {
@@ -25,11 +29,21 @@ impl File for Stdin {
             suspend_current_and_run_next();
         }
         user_buf[0] = c as u8;
-        1
+        Ok(1)
     }
     fn write(&self, _user_buf: &mut [u8]) -> usize {
         panic!("Cannot write to stdin!");
     }
+    fn stat(&self) -> FileStat {
+        FileStat {
+            mode: S_IFCHR | 0o620,
+            nlink: 1,
+            size: 0,
+        }
+    }
+    fn is_tty(&self) -> bool {
+        true
+    }
 }
 
 impl File for Stdout {
@@ -39,14 +53,27 @@ impl File for Stdout {
     fn writable(&self) -> bool {
         true
     }
-    fn read(&self, _user_buf: &mut [u8]) -> usize {
+    fn read(&self, _user_buf: &mut [u8]) -> Result<usize, Errno> {
         panic!("Cannot read from stdout!");
     }
     fn write(&self, user_buf: &mut [u8]) -> usize {
         // for buffer in user_buf.buffers.iter() {
         //     print!("{}", core::str::from_utf8(*buffer).unwrap());
         // }
-        print!("{}", core::str::from_utf8(user_buf).unwrap());
+        // bytes go out as they are, they need not be valid UTF-8
+        for c in user_buf.iter() {
+            DebugConsole::putchar(*c);
+        }
         user_buf.len()
     }
+    fn stat(&self) -> FileStat {
+        FileStat {
+            mode: S_IFCHR | 0o620,
+            nlink: 1,
+            size: 0,
+        }
+    }
+    fn is_tty(&self) -> bool {
+        true
+    }
 }
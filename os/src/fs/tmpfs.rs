@@ -0,0 +1,157 @@
+//! tmpfs, a file system in kernel memory which is gone once unmounted.
+
+use super::vfs::{Inode, NodeKind, SuperBlock};
+use crate::sync::UPSafeCell;
+use crate::syscall::{Errno, EEXIST, ENOENT, EXDEV};
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+
+pub struct TmpFs {
+    root: Arc<TmpInode>,
+}
+
+impl TmpFs {
+    pub fn new() -> Self {
+        Self {
+            root: TmpInode::new(NodeKind::Dir),
+        }
+    }
+}
+
+impl SuperBlock for TmpFs {
+    fn fs_type(&self) -> &'static str {
+        "tmpfs"
+    }
+    fn root(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+}
+
+/// A file or directory of tmpfs, which its directories and open files
+/// keep alive
+pub struct TmpInode {
+    kind: NodeKind,
+    inner: UPSafeCell<TmpInodeInner>,
+}
+
+struct TmpInodeInner {
+    nlink: usize,
+    /// contents of a file
+    data: Vec<u8>,
+    /// entries of a directory
+    entries: BTreeMap<String, Arc<dyn Inode>>,
+}
+
+impl TmpInode {
+    fn new(kind: NodeKind) -> Arc<Self> {
+        Arc::new(Self {
+            kind,
+            inner: unsafe {
+                UPSafeCell::new(TmpInodeInner {
+                    nlink: 1,
+                    data: Vec::new(),
+                    entries: BTreeMap::new(),
+                })
+            },
+        })
+    }
+}
+
+impl Inode for TmpInode {
+    fn kind(&self) -> NodeKind {
+        self.kind
+    }
+    fn size(&self) -> usize {
+        self.inner.exclusive_access().data.len()
+    }
+    fn nlink(&self) -> usize {
+        self.inner.exclusive_access().nlink
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let inner = self.inner.exclusive_access();
+        if offset >= inner.data.len() {
+            return 0;
+        }
+        let len = buf.len().min(inner.data.len() - offset);
+        buf[..len].copy_from_slice(&inner.data[offset..offset + len]);
+        len
+    }
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        let mut inner = self.inner.exclusive_access();
+        let end = match offset.checked_add(buf.len()) {
+            Some(end) => end,
+            None => return 0,
+        };
+        if inner.data.len() < end {
+            // the files live on the kernel heap, running out of it is a
+            // short write rather than a panic
+            let grow = end - inner.data.len();
+            if inner.data.try_reserve_exact(grow).is_err() {
+                return 0;
+            }
+            inner.data.resize(end, 0);
+        }
+        inner.data[offset..end].copy_from_slice(buf);
+        buf.len()
+    }
+    fn clear(&self) {
+        self.inner.exclusive_access().data.clear();
+    }
+    fn lookup(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        let inner = self.inner.exclusive_access();
+        let inode = inner.entries.get(name)?.clone();
+        Some(inode)
+    }
+    fn create(&self, name: &str, kind: NodeKind) -> Result<Arc<dyn Inode>, Errno> {
+        let mut inner = self.inner.exclusive_access();
+        if inner.entries.contains_key(name) {
+            return Err(EEXIST);
+        }
+        let inode = TmpInode::new(kind);
+        inner.entries.insert(String::from(name), inode.clone());
+        Ok(inode)
+    }
+    fn list(&self) -> Vec<String> {
+        self.inner
+            .exclusive_access()
+            .entries
+            .keys()
+            .cloned()
+            .collect()
+    }
+    fn link(&self, name: &str, inode: &Arc<dyn Inode>) -> Result<(), Errno> {
+        let target = inode.as_any().downcast_ref::<TmpInode>().ok_or(EXDEV)?;
+        let mut inner = self.inner.exclusive_access();
+        if inner.entries.contains_key(name) {
+            return Err(EEXIST);
+        }
+        target.inner.exclusive_access().nlink += 1;
+        inner.entries.insert(String::from(name), inode.clone());
+        Ok(())
+    }
+    fn unlink(&self, name: &str) -> Result<(), Errno> {
+        let inode = self.inner.exclusive_access().entries.remove(name);
+        let inode = inode.ok_or(ENOENT)?;
+        if let Some(target) = inode.as_any().downcast_ref::<TmpInode>() {
+            target.inner.exclusive_access().nlink -= 1;
+        }
+        Ok(())
+    }
+    fn replace(&self, name: &str, inode: &Arc<dyn Inode>) -> Result<(), Errno> {
+        let target = inode.as_any().downcast_ref::<TmpInode>().ok_or(EXDEV)?;
+        let mut inner = self.inner.exclusive_access();
+        let entry = inner.entries.get_mut(name).ok_or(ENOENT)?;
+        target.inner.exclusive_access().nlink += 1;
+        let old = core::mem::replace(entry, inode.clone());
+        if let Some(old) = old.as_any().downcast_ref::<TmpInode>() {
+            old.inner.exclusive_access().nlink -= 1;
+        }
+        Ok(())
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
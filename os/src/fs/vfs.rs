@@ -0,0 +1,214 @@
+//! The virtual file system: what the kernel needs from a file system,
+//! whichever one it is. A file system is a `SuperBlock`, whose root and
+//! everything below it are `Inode`s, and a path resolves to a `Dentry`,
+//! the inode together with where in the namespace it was found.
+
+use super::mount::{mounted_at, mounted_inside};
+use super::{File, OpenFlags, S_IFDIR, S_IFREG};
+use crate::syscall::{
+    Errno, EBUSY, EEXIST, EINVAL, EISDIR, ENOENT, ENOTDIR, ENOTEMPTY, EPERM, EXDEV,
+};
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::any::Any;
+
+/// Longest path component of any file system
+pub const NAME_MAX: usize = 255;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    File = 1,
+    Dir = 2,
+}
+
+/// A mounted file system
+pub trait SuperBlock: Send + Sync {
+    /// The name which mount takes for this kind of file system
+    fn fs_type(&self) -> &'static str;
+    fn root(&self) -> Arc<dyn Inode>;
+}
+
+/// A file or directory of some file system. It is freed once it has no
+/// names left and the last `Arc` to it, such as the one of an `OSInode`
+/// which has it open, is dropped.
+pub trait Inode: Any + Send + Sync {
+    fn kind(&self) -> NodeKind;
+    /// File type and permission bits, as in `FileStat::mode`
+    fn mode(&self) -> u32 {
+        match self.kind() {
+            NodeKind::File => S_IFREG | 0o755,
+            NodeKind::Dir => S_IFDIR | 0o755,
+        }
+    }
+    fn size(&self) -> usize;
+    /// Names of the inode in the directories of its file system
+    fn nlink(&self) -> usize;
+    /// Read from `offset` on, return how much was read.
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize;
+    /// Write at `offset`, growing the file if needed. Return how much was
+    /// written.
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize;
+    /// Truncate a file to length 0.
+    fn clear(&self);
+    /// The entry `name` of a directory.
+    fn lookup(&self, name: &str) -> Option<Arc<dyn Inode>>;
+    /// Add an empty file or directory `name` to a directory.
+    fn create(&self, name: &str, kind: NodeKind) -> Result<Arc<dyn Inode>, Errno>;
+    /// The names of the entries of a directory.
+    fn list(&self) -> Vec<String>;
+    /// Add the entry `name` for `inode`, which is of the same file system,
+    /// to a directory which lacks it.
+    fn link(&self, name: &str, inode: &Arc<dyn Inode>) -> Result<(), Errno>;
+    /// Remove the entry `name` of a directory, even if it is a directory
+    /// with entries of its own.
+    fn unlink(&self, name: &str) -> Result<(), Errno>;
+    /// Point the entry `name` of a directory at `inode`, which is of the
+    /// same file system, instead of what it names now. On failure the entry
+    /// is left as it was.
+    fn replace(&self, _name: &str, _inode: &Arc<dyn Inode>) -> Result<(), Errno> {
+        Err(EPERM)
+    }
+    /// For a file system to find its own kind of inode in `link`.
+    fn as_any(&self) -> &dyn Any;
+    /// Open a device, which is read and written by its own `File` rather
+    /// than through `read_at` and `write_at`. None for everything else.
+    fn open_device(&self, _flags: OpenFlags) -> Option<Arc<dyn File + Send + Sync>> {
+        None
+    }
+}
+
+/// An inode found at an absolute path
+#[derive(Clone)]
+pub struct Dentry {
+    pub inode: Arc<dyn Inode>,
+    /// absolute path without `.` or `..`
+    pub path: String,
+    /// the mount which the inode is in
+    pub dev: usize,
+}
+
+fn same_inode(a: &Arc<dyn Inode>, b: &Arc<dyn Inode>) -> bool {
+    Arc::as_ptr(a) as *const u8 == Arc::as_ptr(b) as *const u8
+}
+
+impl Dentry {
+    /// The path of the entry `name` of this directory.
+    fn child_path(&self, name: &str) -> String {
+        let mut path = self.path.clone();
+        if !path.ends_with('/') {
+            path.push('/');
+        }
+        path.push_str(name);
+        path
+    }
+    /// The entry `name` of this directory, which is the root of the file
+    /// system mounted there if there is one.
+    pub fn lookup(&self, name: &str) -> Option<Dentry> {
+        let path = self.child_path(name);
+        let (inode, dev) = match mounted_at(&path) {
+            Some(mounted) => mounted,
+            None => (self.inode.lookup(name)?, self.dev),
+        };
+        Some(Dentry { inode, path, dev })
+    }
+    /// Add an empty file or directory `name` to this directory.
+    pub fn create(&self, name: &str, kind: NodeKind) -> Result<Dentry, Errno> {
+        let path = self.child_path(name);
+        // a mount point is taken even if the directory below lacks it
+        if mounted_at(&path).is_some() {
+            return Err(EEXIST);
+        }
+        let inode = self.inode.create(name, kind)?;
+        Ok(Dentry {
+            inode,
+            path,
+            dev: self.dev,
+        })
+    }
+    /// The entry `name` of this directory, which is not a mount point.
+    fn entry(&self, name: &str) -> Result<Dentry, Errno> {
+        let entry = self.lookup(name).ok_or(ENOENT)?;
+        if entry.dev != self.dev {
+            return Err(EBUSY);
+        }
+        Ok(entry)
+    }
+    /// Give `target` the name `name` in this directory too. Directories
+    /// have just one name.
+    pub fn link(&self, name: &str, target: &Dentry) -> Result<(), Errno> {
+        if target.dev != self.dev {
+            return Err(EXDEV);
+        }
+        if target.is_dir() {
+            return Err(EPERM);
+        }
+        if self.lookup(name).is_some() {
+            return Err(EEXIST);
+        }
+        self.inode.link(name, &target.inode)
+    }
+    /// Fail unless the entry `name` of this directory is a file, or an
+    /// empty directory if `dir`, which can be removed.
+    fn check_removable(&self, name: &str, dir: bool) -> Result<(), Errno> {
+        let entry = self.entry(name)?;
+        match (dir, entry.is_dir()) {
+            (true, false) => return Err(ENOTDIR),
+            (false, true) => return Err(EISDIR),
+            _ => {}
+        }
+        if dir && !entry.inode.list().is_empty() {
+            return Err(ENOTEMPTY);
+        }
+        Ok(())
+    }
+    /// Remove the entry `name` of this directory, which is a file, or an
+    /// empty directory if `dir`.
+    pub fn unlink(&self, name: &str, dir: bool) -> Result<(), Errno> {
+        self.check_removable(name, dir)?;
+        self.inode.unlink(name)
+    }
+    /// Move the entry `name` of this directory to `new_name` in `new_dir`,
+    /// replacing what is there unless `noreplace`. A directory can only
+    /// replace an empty directory, and a file only a file. What is replaced
+    /// stays if the new entry can't be made.
+    pub fn rename(
+        &self,
+        name: &str,
+        new_dir: &Dentry,
+        new_name: &str,
+        noreplace: bool,
+    ) -> Result<(), Errno> {
+        let entry = self.entry(name)?;
+        if new_dir.dev != self.dev {
+            return Err(EXDEV);
+        }
+        // a directory can't be moved into itself, or away from under the
+        // file systems mounted in it
+        let new_path = new_dir.child_path(new_name);
+        if entry.is_dir() {
+            if new_path.starts_with(&entry.path) && new_path[entry.path.len()..].starts_with('/') {
+                return Err(EINVAL);
+            }
+            if mounted_inside(&entry.path) {
+                return Err(EBUSY);
+            }
+        }
+        if let Some(old) = new_dir.lookup(new_name) {
+            if noreplace {
+                return Err(EEXIST);
+            }
+            if same_inode(&old.inode, &entry.inode) {
+                return Ok(());
+            }
+            new_dir.check_removable(new_name, entry.is_dir())?;
+            new_dir.inode.replace(new_name, &entry.inode)?;
+        } else {
+            new_dir.inode.link(new_name, &entry.inode)?;
+        }
+        self.inode.unlink(name)
+    }
+    pub fn is_dir(&self) -> bool {
+        self.inode.kind() == NodeKind::Dir
+    }
+}
@@ -4,10 +4,12 @@
 #![feature(alloc_error_handler)]
 
 use crate::{
-    syscall::syscall,
+    syscall::{syscall, EINTR},
     task::{
-        check_signals_error_of_current, current_add_signal, exit_current_and_run_next,
-        handle_signals, suspend_current_and_run_next, SignalFlags,
+        check_signals_error_of_current, current_add_signal, current_count_page_fault,
+        current_dump_core, current_set_interrupted_syscall, current_trap_enter,
+        current_trap_exit, exit_current_and_run_next, handle_signals, is_step_trap,
+        preempt_current_and_run_next, signaled_status, SignalFlags, SignalInfo, WCOREFLAG,
     },
 };
 // use polyhal::api::ArchInterface;
@@ -21,12 +23,14 @@ extern crate bitflags;
 
 #[macro_use]
 mod console;
+mod cmdline;
 mod config;
 mod drivers;
 mod fs;
 mod lang_items;
 mod logging;
 mod mm;
+mod random;
 mod sync;
 mod syscall;
 mod task;
@@ -37,8 +41,16 @@ pub struct ArchInterfaceImpl;
 #[polyhal::arch_interrupt]
 fn kernel_interrupt(ctx: &mut TrapFrame, trap_type: TrapType) {
     // trace!("trap_type @ {:x?} {:#x?}", trap_type, ctx);
+    current_trap_enter();
+    random::add_trap_entropy();
     match trap_type {
-        Breakpoint => return,
+        Breakpoint => {
+            // stops a traced task for its debugger, kills any other one
+            current_add_signal(
+                SignalFlags::SIGTRAP,
+                SignalInfo::kernel(SignalFlags::SIGTRAP.signum(), ctx[TrapFrameArgs::SEPC]),
+            );
+        }
         SysCall => {
             // jump to next instruction anyway
             ctx.syscall_ok();
@@ -46,11 +58,15 @@ fn kernel_interrupt(ctx: &mut TrapFrame, trap_type: TrapType) {
             // get system call return value
             // info!("syscall: {}", ctx[TrapFrameArgs::SYSCALL]);
 
-            let result = syscall(ctx[TrapFrameArgs::SYSCALL], [args[0], args[1], args[2]]);
+            let syscall_id = ctx[TrapFrameArgs::SYSCALL];
+            let result = syscall(syscall_id, args);
             // cx is changed during sys_exec, so we have to call it again
             ctx[TrapFrameArgs::RET] = result as usize;
+            // a SA_RESTART handler may run the interrupted syscall again
+            let interrupted = result == EINTR.as_ret();
+            current_set_interrupted_syscall(interrupted.then_some((syscall_id, args[0])));
         }
-        StorePageFault(_paddr) | LoadPageFault(_paddr) | InstructionPageFault(_paddr) => {
+        StorePageFault(paddr) | LoadPageFault(paddr) | InstructionPageFault(paddr) => {
             /*
             println!(
                 "[kernel] {:?} in application, bad addr = {:#x}, bad instruction = {:#x}, kernel killed it.",
@@ -59,13 +75,29 @@ fn kernel_interrupt(ctx: &mut TrapFrame, trap_type: TrapType) {
                 current_trap_cx().sepc,
             );
             */
-            current_add_signal(SignalFlags::SIGSEGV);
+            current_count_page_fault();
+            current_add_signal(
+                SignalFlags::SIGSEGV,
+                SignalInfo::kernel(SignalFlags::SIGSEGV.signum(), paddr),
+            );
         }
         IllegalInstruction(_) => {
-            current_add_signal(SignalFlags::SIGILL);
+            current_add_signal(
+                SignalFlags::SIGILL,
+                SignalInfo::kernel(SignalFlags::SIGILL.signum(), ctx[TrapFrameArgs::SEPC]),
+            );
         }
         Time => {
-            suspend_current_and_run_next();
+            random::add_interrupt_entropy();
+            mm::update_vdso();
+            preempt_current_and_run_next();
+        }
+        // the end of a single step which is not reported as a breakpoint
+        _ if is_step_trap(ctx) => {
+            current_add_signal(
+                SignalFlags::SIGTRAP,
+                SignalInfo::kernel(SignalFlags::SIGTRAP.signum(), ctx[TrapFrameArgs::SEPC]),
+            );
         }
         _ => {
             warn!("unsuspended trap type: {:?}", trap_type);
@@ -76,10 +108,17 @@ fn kernel_interrupt(ctx: &mut TrapFrame, trap_type: TrapType) {
     handle_signals();
 
     // check error signals (if error then exit)
-    if let Some((errno, msg)) = check_signals_error_of_current() {
-        println!("[kernel] {}", msg);
-        exit_current_and_run_next(errno);
+    if let Some((signum, msg)) = check_signals_error_of_current() {
+        let mut status = signaled_status(signum as usize);
+        if current_dump_core(signum as usize) {
+            println!("[kernel] {} (core dumped)", msg);
+            status |= WCOREFLAG;
+        } else {
+            println!("[kernel] {}", msg);
+        }
+        exit_current_and_run_next(status);
     }
+    current_trap_exit();
 }
 
 #[polyhal::arch_entry]
@@ -90,8 +129,11 @@ fn main(hartid: usize) {
     }
     println!("[kernel] Hello, world!");
     mm::init_heap();
-    logging::init(Some("info"));
+    logging::init(Some(cmdline::option("log").unwrap_or("info")));
     println!("init logging");
+    if !cmdline::cmdline().is_empty() {
+        println!("[kernel] command line: {}", cmdline::cmdline());
+    }
     // polyhal::init_interrupt(); done in polyhal::CPU::rust_main()
 
     polyhal::common::init(&PageAllocImpl);
@@ -99,6 +141,8 @@ fn main(hartid: usize) {
         println!("init memory region {:#x} - {:#x}", start, start + size);
         mm::init_frame_allocator(start, start + size);
     });
+    mm::init_vdso();
+    random::init_random();
 
     fs::list_apps();
     task::add_initproc();
@@ -40,6 +40,7 @@ trait FrameAllocator {
 }
 
 pub struct StackFrameAllocator {
+    start: usize,
     current: usize,
     end: usize,
     recycled: Vec<usize>,
@@ -47,14 +48,23 @@ pub struct StackFrameAllocator {
 
 impl StackFrameAllocator {
     pub fn init(&mut self, l: PhysPage, r: PhysPage) {
+        self.start = l.as_num();
         self.current = l.as_num();
         self.end = r.as_num();
         println!("last {} Physical Frames.", self.end - self.current);
     }
+    /// (all frames, frames which are not allocated)
+    pub fn stats(&self) -> (usize, usize) {
+        (
+            self.end - self.start,
+            self.end - self.current + self.recycled.len(),
+        )
+    }
 }
 impl FrameAllocator for StackFrameAllocator {
     fn new() -> Self {
         Self {
+            start: 0,
             current: 0,
             end: 0,
             recycled: Vec::new(),
@@ -124,6 +134,11 @@ pub fn frame_alloc_persist() -> Option<PhysPage> {
         .inspect(|x| x.drop_clear())
 }
 
+/// (all frames, frames which are not allocated)
+pub fn frame_stats() -> (usize, usize) {
+    FRAME_ALLOCATOR.exclusive_access().stats()
+}
+
 pub fn frame_dealloc(ppn: PhysPage) {
     FRAME_ALLOCATOR.exclusive_access().dealloc(ppn);
 }
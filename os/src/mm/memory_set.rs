@@ -1,15 +1,46 @@
+use super::vdso::vdso_page;
 use super::vpn_range::VPNRange;
 use super::{frame_alloc, FrameTracker};
-use crate::config::{PAGE_SIZE, USER_STACK_SIZE};
+use crate::config::{PAGE_SIZE, USER_MMAP_BASE, USER_SPACE_END, VDSO_BASE};
 use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
 use polyhal::pagetable::{MappingFlags, MappingSize, PageTable, PageTableWrapper};
 use polyhal::addr::{PhysPage, VirtAddr, VirtPage};
 use log::*;
+/// Why `MemorySet::from_elf` could not load an image
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ElfError {
+    /// not an ELF file, or headers pointing outside of it
+    Malformed,
+    /// the address space would exceed its size limit
+    TooLarge,
+    /// there are not enough free frames for it
+    NoMemory,
+}
+
+/// Name of the note section which user_lib puts in native programs, any
+/// other ELF file is run as a Linux program
+const NATIVE_ABI_NOTE: &str = ".note.rcore.abi";
+
+/// What `MemorySet::from_elf` learned about an image, for the initial stack
+#[derive(Debug, Clone, Copy)]
+pub struct ElfInfo {
+    pub entry: usize,
+    /// address of the loaded program headers, 0 if they are not loaded
+    pub phdr: usize,
+    pub phent: usize,
+    pub phnum: usize,
+    /// whether the image carries `NATIVE_ABI_NOTE`
+    pub native: bool,
+}
+
 pub struct MemorySet {
     page_table: Arc<PageTableWrapper>,
     areas: Vec<MapArea>,
+    // start of the heap which brk moves the end of, 0 if there is none
+    heap_start: usize,
+    brk: usize,
 }
 
 impl MemorySet {
@@ -17,35 +48,77 @@ impl MemorySet {
         Self {
             page_table: Arc::new(PageTableWrapper::alloc()),
             areas: Vec::new(),
+            heap_start: 0,
+            brk: 0,
         }
     }
     pub fn token(&self) -> PageTable {
         self.page_table.0
     }
-    fn push(&mut self, mut map_area: MapArea, data: Option<&[u8]>) {
-        map_area.map(&self.page_table);
+    /// Map `map_area` and copy `data` to it, returning false with nothing
+    /// added if there are not enough free frames.
+    fn push(&mut self, map_area: MapArea, data: Option<&[u8]>) -> bool {
+        self.push_at(map_area, data, 0)
+    }
+    /// Like `push`, with `data` starting `offset` bytes into the first page.
+    fn push_at(&mut self, mut map_area: MapArea, data: Option<&[u8]>, offset: usize) -> bool {
+        if !map_area.map(&self.page_table) {
+            return false;
+        }
         if let Some(data) = data {
-            map_area.copy_data(&self.page_table, data);
+            map_area.copy_data(&self.page_table, data, offset);
         }
         self.areas.push(map_area);
+        true
     }
-    /// Include sections in elf and trampoline and TrapContext and user stack,
-    /// also returns user_sp and entry point.
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
+    /// Include sections in elf, the user stack and the vDSO page,
+    /// also returns user_sp and what the stack setup needs to know.
+    /// The heap starts a guard page above the stack.
+    /// The address space may not grow beyond `max_size` bytes.
+    pub fn from_elf(
+        elf_data: &[u8],
+        stack_size: usize,
+        max_size: usize,
+    ) -> Result<(Self, usize, ElfInfo), ElfError> {
         trace!("os::mm::MemorySet::from_elf");
         let mut memory_set = Self::new_bare();
         // map program headers of elf, with U flag
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
+        let elf = xmas_elf::ElfFile::new(elf_data).map_err(|_| ElfError::Malformed)?;
         let elf_header = elf.header;
         let magic = elf_header.pt1.magic;
-        assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
+        if magic != [0x7f, 0x45, 0x4c, 0x46] {
+            return Err(ElfError::Malformed);
+        }
         let ph_count = elf_header.pt2.ph_count();
+        let ph_offset = elf_header.pt2.ph_offset() as usize;
+        let mut phdr = 0;
+        let entry = elf_header.pt2.entry_point() as usize;
+        let mut entry_loaded = false;
         let mut max_end_vpn = VirtPage::new(0);
         for i in 0..ph_count {
-            let ph = elf.program_header(i).unwrap();
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
+            let ph = elf.program_header(i).map_err(|_| ElfError::Malformed)?;
+            if ph.get_type() == Ok(xmas_elf::program::Type::Load) {
+                // the file part must lie in the file and fit in the segment
+                let file_end = ph.offset().checked_add(ph.file_size());
+                let mem_end = ph.virtual_addr().checked_add(ph.mem_size());
+                let (file_end, mem_end) = match (file_end, mem_end) {
+                    (Some(file_end), Some(mem_end))
+                        if file_end as usize <= elf_data.len()
+                            && ph.file_size() <= ph.mem_size() =>
+                    {
+                        (file_end, mem_end)
+                    }
+                    _ => return Err(ElfError::Malformed),
+                };
+                // user space above the null page and below the vDSO, the
+                // stack and the heap are placed above the segments
+                let (start, end) = (ph.virtual_addr() as usize, mem_end as usize);
+                if start < PAGE_SIZE || end > VDSO_BASE {
+                    return Err(ElfError::Malformed);
+                }
+                entry_loaded |= (start..end).contains(&entry);
                 let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
-                let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
+                let end_va: VirtAddr = (mem_end as usize).into();
                 let mut map_perm = MapPermission::U;
                 let ph_flags = ph.flags();
                 if ph_flags.is_read() {
@@ -58,42 +131,241 @@ impl MemorySet {
                     map_perm |= MapPermission::X;
                 }
                 let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
-                max_end_vpn = map_area.vpn_range.get_end();
-                memory_set.push(
+                // segments need not come in order of their addresses
+                if map_area.vpn_range.get_end() > max_end_vpn {
+                    max_end_vpn = map_area.vpn_range.get_end();
+                }
+                if memory_set.size() + map_area.size() > max_size {
+                    return Err(ElfError::TooLarge);
+                }
+                // the program headers are loaded if a segment covers them
+                let offset = ph.offset() as usize;
+                if (offset..file_end as usize).contains(&ph_offset) {
+                    phdr = ph.virtual_addr() as usize + ph_offset - offset;
+                }
+                if !memory_set.push_at(
                     map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                );
+                    Some(&elf.input[offset..file_end as usize]),
+                    ph.virtual_addr() as usize % PAGE_SIZE,
+                ) {
+                    return Err(ElfError::NoMemory);
+                }
             }
         }
+        if !entry_loaded {
+            return Err(ElfError::Malformed);
+        }
         // map user stack with U flags
         let max_end_va: VirtAddr = max_end_vpn.into();
         let mut user_stack_bottom: usize = max_end_va.into();
         // guard page
         user_stack_bottom += PAGE_SIZE;
-        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
+        let user_stack_top = user_stack_bottom + stack_size;
+        // the stack and a guard page below the heap must fit under the vDSO
+        if user_stack_top + PAGE_SIZE > VDSO_BASE {
+            return Err(ElfError::Malformed);
+        }
+        let stack_area = MapArea::new(
+            user_stack_bottom.into(),
+            user_stack_top.into(),
+            MapType::Framed,
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        );
+        if memory_set.size() + stack_area.size() > max_size {
+            return Err(ElfError::TooLarge);
+        }
+        if !memory_set.push(stack_area, None) {
+            return Err(ElfError::NoMemory);
+        }
         memory_set.push(
             MapArea::new(
-                user_stack_bottom.into(),
-                user_stack_top.into(),
-                MapType::Framed,
-                MapPermission::R | MapPermission::W | MapPermission::U,
+                VDSO_BASE.into(),
+                (VDSO_BASE + PAGE_SIZE).into(),
+                MapType::Shared(vdso_page()),
+                MapPermission::R | MapPermission::X | MapPermission::U,
             ),
             None,
         );
+        memory_set.heap_start = user_stack_top + PAGE_SIZE;
+        memory_set.brk = memory_set.heap_start;
+        let info = ElfInfo {
+            entry,
+            phdr,
+            phent: elf_header.pt2.ph_entry_size() as usize,
+            phnum: ph_count as usize,
+            native: elf.find_section_by_name(NATIVE_ABI_NOTE).is_some(),
+        };
         // map TrapContext
-        (
-            memory_set,
-            user_stack_top,
-            elf.header.pt2.entry_point() as usize,
-        )
+        Ok((memory_set, user_stack_top, info))
+    }
+    /// Move the end of the heap to `brk`, or just report it if `brk` is 0.
+    /// Return the new end, which stays the old one if the heap cannot end
+    /// at `brk` below the vDSO, within `max_size` bytes of address space and
+    /// the free frames.
+    pub fn set_brk(&mut self, brk: usize, max_size: usize) -> usize {
+        if brk < self.heap_start || brk > VDSO_BASE {
+            return self.brk;
+        }
+        let start_vpn: VirtPage = VirtAddr::from(self.heap_start).floor().into();
+        let old_end: VirtPage = VirtAddr::from(self.brk).ceil().into();
+        let new_end: VirtPage = VirtAddr::from(brk).ceil().into();
+        if new_end > old_end {
+            let grow = page_bytes(old_end, new_end);
+            if self.size() + grow > max_size || !self.is_free(old_end, new_end) {
+                return self.brk;
+            }
+        }
+        // the heap is the area starting at heap_start, if it has pages
+        match self
+            .areas
+            .iter()
+            .position(|area| area.vpn_range.get_start() == start_vpn)
+        {
+            Some(idx) if new_end == start_vpn => {
+                self.areas.remove(idx).unmap(&self.page_table);
+            }
+            Some(idx) => {
+                if !self.areas[idx].resize(&self.page_table, new_end) {
+                    return self.brk;
+                }
+            }
+            None if new_end > start_vpn => {
+                let perm = MapPermission::R | MapPermission::W | MapPermission::U;
+                let map_area =
+                    MapArea::new(self.heap_start.into(), brk.into(), MapType::Framed, perm);
+                if !self.push(map_area, None) {
+                    return self.brk;
+                }
+            }
+            None => {}
+        }
+        self.brk = brk;
+        brk
+    }
+    /// Map `len` bytes of zeroed pages with `perm` at `addr`, replacing what
+    /// was mapped there. Without `addr`, the lowest free range above
+    /// USER_MMAP_BASE is used. Return the start of the mapping, or None if it
+    /// does not fit in user space, within `max_size` bytes of address space
+    /// or in the free frames. Nothing is replaced then.
+    pub fn mmap(
+        &mut self,
+        addr: Option<usize>,
+        len: usize,
+        perm: MapPermission,
+        max_size: usize,
+    ) -> Option<usize> {
+        let size = len.checked_add(PAGE_SIZE - 1)? / PAGE_SIZE * PAGE_SIZE;
+        let (start, replaced) = match addr {
+            Some(addr) => {
+                let end = addr.checked_add(size)?;
+                if addr < PAGE_SIZE || end > USER_SPACE_END {
+                    return None;
+                }
+                (addr, self.mapped_bytes(addr.into(), end.into()))
+            }
+            None => (self.find_free(size / PAGE_SIZE)?, 0),
+        };
+        if self.size() - replaced + size > max_size {
+            return None;
+        }
+        let mut map_area = MapArea::new(
+            start.into(),
+            (start + size).into(),
+            MapType::Framed,
+            perm | MapPermission::U,
+        );
+        // take the frames before the old mapping is gone
+        if !map_area.alloc_frames() {
+            return None;
+        }
+        if addr.is_some() {
+            self.munmap(start, size);
+        }
+        self.push(map_area, None);
+        Some(start)
+    }
+    /// Unmap the pages in `[addr, addr + len)`, splitting the areas which
+    /// are only partly in it.
+    pub fn munmap(&mut self, addr: usize, len: usize) {
+        let start: VirtPage = VirtAddr::from(addr).floor().into();
+        let end: VirtPage = VirtAddr::from(addr + len).ceil().into();
+        let mut kept = Vec::new();
+        for mut area in core::mem::take(&mut self.areas) {
+            let area_start = area.vpn_range.get_start();
+            let area_end = area.vpn_range.get_end();
+            if area_end <= start || end <= area_start {
+                kept.push(area);
+                continue;
+            }
+            // the parts before and after the range stay mapped
+            let tail = if end < area_end {
+                Some(area.split_off(end))
+            } else {
+                None
+            };
+            if area_start < start {
+                let middle = area.split_off(start);
+                kept.push(area);
+                area = middle;
+            }
+            area.unmap(&self.page_table);
+            kept.extend(tail);
+        }
+        self.areas = kept;
     }
-    pub fn from_existed_user(user_space: &MemorySet) -> MemorySet {
+    /// Whether no page in `[start, end)` is mapped.
+    fn is_free(&self, start: VirtPage, end: VirtPage) -> bool {
+        self.areas
+            .iter()
+            .all(|area| area.vpn_range.get_end() <= start || end <= area.vpn_range.get_start())
+    }
+    /// Bytes of the pages in `[start, end)` which are mapped.
+    fn mapped_bytes(&self, start: VirtAddr, end: VirtAddr) -> usize {
+        let (start, end): (VirtPage, VirtPage) = (start.floor().into(), end.ceil().into());
+        self.areas
+            .iter()
+            .map(|area| {
+                let from = area.vpn_range.get_start().max(start);
+                let to = area.vpn_range.get_end().min(end);
+                if from < to {
+                    page_bytes(from, to)
+                } else {
+                    0
+                }
+            })
+            .sum()
+    }
+    /// The lowest address above USER_MMAP_BASE with `pages` free pages
+    /// below USER_SPACE_END.
+    fn find_free(&self, pages: usize) -> Option<usize> {
+        let mut ends: Vec<VirtPage> = self
+            .areas
+            .iter()
+            .map(|area| area.vpn_range.get_end())
+            .collect();
+        let base: VirtPage = VirtAddr::from(USER_MMAP_BASE).floor().into();
+        let limit: VirtPage = VirtAddr::from(USER_SPACE_END).floor().into();
+        ends.push(base);
+        ends.sort();
+        ends.into_iter()
+            .filter(|start| base <= *start && *start <= limit)
+            .filter(|start| page_bytes(*start, limit) / PAGE_SIZE >= pages)
+            .find(|start| self.is_free(*start, *start + pages))
+            .map(|start| VirtAddr::from(start).into())
+    }
+    /// A copy of `user_space`, or None if there are not enough free frames.
+    pub fn from_existed_user(user_space: &MemorySet) -> Option<MemorySet> {
         trace!("os::mm::MemorySet::from_existed_user");
         let mut memory_set = Self::new_bare();
         // copy data sections/trap_context/user_stack
         for area in user_space.areas.iter() {
             let new_area = MapArea::from_another(area);
-            memory_set.push(new_area, None);
+            if !memory_set.push(new_area, None) {
+                return None;
+            }
+            if area.map_type != MapType::Framed {
+                continue;
+            }
             // copy data from another space
             for vpn in area.vpn_range {
                 let src_ppn = user_space.translate(vpn).unwrap().0;
@@ -101,7 +373,9 @@ impl MemorySet {
                 dst_ppn.get_buffer().copy_from_slice(src_ppn.get_buffer())
             }
         }
-        memory_set
+        memory_set.heap_start = user_space.heap_start;
+        memory_set.brk = user_space.brk;
+        Some(memory_set)
     }
     pub fn activate(&self) {
         self.page_table.change();
@@ -111,6 +385,76 @@ impl MemorySet {
             .translate(vpn.into())
             .map(|(pa, flags)| (pa.into(), flags))
     }
+    /// Whether `[start, start + len)` is mapped in user space with `flags`.
+    pub fn check_user_range(&self, start: usize, len: usize, flags: MappingFlags) -> bool {
+        let start_vpn: VirtPage = VirtAddr::from(start).floor().into();
+        let end_vpn: VirtPage = VirtAddr::from(start + len).ceil().into();
+        VPNRange::new(start_vpn, end_vpn).into_iter().all(|vpn| {
+            self.translate(vpn)
+                .map_or(false, |(_, f)| f.contains(flags | MappingFlags::U))
+        })
+    }
+    /// Copy the user memory at `addr` into `buf` whatever the permission
+    /// of its pages, also when this space is not the active one.
+    /// Return false if part of it is not mapped.
+    pub fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> bool {
+        self.access_bytes(addr, buf.len(), |page, offset| {
+            buf[offset..offset + page.len()].copy_from_slice(page);
+        })
+    }
+    /// Copy `data` to the user memory at `addr` like `read_bytes`, which
+    /// lets a debugger patch read-only code.
+    pub fn write_bytes(&self, addr: usize, data: &[u8]) -> bool {
+        // a shared page such as the vDSO would change in every address space
+        let end = match addr.checked_add(data.len()) {
+            Some(end) => end,
+            None => return false,
+        };
+        let start_vpn: VirtPage = VirtAddr::from(addr).floor().into();
+        let end_vpn: VirtPage = VirtAddr::from(end).ceil().into();
+        if self.areas.iter().any(|area| {
+            area.map_type != MapType::Framed
+                && area.vpn_range.get_start() < end_vpn
+                && start_vpn < area.vpn_range.get_end()
+        }) {
+            return false;
+        }
+        self.access_bytes(addr, data.len(), |page, offset| {
+            page.copy_from_slice(&data[offset..offset + page.len()]);
+        })
+    }
+    /// Call `f` with each page-sized piece of `[addr, addr + len)` and its
+    /// offset in the range, once all of it is known to be mapped.
+    fn access_bytes(&self, addr: usize, len: usize, mut f: impl FnMut(&mut [u8], usize)) -> bool {
+        if addr.checked_add(len).is_none()
+            || !self.check_user_range(addr, len, MappingFlags::empty())
+        {
+            return false;
+        }
+        let mut offset = 0;
+        while offset < len {
+            let va = addr + offset;
+            let vpn: VirtPage = VirtAddr::from(va).floor().into();
+            let page_offset = va % PAGE_SIZE;
+            let n = (PAGE_SIZE - page_offset).min(len - offset);
+            let ppn = self.translate(vpn).unwrap().0;
+            f(&mut ppn.get_buffer()[page_offset..page_offset + n], offset);
+            offset += n;
+        }
+        true
+    }
+    /// Start, end and permission of each mapped area, in mapping order.
+    pub fn user_areas(&self) -> impl Iterator<Item = (usize, usize, MappingFlags)> + '_ {
+        self.areas.iter().map(|area| {
+            let start: VirtAddr = area.vpn_range.get_start().into();
+            let end: VirtAddr = area.vpn_range.get_end().into();
+            (start.into(), end.into(), area.map_perm.into())
+        })
+    }
+    /// Bytes mapped in user space.
+    pub fn size(&self) -> usize {
+        self.areas.iter().map(|area| area.size()).sum()
+    }
     pub fn recycle_data_pages(&mut self) {
         //*self = Self::new_bare(); 
         self.areas.clear();
@@ -148,52 +492,120 @@ impl MapArea {
             map_perm: another.map_perm,
         }
     }
-    pub fn map(&mut self, page_table: &Arc<PageTableWrapper>) {
+    pub fn size(&self) -> usize {
+        self.vpn_range.into_iter().count() * PAGE_SIZE
+    }
+    /// Take a frame for every page of a framed area which has none yet,
+    /// returning false with none taken if there are not enough.
+    pub fn alloc_frames(&mut self) -> bool {
+        if self.map_type != MapType::Framed {
+            return true;
+        }
+        let mut frames = BTreeMap::new();
+        for vpn in self.vpn_range {
+            if self.data_frames.contains_key(&vpn) {
+                continue;
+            }
+            match frame_alloc() {
+                Some(frame) => frames.insert(vpn, frame),
+                None => return false,
+            };
+        }
+        self.data_frames.append(&mut frames);
+        true
+    }
+    /// Map the pages of the area, returning false with none mapped if there
+    /// are not enough free frames.
+    pub fn map(&mut self, page_table: &Arc<PageTableWrapper>) -> bool {
         trace!("os::mm::memory_set::MapArea::map");
+        if !self.alloc_frames() {
+            return false;
+        }
         for vpn in self.vpn_range {
-            // self.map_one(page_table, vpn);
-            let p_tracker = frame_alloc().expect("can't allocate frame");
-            page_table.map_page(vpn, p_tracker.ppn, self.map_perm.into(), MappingSize::Page4KB);
-            self.data_frames.insert(vpn, p_tracker);
+            let ppn = match self.map_type {
+                MapType::Framed => self.data_frames[&vpn].ppn,
+                MapType::Shared(ppn) => ppn,
+            };
+            page_table.map_page(vpn, ppn, self.map_perm.into(), MappingSize::Page4KB);
         }
+        true
     }
 
     /// Unmap page area
-    #[allow(unused)]
     pub fn unmap(&mut self, page_table: &Arc<PageTableWrapper>) {
         trace!("os::mm::memory_set::MapArea::unmap");
         for vpn in self.vpn_range {
             page_table.unmap_page(vpn);
+            self.data_frames.remove(&vpn);
+        }
+    }
+
+    /// Map or unmap pages at the end so that the area ends at `end`. Return
+    /// false with the area unchanged if there are not enough free frames.
+    pub fn resize(&mut self, page_table: &Arc<PageTableWrapper>, end: VirtPage) -> bool {
+        let start = self.vpn_range.get_start();
+        let old_end = self.vpn_range.get_end();
+        if end > old_end {
+            let mut grown = Self::from_another(self);
+            grown.vpn_range = VPNRange::new(old_end, end);
+            if !grown.map(page_table) {
+                return false;
+            }
+            self.data_frames.append(&mut grown.data_frames);
+        } else {
+            self.split_off(end).unmap(page_table);
         }
+        self.vpn_range = VPNRange::new(start, end);
+        true
     }
 
-    /// data: start-aligned but maybe with shorter length
+    /// Split the area at `at`, returning the pages from `at` on.
+    pub fn split_off(&mut self, at: VirtPage) -> Self {
+        let end = self.vpn_range.get_end();
+        self.vpn_range = VPNRange::new(self.vpn_range.get_start(), at);
+        Self {
+            vpn_range: VPNRange::new(at, end),
+            data_frames: self.data_frames.split_off(&at),
+            map_type: self.map_type,
+            map_perm: self.map_perm,
+        }
+    }
+
+    /// data: starts `offset` bytes into the first page, maybe shorter than
+    /// the area
     /// assume that all frames were cleared before
-    pub fn copy_data(&mut self, page_table: &Arc<PageTableWrapper>, data: &[u8]) {
+    pub fn copy_data(&mut self, page_table: &Arc<PageTableWrapper>, data: &[u8], offset: usize) {
         trace!("os::mm::memory_set::MapArea::copy_data");
         assert_eq!(self.map_type, MapType::Framed);
         let mut start: usize = 0;
+        let mut page_offset = offset;
         let mut current_vpn = self.vpn_range.get_start();
         let len = data.len();
-        loop {
-            let src = &data[start..len.min(start + PAGE_SIZE)];
+        while start < len {
+            let src = &data[start..len.min(start + PAGE_SIZE - page_offset)];
             let dst = &mut PhysPage::from(page_table.translate(current_vpn.into()).unwrap().0)
-                .get_buffer()[..src.len()];
+                .get_buffer()[page_offset..page_offset + src.len()];
             dst.copy_from_slice(src);
-            start += PAGE_SIZE;
-            if start >= len {
-                break;
-            }
+            start += src.len();
+            page_offset = 0;
             // current_vpn.step();
             current_vpn = current_vpn + 1;
         }
     }
 }
 
+/// Bytes in the pages `[start, end)`.
+fn page_bytes(start: VirtPage, end: VirtPage) -> usize {
+    usize::from(VirtAddr::from(end)) - usize::from(VirtAddr::from(start))
+}
+
 #[derive(Copy, Clone, PartialEq, Debug)]
 pub enum MapType {
 //  Identical, not used now
     Framed,
+    /// a single page of a frame owned elsewhere, such as the vDSO, which
+    /// fork shares instead of copying
+    Shared(PhysPage),
 }
 
 bitflags! {
@@ -2,10 +2,14 @@ mod frame_allocator;
 mod heap_allocator;
 mod memory_set;
 mod page_table;
+mod vdso;
 mod vpn_range;
 
 pub use frame_allocator::init_frame_allocator;
-pub use frame_allocator::{frame_alloc, frame_alloc_persist, frame_dealloc, FrameTracker};
+pub use frame_allocator::{
+    frame_alloc, frame_alloc_persist, frame_dealloc, frame_stats, FrameTracker,
+};
 pub use heap_allocator::init_heap;
-pub use memory_set::MemorySet;
-pub use page_table::{translated_byte_buffer, translated_ref, translated_refmut, translated_str};
+pub use memory_set::{ElfError, ElfInfo, MapPermission, MemorySet};
+pub use page_table::{translated_byte_buffer, translated_ref, translated_refmut};
+pub use vdso::{init_vdso, update_vdso};
@@ -0,0 +1,145 @@
+//! The vDSO page, which every user address space maps read-only at
+//! VDSO_BASE. It starts with `VdsoData`, which the kernel brings up to date
+//! on each timer interrupt, and holds code which reads the time counter, so
+//! that user space can tell the time without a syscall. The sigreturn
+//! trampoline of Linux programs lives in it as well.
+//! Unlike the vDSO of Linux it is not an ELF image, so only native programs
+//! are told about it with AT_SYSINFO_EHDR.
+
+use super::{frame_alloc, FrameTracker};
+use crate::config::{SIGRETURN_TRAMPOLINE, VDSO_BASE};
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+use lazy_static::*;
+use polyhal::addr::PhysPage;
+use polyhal::time::Time;
+
+/// The start of the vDSO page, user_lib has the same struct
+#[repr(C)]
+pub struct VdsoData {
+    /// odd while the kernel updates the fields below, readers try again if
+    /// it is odd or changes while they read
+    pub seq: AtomicUsize,
+    /// time counter ticks per second
+    pub freq: usize,
+    /// the time counter at the last update
+    pub counter: usize,
+    /// the kernel clock in microseconds at the last update, the time is
+    /// this offset plus the ticks since `counter`
+    pub offset_us: usize,
+    /// offset in the page of `extern "C" fn() -> usize`, which returns the
+    /// time counter
+    pub read_counter: usize,
+}
+
+/// Where the code of `VdsoData::read_counter` starts
+const READ_COUNTER_OFFSET: usize = 0x100;
+
+#[cfg(target_arch = "riscv64")]
+const READ_COUNTER_CODE: &[u8] = &[
+    0x73, 0x25, 0x10, 0xc0, // rdtime a0
+    0x67, 0x80, 0x00, 0x00, // ret
+];
+#[cfg(target_arch = "aarch64")]
+const READ_COUNTER_CODE: &[u8] = &[
+    0x40, 0xe0, 0x3b, 0xd5, // mrs x0, cntvct_el0
+    0xc0, 0x03, 0x5f, 0xd6, // ret
+];
+#[cfg(target_arch = "loongarch64")]
+const READ_COUNTER_CODE: &[u8] = &[
+    0x04, 0x68, 0x00, 0x00, // rdtime.d $a0, $zero
+    0x20, 0x00, 0x00, 0x4c, // ret
+];
+#[cfg(target_arch = "x86_64")]
+const READ_COUNTER_CODE: &[u8] = &[
+    0x0f, 0x31, // rdtsc
+    0x48, 0xc1, 0xe2, 0x20, // shl rdx, 32
+    0x48, 0x09, 0xd0, // or rax, rdx
+    0xc3, // ret
+];
+
+/// Code at SIGRETURN_TRAMPOLINE: call sigreturn. musl only passes a
+/// restorer of its own where Linux requires one.
+#[cfg(target_arch = "riscv64")]
+const SIGRETURN_CODE: &[u8] = &[
+    0x93, 0x08, 0xb0, 0x08, // li a7, 139
+    0x73, 0x00, 0x00, 0x00, // ecall
+];
+#[cfg(target_arch = "aarch64")]
+const SIGRETURN_CODE: &[u8] = &[
+    0x68, 0x11, 0x80, 0xd2, // mov x8, #139
+    0x01, 0x00, 0x00, 0xd4, // svc #0
+];
+#[cfg(target_arch = "loongarch64")]
+const SIGRETURN_CODE: &[u8] = &[
+    0x0b, 0x2c, 0x82, 0x02, // addi.w $a7, $zero, 139
+    0x00, 0x00, 0x2b, 0x00, // syscall 0
+];
+#[cfg(target_arch = "x86_64")]
+const SIGRETURN_CODE: &[u8] = &[
+    0xb8, 0x8b, 0x00, 0x00, 0x00, // mov eax, 139
+    0x0f, 0x05, // syscall
+];
+
+lazy_static! {
+    static ref VDSO_FRAME: FrameTracker = {
+        let frame = frame_alloc().expect("can't allocate the vDSO page");
+        let page = frame.ppn.get_buffer();
+        page[READ_COUNTER_OFFSET..READ_COUNTER_OFFSET + READ_COUNTER_CODE.len()]
+            .copy_from_slice(READ_COUNTER_CODE);
+        let sigreturn = SIGRETURN_TRAMPOLINE - VDSO_BASE;
+        page[sigreturn..sigreturn + SIGRETURN_CODE.len()].copy_from_slice(SIGRETURN_CODE);
+        frame
+    };
+}
+
+/// The frame which every address space maps at VDSO_BASE.
+pub fn vdso_page() -> PhysPage {
+    VDSO_FRAME.ppn
+}
+
+fn vdso_data() -> &'static mut VdsoData {
+    unsafe { &mut *(vdso_page().get_buffer().as_mut_ptr() as *mut VdsoData) }
+}
+
+/// Fill in the vDSO page and let user mode read the time counter.
+pub fn init_vdso() {
+    allow_user_counter();
+    let data = vdso_data();
+    data.freq = Time::get_freq();
+    data.read_counter = READ_COUNTER_OFFSET;
+    update_vdso();
+}
+
+/// Bring the time in the vDSO up to date.
+pub fn update_vdso() {
+    let data = vdso_data();
+    data.seq.fetch_add(1, Ordering::Relaxed);
+    fence(Ordering::Release);
+    let now = Time::now();
+    data.counter = now.raw();
+    data.offset_us = now.to_usec();
+    data.seq.fetch_add(1, Ordering::Release);
+}
+
+#[cfg(target_arch = "riscv64")]
+fn allow_user_counter() {
+    // scounteren.TM
+    unsafe { core::arch::asm!("csrs scounteren, {}", in(reg) 1 << 1) };
+}
+
+#[cfg(target_arch = "aarch64")]
+fn allow_user_counter() {
+    // CNTKCTL_EL1.EL0VCTEN
+    unsafe {
+        core::arch::asm!(
+            "mrs {0}, cntkctl_el1",
+            "orr {0}, {0}, #2",
+            "msr cntkctl_el1, {0}",
+            out(reg) _,
+        )
+    };
+}
+
+/// rdtime and rdtsc are allowed in user mode unless the kernel turns them off
+#[cfg(any(target_arch = "loongarch64", target_arch = "x86_64"))]
+fn allow_user_counter() {}
@@ -0,0 +1,83 @@
+//! The ChaCha20 stream cipher of Bernstein, used as a random number
+//! generator with a 64-bit block counter and a 64-bit nonce.
+
+/// "expand 32-byte k"
+const CONSTANTS: [u32; 4] = [0x6170_7865, 0x3320_646e, 0x7962_2d32, 0x6b20_6574];
+
+fn quarter_round(x: &mut [u32; 16], a: usize, b: usize, c: usize, d: usize) {
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(16);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(12);
+    x[a] = x[a].wrapping_add(x[b]);
+    x[d] = (x[d] ^ x[a]).rotate_left(8);
+    x[c] = x[c].wrapping_add(x[d]);
+    x[b] = (x[b] ^ x[c]).rotate_left(7);
+}
+
+/// Block `counter` of the key stream of `key` and `nonce`.
+pub fn chacha20_block(key: &[u32; 8], counter: u64, nonce: u64) -> [u32; 16] {
+    let mut state = [0u32; 16];
+    state[..4].copy_from_slice(&CONSTANTS);
+    state[4..12].copy_from_slice(key);
+    state[12] = counter as u32;
+    state[13] = (counter >> 32) as u32;
+    state[14] = nonce as u32;
+    state[15] = (nonce >> 32) as u32;
+    let mut x = state;
+    for _ in 0..10 {
+        quarter_round(&mut x, 0, 4, 8, 12);
+        quarter_round(&mut x, 1, 5, 9, 13);
+        quarter_round(&mut x, 2, 6, 10, 14);
+        quarter_round(&mut x, 3, 7, 11, 15);
+        quarter_round(&mut x, 0, 5, 10, 15);
+        quarter_round(&mut x, 1, 6, 11, 12);
+        quarter_round(&mut x, 2, 7, 8, 13);
+        quarter_round(&mut x, 3, 4, 9, 14);
+    }
+    for (word, input) in x.iter_mut().zip(state) {
+        *word = word.wrapping_add(input);
+    }
+    x
+}
+
+/// A generator which hands out the key stream of a key, and replaces the
+/// key after each request, so that bytes already handed out cannot be
+/// computed again from the state.
+pub struct ChaCha20Rng {
+    key: [u32; 8],
+    counter: u64,
+}
+
+impl ChaCha20Rng {
+    pub fn new() -> Self {
+        Self {
+            key: [0; 8],
+            counter: 0,
+        }
+    }
+    fn next_block(&mut self) -> [u32; 16] {
+        let block = chacha20_block(&self.key, self.counter, 0);
+        self.counter = self.counter.wrapping_add(1);
+        block
+    }
+    /// Hash `seed` into the key, two words a block.
+    pub fn reseed(&mut self, seed: &[u64]) {
+        for pair in seed.chunks(2) {
+            let nonce = pair.get(1).copied().unwrap_or(0);
+            let block = chacha20_block(&self.key, pair[0], nonce);
+            self.key.copy_from_slice(&block[..8]);
+        }
+        self.counter = 0;
+    }
+    pub fn fill(&mut self, buf: &mut [u8]) {
+        for chunk in buf.chunks_mut(64) {
+            let block = self.next_block();
+            for (bytes, word) in chunk.chunks_mut(4).zip(block) {
+                bytes.copy_from_slice(&word.to_le_bytes()[..bytes.len()]);
+            }
+        }
+        let block = self.next_block();
+        self.key.copy_from_slice(&block[..8]);
+    }
+}
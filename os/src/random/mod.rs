@@ -0,0 +1,124 @@
+//! The kernel entropy pool and random number generator. Samples of the time
+//! counter come in at every trap but only count at interrupts, whose timing
+//! depends on the world outside, and bytes come from a virtio-rng device if
+//! there is one. They are mixed into the pool, which a ChaCha20
+//! generator is reseeded from once enough entropy has come in.
+//! getrandom, /dev/random, /dev/urandom and AT_RANDOM read the generator.
+
+mod chacha;
+
+use crate::drivers::RNG_DEVICE;
+use crate::sync::UPSafeCell;
+use chacha::ChaCha20Rng;
+use lazy_static::*;
+use log::info;
+use polyhal::time::Time;
+
+/// Words in the pool
+const POOL_WORDS: usize = 8;
+// entropy credited for each sample, in eighths of a bit
+const INTERRUPT_CREDIT: usize = 8;
+const DEVICE_BYTE_CREDIT: usize = 64;
+/// The generator is reseeded once the pool holds 256 bits
+const RESEED_CREDIT: usize = 256 * 8;
+/// Bytes taken from the rng device at boot
+const DEVICE_SEED_LEN: usize = 32;
+
+struct EntropyPool {
+    pool: [u64; POOL_WORDS],
+    // word which the next sample goes into
+    next: usize,
+    // entropy which came in since the last reseed
+    credit: usize,
+    // whether the generator got RESEED_CREDIT once
+    seeded: bool,
+    rng: ChaCha20Rng,
+}
+
+impl EntropyPool {
+    fn new() -> Self {
+        Self {
+            pool: [0; POOL_WORDS],
+            next: 0,
+            credit: 0,
+            seeded: false,
+            rng: ChaCha20Rng::new(),
+        }
+    }
+    /// Mix `sample` in, which is worth `credit` eighths of a bit.
+    fn add(&mut self, sample: u64, credit: usize) {
+        // cheap enough for every trap, the generator hashes the pool later
+        let i = self.next % POOL_WORDS;
+        self.next = self.next.wrapping_add(1);
+        let neighbour = self.pool[(i + 1) % POOL_WORDS];
+        self.pool[i] = (self.pool[i] ^ sample)
+            .rotate_left(23)
+            .wrapping_add(neighbour);
+        self.credit = (self.credit + credit).min(RESEED_CREDIT);
+    }
+    fn add_bytes(&mut self, bytes: &[u8], credit_per_byte: usize) {
+        for chunk in bytes.chunks(8) {
+            let mut word = [0u8; 8];
+            word[..chunk.len()].copy_from_slice(chunk);
+            self.add(u64::from_ne_bytes(word), chunk.len() * credit_per_byte);
+        }
+    }
+    fn fill(&mut self, buf: &mut [u8]) {
+        // the time of the request is one more sample
+        self.add(Time::now().raw() as u64, 0);
+        // until it is seeded, the generator takes whatever there is
+        if !self.seeded || self.credit >= RESEED_CREDIT {
+            self.rng.reseed(&self.pool);
+            if self.credit >= RESEED_CREDIT {
+                self.seeded = true;
+                self.credit = 0;
+            }
+        }
+        self.rng.fill(buf);
+    }
+}
+
+lazy_static! {
+    static ref POOL: UPSafeCell<EntropyPool> = unsafe { UPSafeCell::new(EntropyPool::new()) };
+}
+
+/// Seed the pool from the rng device, if there is one.
+pub fn init_random() {
+    let mut seed = [0u8; DEVICE_SEED_LEN];
+    let len = RNG_DEVICE
+        .as_ref()
+        .map_or(0, |rng| rng.read_entropy(&mut seed));
+    info!("random: {} bytes from the rng device", len);
+    let mut pool = POOL.exclusive_access();
+    pool.add_bytes(&seed[..len], DEVICE_BYTE_CREDIT);
+    pool.add(Time::now().raw() as u64, 0);
+}
+
+/// Stir the time counter into the pool, on every trap. The sample counts
+/// for nothing, as a user program can time its own syscalls and faults.
+pub fn add_trap_entropy() {
+    POOL.exclusive_access().add(Time::now().raw() as u64, 0);
+}
+
+/// Stir the time counter into the pool, on interrupts.
+pub fn add_interrupt_entropy() {
+    POOL.exclusive_access()
+        .add(Time::now().raw() as u64, INTERRUPT_CREDIT);
+}
+
+/// Mix bytes which user space wrote to /dev/random in, which count for
+/// nothing as anyone may write them.
+pub fn add_user_entropy(bytes: &[u8]) {
+    POOL.exclusive_access().add_bytes(bytes, 0);
+}
+
+/// Whether enough entropy came in for the generator to be unpredictable.
+pub fn random_ready() -> bool {
+    let pool = POOL.exclusive_access();
+    pool.seeded || pool.credit >= RESEED_CREDIT
+}
+
+/// Fill `buf` with random bytes, even if the generator is not ready yet.
+pub fn fill_random(buf: &mut [u8]) {
+    POOL.exclusive_access().fill(buf);
+}
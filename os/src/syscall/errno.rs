@@ -0,0 +1,136 @@
+use crate::config::PAGE_SIZE;
+use crate::task::current_task;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use polyhal::pagetable::MappingFlags;
+
+/// Why a syscall failed, user space sees the negated number
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Errno(pub isize);
+
+/// What a syscall handler returns, `Ok` is passed on to user space as is
+pub type SyscallResult = Result<usize, Errno>;
+
+// same numbers as Linux
+pub const EPERM: Errno = Errno(1);
+pub const ENOENT: Errno = Errno(2);
+pub const ESRCH: Errno = Errno(3);
+/// A blocking syscall was interrupted by a signal handler
+pub const EINTR: Errno = Errno(4);
+pub const EIO: Errno = Errno(5);
+pub const E2BIG: Errno = Errno(7);
+pub const ENOEXEC: Errno = Errno(8);
+pub const EBADF: Errno = Errno(9);
+pub const ECHILD: Errno = Errno(10);
+pub const EAGAIN: Errno = Errno(11);
+pub const ENOMEM: Errno = Errno(12);
+pub const EFAULT: Errno = Errno(14);
+pub const EBUSY: Errno = Errno(16);
+pub const EEXIST: Errno = Errno(17);
+pub const EXDEV: Errno = Errno(18);
+pub const ENODEV: Errno = Errno(19);
+pub const ENOTDIR: Errno = Errno(20);
+pub const EISDIR: Errno = Errno(21);
+pub const EINVAL: Errno = Errno(22);
+pub const EMFILE: Errno = Errno(24);
+pub const ENOTTY: Errno = Errno(25);
+pub const ENOSPC: Errno = Errno(28);
+pub const ERANGE: Errno = Errno(34);
+pub const ENAMETOOLONG: Errno = Errno(36);
+/// The syscall does not exist, or was turned down by a seccomp filter
+/// which sends SIGSYS
+pub const ENOSYS: Errno = Errno(38);
+pub const ENOTEMPTY: Errno = Errno(39);
+pub const ELOOP: Errno = Errno(40);
+
+impl Errno {
+    /// The value left in the return register of a failed syscall.
+    pub fn as_ret(self) -> isize {
+        -self.0
+    }
+    /// Name of the errno, None if it is not one of ours.
+    pub fn name(self) -> Option<&'static str> {
+        let name = match self {
+            EPERM => "EPERM",
+            ENOENT => "ENOENT",
+            ESRCH => "ESRCH",
+            EINTR => "EINTR",
+            EIO => "EIO",
+            E2BIG => "E2BIG",
+            ENOEXEC => "ENOEXEC",
+            EBADF => "EBADF",
+            ECHILD => "ECHILD",
+            EAGAIN => "EAGAIN",
+            ENOMEM => "ENOMEM",
+            EFAULT => "EFAULT",
+            EBUSY => "EBUSY",
+            EEXIST => "EEXIST",
+            EXDEV => "EXDEV",
+            ENODEV => "ENODEV",
+            ENOTDIR => "ENOTDIR",
+            EISDIR => "EISDIR",
+            EINVAL => "EINVAL",
+            EMFILE => "EMFILE",
+            ENOTTY => "ENOTTY",
+            ENOSPC => "ENOSPC",
+            ERANGE => "ERANGE",
+            ENAMETOOLONG => "ENAMETOOLONG",
+            ENOSYS => "ENOSYS",
+            ENOTEMPTY => "ENOTEMPTY",
+            ELOOP => "ELOOP",
+            _ => return None,
+        };
+        Some(name)
+    }
+}
+
+/// Fail with EFAULT unless `[addr, addr + len)` is mapped in the current
+/// task with `flags`.
+pub fn check_user_range(addr: usize, len: usize, flags: MappingFlags) -> Result<(), Errno> {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let ok = addr.checked_add(len).is_some()
+        && inner
+            .memory_set
+            .exclusive_access()
+            .check_user_range(addr, len, flags);
+    if ok {
+        Ok(())
+    } else {
+        Err(EFAULT)
+    }
+}
+
+/// Longest path taken from user space, with its nul, like Linux PATH_MAX
+pub const PATH_MAX: usize = 4096;
+/// Longest argument or environment string of exec, like Linux
+/// MAX_ARG_STRLEN
+pub const MAX_ARG_STRLEN: usize = 32 * PAGE_SIZE;
+
+/// Copy the nul-terminated string at `ptr` of the current task, which has
+/// to fit in `max_len` bytes with its nul. Fail with EFAULT if it runs
+/// into memory which is not readable, with ENAMETOOLONG if it is longer
+/// and with EINVAL if it is not UTF-8.
+pub fn read_user_str(ptr: *const u8, max_len: usize) -> Result<String, Errno> {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let memory_set = inner.memory_set.exclusive_access();
+    let mut bytes = Vec::new();
+    let mut chunk = vec![0u8; PAGE_SIZE];
+    let mut addr = ptr as usize;
+    while bytes.len() < max_len {
+        // up to the end of the page, the next one may not be mapped
+        let len = (PAGE_SIZE - addr % PAGE_SIZE).min(max_len - bytes.len());
+        if !memory_set.read_bytes(addr, &mut chunk[..len]) {
+            return Err(EFAULT);
+        }
+        if let Some(nul) = chunk[..len].iter().position(|&b| b == 0) {
+            bytes.extend_from_slice(&chunk[..nul]);
+            return String::from_utf8(bytes).map_err(|_| EINVAL);
+        }
+        bytes.extend_from_slice(&chunk[..len]);
+        addr += len;
+    }
+    Err(ENAMETOOLONG)
+}
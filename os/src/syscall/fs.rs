@@ -1,99 +1,527 @@
-use crate::fs::{make_pipe, open_file, OpenFlags};
-use crate::mm::{translated_byte_buffer, translated_refmut, translated_str};
-use crate::task::{current_task, current_user_token};
+use super::{
+    check_user_range, read_user_str, Errno, SyscallResult, EBADF, EEXIST, EINVAL, EMFILE, ENOENT,
+    ENOTDIR, ENOTTY, EPERM, ERANGE, PATH_MAX,
+};
+use crate::fs::{
+    make_pipe, mount, open, resolve, resolve_parent, umount, Dentry, File, FileStat, NodeKind,
+    OpenFlags,
+};
+use crate::mm::{translated_byte_buffer, translated_ref, translated_refmut};
+use crate::task::{current_cwd, current_task, current_user_token, RLIMIT_NOFILE};
+use alloc::string::String;
 use alloc::sync::Arc;
+use core::mem::size_of;
+use polyhal::pagetable::MappingFlags;
 
-pub fn sys_write(fd: usize, buf: *mut u8, len: usize) -> isize {
+pub fn sys_write(fd: usize, buf: *mut u8, len: usize) -> SyscallResult {
+    check_user_range(buf as usize, len, MappingFlags::R)?;
     let token = current_user_token();
     let task = current_task().unwrap();
     let inner = task.inner_exclusive_access();
-    if fd >= inner.fd_table.len() {
-        return -1;
+    let fd_table = inner.fd_table.exclusive_access();
+    if fd >= fd_table.len() {
+        return Err(EBADF);
     }
-    if let Some(file) = &inner.fd_table[fd] {
+    if let Some(file) = &fd_table[fd] {
         if !file.writable() {
-            return -1;
+            return Err(EBADF);
         }
         let file = file.clone();
         // release current task TCB manually to avoid multi-borrow
+        drop(fd_table);
         drop(inner);
-        file.write(translated_byte_buffer(token, buf, len)) as isize
+        Ok(file.write(translated_byte_buffer(token, buf, len)))
     } else {
-        -1
+        Err(EBADF)
     }
 }
 
-pub fn sys_read(fd: usize, buf: *mut u8, len: usize) -> isize {
+pub fn sys_read(fd: usize, buf: *mut u8, len: usize) -> SyscallResult {
+    check_user_range(buf as usize, len, MappingFlags::W)?;
     let token = current_user_token();
     let task = current_task().unwrap();
     let inner = task.inner_exclusive_access();
-    if fd >= inner.fd_table.len() {
-        return -1;
+    let fd_table = inner.fd_table.exclusive_access();
+    if fd >= fd_table.len() {
+        return Err(EBADF);
     }
-    if let Some(file) = &inner.fd_table[fd] {
+    if let Some(file) = &fd_table[fd] {
         let file = file.clone();
         if !file.readable() {
-            return -1;
+            return Err(EBADF);
         }
         // release current task TCB manually to avoid multi-borrow
+        drop(fd_table);
         drop(inner);
-        file.read(translated_byte_buffer(token, buf, len)) as isize
+        file.read(translated_byte_buffer(token, buf, len))
     } else {
-        -1
+        Err(EBADF)
     }
 }
 
-pub fn sys_open(path: *const u8, flags: u32) -> isize {
+pub fn sys_open(path: *const u8, flags: u32) -> SyscallResult {
     let task = current_task().unwrap();
-    let token = current_user_token();
-    let path = translated_str(token, path);
-    if let Some(inode) = open_file(path.as_str(), OpenFlags::from_bits(flags).unwrap()) {
-        let mut inner = task.inner_exclusive_access();
-        let fd = inner.alloc_fd();
-        inner.fd_table[fd] = Some(inode);
-        fd as isize
-    } else {
-        -1
-    }
+    let path = read_user_str(path, PATH_MAX)?;
+    let flags = OpenFlags::from_bits(flags).ok_or(EINVAL)?;
+    let file = open(&current_cwd(), path.as_str(), flags)?;
+    let mut inner = task.inner_exclusive_access();
+    let fd = inner.alloc_fd().ok_or(EMFILE)?;
+    inner.fd_table.exclusive_access()[fd] = Some(file);
+    Ok(fd)
 }
 
-pub fn sys_close(fd: usize) -> isize {
+pub fn sys_close(fd: usize) -> SyscallResult {
     let task = current_task().unwrap();
-    let mut inner = task.inner_exclusive_access();
-    if fd >= inner.fd_table.len() {
-        return -1;
+    let inner = task.inner_exclusive_access();
+    let mut fd_table = inner.fd_table.exclusive_access();
+    if fd >= fd_table.len() {
+        return Err(EBADF);
     }
-    if inner.fd_table[fd].is_none() {
-        return -1;
+    if fd_table[fd].is_none() {
+        return Err(EBADF);
     }
-    inner.fd_table[fd].take();
-    0
+    fd_table[fd].take();
+    Ok(0)
 }
 
-pub fn sys_pipe(pipe: *mut usize) -> isize {
+pub fn sys_pipe(pipe: *mut usize) -> SyscallResult {
+    check_user_range(pipe as usize, 2 * size_of::<usize>(), MappingFlags::W)?;
     let task = current_task().unwrap();
     let token = current_user_token();
     let mut inner = task.inner_exclusive_access();
     let (pipe_read, pipe_write) = make_pipe();
-    let read_fd = inner.alloc_fd();
-    inner.fd_table[read_fd] = Some(pipe_read);
-    let write_fd = inner.alloc_fd();
-    inner.fd_table[write_fd] = Some(pipe_write);
+    let read_fd = inner.alloc_fd().ok_or(EMFILE)?;
+    inner.fd_table.exclusive_access()[read_fd] = Some(pipe_read);
+    let write_fd = match inner.alloc_fd() {
+        Some(fd) => fd,
+        None => {
+            inner.fd_table.exclusive_access()[read_fd] = None;
+            return Err(EMFILE);
+        }
+    };
+    inner.fd_table.exclusive_access()[write_fd] = Some(pipe_write);
     *translated_refmut(token, pipe) = read_fd;
     *translated_refmut(token, unsafe { pipe.add(1) }) = write_fd;
-    0
+    Ok(0)
 }
 
-pub fn sys_dup(fd: usize) -> isize {
+pub fn sys_dup(fd: usize) -> SyscallResult {
     let task = current_task().unwrap();
     let mut inner = task.inner_exclusive_access();
-    if fd >= inner.fd_table.len() {
-        return -1;
+    let file = match inner.fd_table.exclusive_access().get(fd) {
+        Some(Some(file)) => file.clone(),
+        _ => return Err(EBADF),
+    };
+    let new_fd = inner.alloc_fd().ok_or(EMFILE)?;
+    inner.fd_table.exclusive_access()[new_fd] = Some(file);
+    Ok(new_fd)
+}
+
+/// The file open as `fd` in the current task.
+fn get_file(fd: usize) -> Result<Arc<dyn File + Send + Sync>, Errno> {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let fd_table = inner.fd_table.exclusive_access();
+    match fd_table.get(fd) {
+        Some(Some(file)) => Ok(file.clone()),
+        _ => Err(EBADF),
+    }
+}
+
+/// Make `new_fd` refer to the file of `old_fd`, closing what it referred to.
+/// `flags` may only be O_CLOEXEC, which is ignored as there is no
+/// close-on-exec.
+pub fn sys_dup3(old_fd: usize, new_fd: usize, flags: u32) -> SyscallResult {
+    if old_fd == new_fd || flags & !O_CLOEXEC != 0 {
+        return Err(EINVAL);
+    }
+    let file = get_file(old_fd)?;
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if new_fd >= inner.rlimits[RLIMIT_NOFILE].cur {
+        return Err(EBADF);
+    }
+    let mut fd_table = inner.fd_table.exclusive_access();
+    if new_fd >= fd_table.len() {
+        fd_table.resize(new_fd + 1, None);
+    }
+    fd_table[new_fd] = Some(file);
+    Ok(new_fd)
+}
+
+pub const O_CLOEXEC: u32 = 0o2000000;
+
+// fcntl commands
+const F_DUPFD: usize = 0;
+const F_GETFD: usize = 1;
+const F_SETFD: usize = 2;
+const F_GETFL: usize = 3;
+const F_SETFL: usize = 4;
+const F_DUPFD_CLOEXEC: usize = 1030;
+
+/// Duplicate `fd` or get its flags. Close-on-exec and status flags cannot
+/// be set, so they always read back as clear.
+pub fn sys_fcntl(fd: usize, cmd: usize, arg: usize) -> SyscallResult {
+    let file = get_file(fd)?;
+    match cmd {
+        F_DUPFD | F_DUPFD_CLOEXEC => {
+            let task = current_task().unwrap();
+            let mut inner = task.inner_exclusive_access();
+            let new_fd = inner.alloc_fd_from(arg).ok_or(EMFILE)?;
+            inner.fd_table.exclusive_access()[new_fd] = Some(file);
+            Ok(new_fd)
+        }
+        F_GETFD | F_SETFD | F_SETFL => Ok(0),
+        // O_RDONLY, O_WRONLY or O_RDWR
+        F_GETFL => Ok(match (file.readable(), file.writable()) {
+            (true, false) => 0,
+            (false, true) => 1,
+            _ => 2,
+        }),
+        _ => Err(EINVAL),
+    }
+}
+
+// tty ioctls
+const TCGETS: usize = 0x5401;
+const TCSETS: usize = 0x5402;
+const TCSETSW: usize = 0x5403;
+const TCSETSF: usize = 0x5404;
+const TIOCGWINSZ: usize = 0x5413;
+
+/// Linux `struct termios` as the kernel passes it
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Termios {
+    pub iflag: u32,
+    pub oflag: u32,
+    pub cflag: u32,
+    pub lflag: u32,
+    pub line: u8,
+    pub cc: [u8; 19],
+}
+
+/// Linux `struct winsize`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct WinSize {
+    pub row: u16,
+    pub col: u16,
+    pub xpixel: u16,
+    pub ypixel: u16,
+}
+
+/// The console passes bytes through as they are: no echo, no line editing
+/// and no output processing, which is what raw mode asks for anyway.
+const CONSOLE_TERMIOS: Termios = Termios {
+    iflag: 0,
+    oflag: 0,
+    // CS8 | CREAD
+    cflag: 0o60 | 0o200,
+    lflag: 0,
+    line: 0,
+    // VINTR, VQUIT, VERASE, VKILL, VEOF, VTIME and VMIN
+    cc: [
+        3, 0x1c, 0x7f, 0x15, 4, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+    ],
+};
+
+/// Answer the terminal ioctls on the console, setting its attributes is
+/// accepted but changes nothing.
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> SyscallResult {
+    if !get_file(fd)?.is_tty() {
+        return Err(ENOTTY);
+    }
+    match request {
+        TCGETS => {
+            check_user_range(arg, size_of::<Termios>(), MappingFlags::W)?;
+            *translated_refmut(current_user_token(), arg as *mut Termios) = CONSOLE_TERMIOS;
+        }
+        TCSETS | TCSETSW | TCSETSF => {
+            check_user_range(arg, size_of::<Termios>(), MappingFlags::R)?;
+        }
+        TIOCGWINSZ => {
+            check_user_range(arg, size_of::<WinSize>(), MappingFlags::W)?;
+            *translated_refmut(current_user_token(), arg as *mut WinSize) = WinSize {
+                row: 24,
+                col: 80,
+                xpixel: 0,
+                ypixel: 0,
+            };
+        }
+        _ => return Err(ENOTTY),
+    }
+    Ok(0)
+}
+
+/// Linux `struct iovec`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct IoVec {
+    pub base: usize,
+    pub len: usize,
+}
+
+/// At most this many buffers are read or written in one call
+const IOV_MAX: usize = 1024;
+
+/// Run `rw` on each buffer of `iov` in turn, until one comes back short.
+/// An error is only returned if nothing was transferred before it.
+fn for_each_iovec(
+    iov: *const IoVec,
+    iovcnt: usize,
+    rw: impl Fn(usize, usize) -> SyscallResult,
+) -> SyscallResult {
+    if iovcnt > IOV_MAX {
+        return Err(EINVAL);
+    }
+    check_user_range(iov as usize, iovcnt * size_of::<IoVec>(), MappingFlags::R)?;
+    let token = current_user_token();
+    let mut total = 0;
+    for i in 0..iovcnt {
+        let vec = *translated_ref(token, unsafe { iov.add(i) });
+        match rw(vec.base, vec.len) {
+            Ok(len) => {
+                total += len;
+                if len < vec.len {
+                    break;
+                }
+            }
+            Err(errno) if total == 0 => return Err(errno),
+            Err(_) => break,
+        }
+    }
+    Ok(total)
+}
+
+pub fn sys_readv(fd: usize, iov: *const IoVec, iovcnt: usize) -> SyscallResult {
+    for_each_iovec(iov, iovcnt, |base, len| sys_read(fd, base as *mut u8, len))
+}
+
+pub fn sys_writev(fd: usize, iov: *const IoVec, iovcnt: usize) -> SyscallResult {
+    for_each_iovec(iov, iovcnt, |base, len| sys_write(fd, base as *mut u8, len))
+}
+
+/// Linux `struct stat` of the generic syscall table
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Kstat {
+    pub dev: u64,
+    pub ino: u64,
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u64,
+    pub __pad: u64,
+    pub size: i64,
+    pub blksize: i32,
+    pub __pad2: i32,
+    pub blocks: i64,
+    pub atime: [i64; 2],
+    pub mtime: [i64; 2],
+    pub ctime: [i64; 2],
+    pub __unused: [u32; 2],
+}
+
+const BLOCK_SIZE: usize = 512;
+
+fn write_stat(stat: FileStat, statbuf: *mut Kstat) -> SyscallResult {
+    check_user_range(statbuf as usize, size_of::<Kstat>(), MappingFlags::W)?;
+    // every file belongs to root and there are no times to report
+    *translated_refmut(current_user_token(), statbuf) = Kstat {
+        mode: stat.mode,
+        nlink: stat.nlink as u32,
+        size: stat.size as i64,
+        blksize: BLOCK_SIZE as i32,
+        blocks: ((stat.size + BLOCK_SIZE - 1) / BLOCK_SIZE) as i64,
+        ..Default::default()
+    };
+    Ok(0)
+}
+
+pub fn sys_fstat(fd: usize, statbuf: *mut Kstat) -> SyscallResult {
+    write_stat(get_file(fd)?.stat(), statbuf)
+}
+
+/// `dirfd` of the *at syscalls for the current directory
+pub const AT_FDCWD: isize = -100;
+/// fstatat on `dirfd` itself if the path is empty
+const AT_EMPTY_PATH: u32 = 0x1000;
+
+/// The directory which a relative `path` of an *at syscall is taken from,
+/// the current directory for AT_FDCWD or else the directory open as `dirfd`.
+pub fn dir_at(dirfd: isize, path: &str) -> Result<String, Errno> {
+    if path.starts_with('/') || dirfd == AT_FDCWD {
+        return Ok(current_cwd());
+    }
+    let file = get_file(dirfd as usize)?;
+    file.dir_path().map(String::from).ok_or(ENOTDIR)
+}
+
+pub fn sys_newfstatat(
+    dirfd: isize,
+    path: *const u8,
+    statbuf: *mut Kstat,
+    flags: u32,
+) -> SyscallResult {
+    let path = read_user_str(path, PATH_MAX)?;
+    if path.is_empty() {
+        if flags & AT_EMPTY_PATH == 0 {
+            return Err(ENOENT);
+        }
+        return sys_fstat(dirfd as usize, statbuf);
+    }
+    let stat = open(&dir_at(dirfd, &path)?, &path, OpenFlags::RDONLY)?.stat();
+    write_stat(stat, statbuf)
+}
+
+/// Write the current directory to `buf`, null-terminated. Return its
+/// length with the null byte.
+pub fn sys_getcwd(buf: *mut u8, size: usize) -> SyscallResult {
+    let mut cwd = current_cwd().into_bytes();
+    cwd.push(0);
+    if size < cwd.len() {
+        return Err(ERANGE);
+    }
+    check_user_range(buf as usize, cwd.len(), MappingFlags::W)?;
+    let token = current_user_token();
+    for (i, c) in cwd.iter().enumerate() {
+        *translated_refmut(token, unsafe { buf.add(i) }) = *c;
+    }
+    Ok(cwd.len())
+}
+
+pub fn sys_chdir(path: *const u8) -> SyscallResult {
+    let path = read_user_str(path, PATH_MAX)?;
+    let dentry = resolve(&current_cwd(), &path)?;
+    if !dentry.is_dir() {
+        return Err(ENOTDIR);
+    }
+    let task = current_task().unwrap();
+    *task.inner_exclusive_access().cwd.exclusive_access() = dentry.path;
+    Ok(0)
+}
+
+/// Make the directory `path` taken from `dirfd`. There are no permissions,
+/// so `mode` is ignored.
+pub fn sys_mkdirat(dirfd: isize, path: *const u8, _mode: u32) -> SyscallResult {
+    let path = read_user_str(path, PATH_MAX)?;
+    let (dir, name) = resolve_parent(&dir_at(dirfd, &path)?, &path)?;
+    dir.create(name, NodeKind::Dir)?;
+    Ok(0)
+}
+
+/// unlinkat removes a directory instead of a file
+const AT_REMOVEDIR: u32 = 0x200;
+/// linkat follows a symbolic link at the old path, there are none anyway
+const AT_SYMLINK_FOLLOW: u32 = 0x400;
+/// renameat2 fails with EEXIST instead of replacing the new path
+const RENAME_NOREPLACE: u32 = 1;
+
+/// The directory and the name of `path` taken from `dirfd`, for the
+/// syscalls which take the name away, which `.`, `..` and `/` are not.
+fn parent_at(dirfd: isize, path: &str) -> Result<(Dentry, &str), Errno> {
+    match resolve_parent(&dir_at(dirfd, path)?, path) {
+        Err(EEXIST) => Err(EINVAL),
+        parent => parent,
+    }
+}
+
+/// Remove the name `path` taken from `dirfd`, which is a file, or an
+/// empty directory with AT_REMOVEDIR. A file is freed once it has no names
+/// left and no one has it open.
+pub fn sys_unlinkat(dirfd: isize, path: *const u8, flags: u32) -> SyscallResult {
+    if flags & !AT_REMOVEDIR != 0 {
+        return Err(EINVAL);
+    }
+    let path = read_user_str(path, PATH_MAX)?;
+    let (dir, name) = parent_at(dirfd, &path)?;
+    dir.unlink(name, flags & AT_REMOVEDIR != 0)?;
+    Ok(0)
+}
+
+/// Remove the empty directory `path`, like unlinkat with AT_REMOVEDIR.
+pub fn sys_rmdir(path: *const u8) -> SyscallResult {
+    sys_unlinkat(AT_FDCWD, path, AT_REMOVEDIR)
+}
+
+/// Give the file at `old_path` taken from `old_dirfd` the name `new_path`
+/// taken from `new_dirfd` too.
+pub fn sys_linkat(
+    old_dirfd: isize,
+    old_path: *const u8,
+    new_dirfd: isize,
+    new_path: *const u8,
+    flags: u32,
+) -> SyscallResult {
+    if flags & !AT_SYMLINK_FOLLOW != 0 {
+        return Err(EINVAL);
+    }
+    let old_path = read_user_str(old_path, PATH_MAX)?;
+    let new_path = read_user_str(new_path, PATH_MAX)?;
+    let target = resolve(&dir_at(old_dirfd, &old_path)?, &old_path)?;
+    let (dir, name) = resolve_parent(&dir_at(new_dirfd, &new_path)?, &new_path)?;
+    dir.link(name, &target)?;
+    Ok(0)
+}
+
+/// Move `old_path` taken from `old_dirfd` to `new_path` taken from
+/// `new_dirfd`, within one file system. RENAME_NOREPLACE is the only flag.
+pub fn sys_renameat2(
+    old_dirfd: isize,
+    old_path: *const u8,
+    new_dirfd: isize,
+    new_path: *const u8,
+    flags: u32,
+) -> SyscallResult {
+    if flags & !RENAME_NOREPLACE != 0 {
+        return Err(EINVAL);
+    }
+    let old_path = read_user_str(old_path, PATH_MAX)?;
+    let new_path = read_user_str(new_path, PATH_MAX)?;
+    let (old_dir, old_name) = parent_at(old_dirfd, &old_path)?;
+    let (new_dir, new_name) = parent_at(new_dirfd, &new_path)?;
+    old_dir.rename(old_name, &new_dir, new_name, flags & RENAME_NOREPLACE != 0)?;
+    Ok(0)
+}
+
+/// Mount a new file system of type `fs_type` on the directory `target`.
+/// Only file systems which need no device can be made, so `source`, like
+/// `flags` and `data`, is ignored. Root only.
+pub fn sys_mount(
+    _source: *const u8,
+    target: *const u8,
+    fs_type: *const u8,
+    _flags: usize,
+    _data: *const u8,
+) -> SyscallResult {
+    let creds = current_task().unwrap().inner_exclusive_access().creds;
+    if !creds.is_root() {
+        return Err(EPERM);
+    }
+    let target = read_user_str(target, PATH_MAX)?;
+    let fs_type = read_user_str(fs_type, PATH_MAX)?;
+    let dentry = resolve(&current_cwd(), &target)?;
+    if !dentry.is_dir() {
+        return Err(ENOTDIR);
+    }
+    mount(&dentry, &fs_type)?;
+    Ok(0)
+}
+
+/// Unmount the file system mounted last on the directory `target`. There
+/// are no flags. Root only.
+pub fn sys_umount2(target: *const u8, flags: u32) -> SyscallResult {
+    let creds = current_task().unwrap().inner_exclusive_access().creds;
+    if !creds.is_root() {
+        return Err(EPERM);
     }
-    if inner.fd_table[fd].is_none() {
-        return -1;
+    if flags != 0 {
+        return Err(EINVAL);
     }
-    let new_fd = inner.alloc_fd();
-    inner.fd_table[new_fd] = Some(Arc::clone(inner.fd_table[fd].as_ref().unwrap()));
-    new_fd as isize
+    let target = read_user_str(target, PATH_MAX)?;
+    let dentry = resolve(&current_cwd(), &target)?;
+    umount(&dentry.path)?;
+    Ok(0)
 }
@@ -0,0 +1,224 @@
+//! Syscalls of Linux programs whose numbers mean something else to native
+//! ones. Every other number is shared, so Linux programs fall through to
+//! the native table. Only the generic table of riscv64, aarch64 and
+//! loongarch64 is spoken, exec turns down Linux programs on x86_64.
+
+use super::{
+    check_user_range, dir_at, read_user_str, set_sigaction, sys_dup3, SyscallResult, EINVAL,
+    EMFILE, PATH_MAX,
+};
+use crate::config::SIGRETURN_TRAMPOLINE;
+use crate::fs::{make_pipe, open, OpenFlags};
+use crate::mm::{translated_ref, translated_refmut};
+use crate::task::{
+    current_task, current_user_token, now_us, SignalAction, SignalActionFlags, SignalFlags, TimeVal,
+};
+use core::mem::size_of;
+use polyhal::pagetable::MappingFlags;
+
+pub const SYSCALL_DUP3: usize = 24;
+pub const SYSCALL_OPENAT: usize = 56;
+pub const SYSCALL_PIPE2: usize = 59;
+pub const SYSCALL_RT_SIGACTION: usize = 134;
+pub const SYSCALL_RT_SIGPROCMASK: usize = 135;
+pub const SYSCALL_GETTIMEOFDAY: usize = 169;
+
+/// Run a Linux syscall which has another meaning in the native table, or
+/// return None if the native handler serves both.
+pub fn linux_dispatch(syscall_id: usize, args: [usize; 6]) -> Option<SyscallResult> {
+    let ret = match syscall_id {
+        SYSCALL_DUP3 => sys_dup3(args[0], args[1], args[2] as u32),
+        SYSCALL_OPENAT => sys_openat(args[0] as isize, args[1] as *const u8, args[2] as u32),
+        SYSCALL_PIPE2 => sys_pipe2(args[0] as *mut i32, args[1] as u32),
+        SYSCALL_RT_SIGACTION => sys_rt_sigaction(
+            args[0] as i32,
+            args[1] as *const LinuxSigAction,
+            args[2] as *mut LinuxSigAction,
+            args[3],
+        ),
+        SYSCALL_RT_SIGPROCMASK => {
+            sys_rt_sigprocmask(args[0], args[1] as *const u64, args[2] as *mut u64, args[3])
+        }
+        SYSCALL_GETTIMEOFDAY => sys_gettimeofday(args[0] as *mut TimeVal),
+        _ => return None,
+    };
+    Some(ret)
+}
+
+// Linux open flags which have a native counterpart, the others are ignored
+const O_ACCMODE: u32 = 3;
+const O_CREAT: u32 = 0o100;
+const O_TRUNC: u32 = 0o1000;
+
+fn sys_openat(dirfd: isize, path: *const u8, flags: u32) -> SyscallResult {
+    let path = read_user_str(path, PATH_MAX)?;
+    let dir = dir_at(dirfd, &path)?;
+    let mut open_flags = OpenFlags::from_bits(flags & O_ACCMODE).ok_or(EINVAL)?;
+    if flags & O_CREAT != 0 {
+        open_flags |= OpenFlags::CREATE;
+    }
+    if flags & O_TRUNC != 0 {
+        open_flags |= OpenFlags::TRUNC;
+    }
+    let file = open(&dir, &path, open_flags)?;
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let fd = inner.alloc_fd().ok_or(EMFILE)?;
+    inner.fd_table.exclusive_access()[fd] = Some(file);
+    Ok(fd)
+}
+
+/// Like the native pipe, but the fds are ints. `flags` are ignored.
+fn sys_pipe2(pipe: *mut i32, _flags: u32) -> SyscallResult {
+    check_user_range(pipe as usize, 2 * size_of::<i32>(), MappingFlags::W)?;
+    let task = current_task().unwrap();
+    let token = current_user_token();
+    let mut inner = task.inner_exclusive_access();
+    let (pipe_read, pipe_write) = make_pipe();
+    let read_fd = inner.alloc_fd().ok_or(EMFILE)?;
+    inner.fd_table.exclusive_access()[read_fd] = Some(pipe_read);
+    let write_fd = match inner.alloc_fd() {
+        Some(fd) => fd,
+        None => {
+            inner.fd_table.exclusive_access()[read_fd] = None;
+            return Err(EMFILE);
+        }
+    };
+    inner.fd_table.exclusive_access()[write_fd] = Some(pipe_write);
+    *translated_refmut(token, pipe) = read_fd as i32;
+    *translated_refmut(token, unsafe { pipe.add(1) }) = write_fd as i32;
+    Ok(0)
+}
+
+/// Bytes in a Linux sigset, signal n is bit n - 1
+const SIGSET_SIZE: usize = 8;
+
+fn from_sigset(set: u64) -> SignalFlags {
+    SignalFlags::from_bits_truncate(set << 1)
+}
+
+fn to_sigset(signals: SignalFlags) -> u64 {
+    signals.bits() >> 1
+}
+
+/// The handler returns to `restorer`
+const SA_RESTORER: usize = 0x0400_0000;
+
+/// Linux `struct sigaction` as the kernel takes it, which only has a
+/// restorer where SA_RESTORER exists
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct LinuxSigAction {
+    pub handler: usize,
+    pub flags: usize,
+    #[cfg(target_arch = "aarch64")]
+    pub restorer: usize,
+    pub mask: u64,
+}
+
+fn sys_rt_sigaction(
+    signum: i32,
+    action: *const LinuxSigAction,
+    old_action: *mut LinuxSigAction,
+    sigsetsize: usize,
+) -> SyscallResult {
+    if sigsetsize != SIGSET_SIZE {
+        return Err(EINVAL);
+    }
+    let action_size = size_of::<LinuxSigAction>();
+    if !action.is_null() {
+        check_user_range(action as usize, action_size, MappingFlags::R)?;
+    }
+    if !old_action.is_null() {
+        check_user_range(old_action as usize, action_size, MappingFlags::W)?;
+    }
+    let token = current_user_token();
+    let action = if action.is_null() {
+        None
+    } else {
+        let action = *translated_ref(token, action);
+        // handlers without a restorer of their own return to the trampoline
+        #[cfg(target_arch = "aarch64")]
+        let restorer = if action.flags & SA_RESTORER != 0 {
+            action.restorer
+        } else {
+            SIGRETURN_TRAMPOLINE
+        };
+        #[cfg(not(target_arch = "aarch64"))]
+        let restorer = SIGRETURN_TRAMPOLINE;
+        Some(SignalAction {
+            handler: action.handler,
+            mask: from_sigset(action.mask),
+            flags: SignalActionFlags::from_bits_truncate(action.flags as u32),
+            restorer,
+        })
+    };
+    let prev = set_sigaction(signum, action)?;
+    if !old_action.is_null() {
+        let mut flags = prev.flags.bits() as usize;
+        if prev.restorer != SIGRETURN_TRAMPOLINE {
+            flags |= SA_RESTORER;
+        }
+        *translated_refmut(token, old_action) = LinuxSigAction {
+            handler: prev.handler,
+            flags,
+            #[cfg(target_arch = "aarch64")]
+            restorer: prev.restorer,
+            mask: to_sigset(prev.mask),
+        };
+    }
+    Ok(0)
+}
+
+// how rt_sigprocmask changes the mask
+const SIG_BLOCK: usize = 0;
+const SIG_UNBLOCK: usize = 1;
+const SIG_SETMASK: usize = 2;
+
+/// Change the signal mask by `set` as `how` says unless `set` is null, and
+/// report the old mask in `old_set` unless it is null. SIGKILL and SIGSTOP
+/// cannot be blocked.
+fn sys_rt_sigprocmask(
+    how: usize,
+    set: *const u64,
+    old_set: *mut u64,
+    sigsetsize: usize,
+) -> SyscallResult {
+    if sigsetsize != SIGSET_SIZE {
+        return Err(EINVAL);
+    }
+    if !set.is_null() {
+        check_user_range(set as usize, SIGSET_SIZE, MappingFlags::R)?;
+    }
+    if !old_set.is_null() {
+        check_user_range(old_set as usize, SIGSET_SIZE, MappingFlags::W)?;
+    }
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let old_mask = inner.signal_mask;
+    if !set.is_null() {
+        let signals = from_sigset(*translated_ref(token, set));
+        let mask = match how {
+            SIG_BLOCK => old_mask | signals,
+            SIG_UNBLOCK => old_mask - signals,
+            SIG_SETMASK => signals,
+            _ => return Err(EINVAL),
+        };
+        inner.signal_mask = mask.blockable();
+    }
+    if !old_set.is_null() {
+        *translated_refmut(token, old_set) = to_sigset(old_mask);
+    }
+    Ok(0)
+}
+
+/// There is no RTC, so the time of day counts from boot. The timezone is
+/// not reported.
+fn sys_gettimeofday(tv: *mut TimeVal) -> SyscallResult {
+    if !tv.is_null() {
+        check_user_range(tv as usize, size_of::<TimeVal>(), MappingFlags::W)?;
+        *translated_refmut(current_user_token(), tv) = TimeVal::from_us(now_us());
+    }
+    Ok(0)
+}
@@ -0,0 +1,84 @@
+use super::{SyscallResult, EINVAL, ENODEV, ENOMEM};
+use crate::config::{PAGE_SIZE, USER_SPACE_END};
+use crate::mm::MapPermission;
+use crate::task::{current_rlimit, current_task, RLIMIT_AS};
+
+// mmap protection
+pub const PROT_READ: usize = 1;
+pub const PROT_WRITE: usize = 2;
+pub const PROT_EXEC: usize = 4;
+
+// mmap flags
+pub const MAP_SHARED: usize = 1;
+pub const MAP_PRIVATE: usize = 2;
+pub const MAP_FIXED: usize = 0x10;
+pub const MAP_ANONYMOUS: usize = 0x20;
+
+/// Move the end of the heap to `brk` and return the new end. A failed move
+/// returns the old end, as on Linux.
+pub fn sys_brk(brk: usize) -> SyscallResult {
+    let max_size = current_rlimit(RLIMIT_AS);
+    let task = current_task().unwrap();
+    let memory_set = task.inner_exclusive_access().memory_set.clone();
+    let brk = memory_set.exclusive_access().set_brk(brk, max_size);
+    Ok(brk)
+}
+
+/// Map zeroed private memory. Only anonymous mappings are supported, there
+/// is nothing to back shared or file mappings with.
+pub fn sys_mmap(
+    addr: usize,
+    len: usize,
+    prot: usize,
+    flags: usize,
+    _fd: usize,
+    _offset: usize,
+) -> SyscallResult {
+    if len == 0 || addr.checked_add(len).is_none() {
+        return Err(EINVAL);
+    }
+    if flags & (MAP_SHARED | MAP_PRIVATE) != MAP_PRIVATE {
+        return Err(EINVAL);
+    }
+    if flags & MAP_ANONYMOUS == 0 {
+        return Err(ENODEV);
+    }
+    // without MAP_FIXED the address is only a hint, which we do not take
+    let addr = if flags & MAP_FIXED != 0 {
+        if addr % PAGE_SIZE != 0 {
+            return Err(EINVAL);
+        }
+        Some(addr)
+    } else {
+        None
+    };
+    let mut perm = MapPermission::empty();
+    if prot & PROT_READ != 0 {
+        perm |= MapPermission::R;
+    }
+    if prot & PROT_WRITE != 0 {
+        perm |= MapPermission::W;
+    }
+    if prot & PROT_EXEC != 0 {
+        perm |= MapPermission::X;
+    }
+    let max_size = current_rlimit(RLIMIT_AS);
+    let task = current_task().unwrap();
+    let memory_set = task.inner_exclusive_access().memory_set.clone();
+    let start = memory_set
+        .exclusive_access()
+        .mmap(addr, len, perm, max_size)
+        .ok_or(ENOMEM)?;
+    Ok(start)
+}
+
+pub fn sys_munmap(addr: usize, len: usize) -> SyscallResult {
+    let end = addr.checked_add(len).ok_or(EINVAL)?;
+    if addr % PAGE_SIZE != 0 || len == 0 || end > USER_SPACE_END {
+        return Err(EINVAL);
+    }
+    let task = current_task().unwrap();
+    let memory_set = task.inner_exclusive_access().memory_set.clone();
+    memory_set.exclusive_access().munmap(addr, len);
+    Ok(0)
+}
@@ -1,53 +1,247 @@
+const SYSCALL_GETCWD: usize = 17;
 const SYSCALL_DUP: usize = 24;
+const SYSCALL_FCNTL: usize = 25;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_MKDIRAT: usize = 34;
+const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_LINKAT: usize = 37;
+const SYSCALL_UMOUNT2: usize = 39;
+const SYSCALL_MOUNT: usize = 40;
+const SYSCALL_CHDIR: usize = 49;
 const SYSCALL_OPEN: usize = 56;
 const SYSCALL_CLOSE: usize = 57;
 const SYSCALL_PIPE: usize = 59;
 const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_READV: usize = 65;
+const SYSCALL_WRITEV: usize = 66;
+const SYSCALL_NEWFSTATAT: usize = 79;
+const SYSCALL_FSTAT: usize = 80;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_EXIT_GROUP: usize = 94;
+const SYSCALL_SET_TID_ADDRESS: usize = 96;
+const SYSCALL_GETITIMER: usize = 102;
+const SYSCALL_SETITIMER: usize = 103;
+const SYSCALL_CLOCK_GETTIME: usize = 113;
+const SYSCALL_PTRACE: usize = 117;
 const SYSCALL_YIELD: usize = 124;
 const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGALTSTACK: usize = 132;
 const SYSCALL_SIGACTION: usize = 134;
 const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_SIGQUEUE: usize = 138;
 const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SETGID: usize = 144;
+const SYSCALL_SETUID: usize = 146;
+const SYSCALL_TIMES: usize = 153;
+const SYSCALL_UNAME: usize = 160;
+const SYSCALL_GETRLIMIT: usize = 163;
+const SYSCALL_SETRLIMIT: usize = 164;
+const SYSCALL_GETRUSAGE: usize = 165;
 const SYSCALL_GET_TIME: usize = 169;
 const SYSCALL_GETPID: usize = 172;
-const SYSCALL_FORK: usize = 220;
-const SYSCALL_EXEC: usize = 221;
+const SYSCALL_GETPPID: usize = 173;
+const SYSCALL_GETUID: usize = 174;
+const SYSCALL_GETEUID: usize = 175;
+const SYSCALL_GETGID: usize = 176;
+const SYSCALL_GETEGID: usize = 177;
+const SYSCALL_GETTID: usize = 178;
+const SYSCALL_SYSINFO: usize = 179;
+const SYSCALL_BRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
+const SYSCALL_CLONE: usize = 220;
+const SYSCALL_EXECVE: usize = 221;
+const SYSCALL_MMAP: usize = 222;
 const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_PRLIMIT: usize = 261;
+const SYSCALL_RENAMEAT2: usize = 276;
+const SYSCALL_SECCOMP: usize = 277;
+const SYSCALL_GETRANDOM: usize = 278;
+// Extensions without a number in the Linux generic table start from 1001
+const SYSCALL_VFORK: usize = 1001;
+const SYSCALL_SPAWN: usize = 1002;
+const SYSCALL_STRACE: usize = 1003;
+const SYSCALL_RMDIR: usize = 1004;
 
+mod errno;
 mod fs;
+mod linux;
+mod mm;
 mod process;
+mod ptrace;
+mod strace;
 
+pub use errno::*;
 use fs::*;
+use linux::*;
+use mm::*;
 use process::*;
+use ptrace::*;
+use strace::*;
+pub use strace::strace_all;
 use log::*;
-use crate::task::SignalAction;
+use crate::task::{
+    current_filter_syscall, current_task, FilterAction, ITimerVal, Personality, RLimit, RUsage,
+    SeccompFilterSpec, SignalAction, SignalStack, TimeSpec, Tms,
+};
 
-pub fn syscall(syscall_id: usize, args: [usize; 3]) -> isize {
+/// Run syscall `syscall_id` with the six argument registers of the trap,
+/// which handlers take as many of as they need. Return the value for the
+/// return register, which is -errno if it failed.
+pub fn syscall(syscall_id: usize, args: [usize; 6]) -> isize {
     trace!("syscall: id: {}, args: {:?}", syscall_id, args);
+    let traced = strace_enter(syscall_id, &args);
+    let ret = dispatch(syscall_id, args);
+    if let Some(line) = traced {
+        strace_exit(line, syscall_id, &ret);
+    }
+    match ret {
+        Ok(ret) => ret as isize,
+        Err(errno) => errno.as_ret(),
+    }
+}
+
+fn dispatch(syscall_id: usize, args: [usize; 6]) -> SyscallResult {
+    // seccomp filters never see exit and sigreturn, so that a sandboxed
+    // task can always return from its SIGSYS handler and finish
+    if syscall_id != SYSCALL_EXIT && syscall_id != SYSCALL_SIGRETURN {
+        match current_filter_syscall(syscall_id) {
+            Some(FilterAction::Errno(errno)) => return Err(errno),
+            Some(_) => return Err(ENOSYS),
+            None => {}
+        }
+    }
+    let personality = current_task().unwrap().inner_exclusive_access().personality;
+    if personality == Personality::Linux {
+        if let Some(ret) = linux_dispatch(syscall_id, args) {
+            return ret;
+        }
+    }
     match syscall_id {
+        SYSCALL_GETCWD => sys_getcwd(args[0] as *mut u8, args[1]),
         SYSCALL_DUP => sys_dup(args[0]),
+        SYSCALL_FCNTL => sys_fcntl(args[0], args[1], args[2]),
+        SYSCALL_IOCTL => sys_ioctl(args[0], args[1], args[2]),
+        SYSCALL_MKDIRAT => sys_mkdirat(args[0] as isize, args[1] as *const u8, args[2] as u32),
+        SYSCALL_UNLINKAT => sys_unlinkat(args[0] as isize, args[1] as *const u8, args[2] as u32),
+        SYSCALL_LINKAT => sys_linkat(
+            args[0] as isize,
+            args[1] as *const u8,
+            args[2] as isize,
+            args[3] as *const u8,
+            args[4] as u32,
+        ),
+        SYSCALL_UMOUNT2 => sys_umount2(args[0] as *const u8, args[1] as u32),
+        SYSCALL_MOUNT => sys_mount(
+            args[0] as *const u8,
+            args[1] as *const u8,
+            args[2] as *const u8,
+            args[3],
+            args[4] as *const u8,
+        ),
+        SYSCALL_CHDIR => sys_chdir(args[0] as *const u8),
         SYSCALL_OPEN => sys_open(args[0] as *const u8, args[1] as u32),
         SYSCALL_CLOSE => sys_close(args[0]),
         SYSCALL_PIPE => sys_pipe(args[0] as *mut usize),
         SYSCALL_READ => sys_read(args[0], args[1] as *mut u8, args[2]),
         SYSCALL_WRITE => sys_write(args[0], args[1] as *mut u8, args[2]),
+        SYSCALL_READV => sys_readv(args[0], args[1] as *const IoVec, args[2]),
+        SYSCALL_WRITEV => sys_writev(args[0], args[1] as *const IoVec, args[2]),
+        SYSCALL_NEWFSTATAT => sys_newfstatat(
+            args[0] as isize,
+            args[1] as *const u8,
+            args[2] as *mut Kstat,
+            args[3] as u32,
+        ),
+        SYSCALL_FSTAT => sys_fstat(args[0], args[1] as *mut Kstat),
         SYSCALL_EXIT => sys_exit(args[0] as i32),
+        SYSCALL_EXIT_GROUP => sys_exit_group(args[0] as i32),
+        SYSCALL_SET_TID_ADDRESS => sys_set_tid_address(args[0]),
+        SYSCALL_GETITIMER => sys_getitimer(args[0], args[1] as *mut ITimerVal),
+        SYSCALL_SETITIMER => sys_setitimer(
+            args[0],
+            args[1] as *const ITimerVal,
+            args[2] as *mut ITimerVal,
+        ),
+        SYSCALL_CLOCK_GETTIME => sys_clock_gettime(args[0], args[1] as *mut TimeSpec),
+        SYSCALL_PTRACE => sys_ptrace(args[0], args[1], args[2], args[3]),
         SYSCALL_YIELD => sys_yield(),
         SYSCALL_KILL => sys_kill(args[0], args[1] as i32),
+        SYSCALL_SIGALTSTACK => sys_sigaltstack(
+            args[0] as *const SignalStack,
+            args[1] as *mut SignalStack,
+        ),
         SYSCALL_SIGACTION => sys_sigaction(
             args[0] as i32,
             args[1] as *const SignalAction,
             args[2] as *mut SignalAction,
         ),
-        SYSCALL_SIGPROCMASK => sys_sigprocmask(args[0] as u32),
+        SYSCALL_SIGPROCMASK => sys_sigprocmask(args[0], args[1] as *mut u64),
+        SYSCALL_SIGQUEUE => sys_sigqueue(args[0], args[1] as i32, args[2]),
         SYSCALL_SIGRETURN => sys_sigreturn(),
+        SYSCALL_SETGID => sys_setgid(args[0]),
+        SYSCALL_SETUID => sys_setuid(args[0]),
+        SYSCALL_TIMES => sys_times(args[0] as *mut Tms),
+        SYSCALL_UNAME => sys_uname(args[0] as *mut UtsName),
+        SYSCALL_GETRLIMIT => sys_getrlimit(args[0], args[1] as *mut RLimit),
+        SYSCALL_SETRLIMIT => sys_setrlimit(args[0], args[1] as *const RLimit),
+        SYSCALL_GETRUSAGE => sys_getrusage(args[0] as isize, args[1] as *mut RUsage),
         SYSCALL_GET_TIME => sys_get_time(),
         SYSCALL_GETPID => sys_getpid(),
-        SYSCALL_FORK => sys_fork(),
-        SYSCALL_EXEC => sys_exec(args[0] as *const u8, args[1] as *const usize),
-        SYSCALL_WAITPID => sys_waitpid(args[0] as isize, args[1] as *mut i32),
-        _ => panic!("Unsupported syscall_id: {}", syscall_id),
+        SYSCALL_GETPPID => sys_getppid(),
+        SYSCALL_GETUID => sys_getuid(),
+        SYSCALL_GETEUID => sys_geteuid(),
+        SYSCALL_GETGID => sys_getgid(),
+        SYSCALL_GETEGID => sys_getegid(),
+        SYSCALL_GETTID => sys_gettid(),
+        SYSCALL_SYSINFO => sys_sysinfo(args[0] as *mut SysInfo),
+        SYSCALL_BRK => sys_brk(args[0]),
+        SYSCALL_MUNMAP => sys_munmap(args[0], args[1]),
+        SYSCALL_CLONE => sys_clone(
+            args[0],
+            args[1],
+            args[2] as *mut u32,
+            args[3],
+            args[4] as *mut u32,
+        ),
+        SYSCALL_EXECVE => sys_execve(
+            args[0] as *const u8,
+            args[1] as *const usize,
+            args[2] as *const usize,
+        ),
+        SYSCALL_MMAP => sys_mmap(args[0], args[1], args[2], args[3], args[4], args[5]),
+        SYSCALL_WAITPID => sys_waitpid(
+            args[0] as isize,
+            args[1] as *mut i32,
+            args[2] as u32,
+            args[3] as *mut RUsage,
+        ),
+        SYSCALL_PRLIMIT => sys_prlimit(
+            args[0],
+            args[1],
+            args[2] as *const RLimit,
+            args[3] as *mut RLimit,
+        ),
+        SYSCALL_RENAMEAT2 => sys_renameat2(
+            args[0] as isize,
+            args[1] as *const u8,
+            args[2] as isize,
+            args[3] as *const u8,
+            args[4] as u32,
+        ),
+        SYSCALL_SECCOMP => sys_seccomp(args[0], args[1], args[2] as *const SeccompFilterSpec),
+        SYSCALL_GETRANDOM => sys_getrandom(args[0] as *mut u8, args[1], args[2] as u32),
+        SYSCALL_VFORK => sys_vfork(),
+        SYSCALL_SPAWN => sys_spawn(
+            args[0] as *const u8,
+            args[1] as *const usize,
+            args[2] as *const SpawnAttr,
+        ),
+        SYSCALL_STRACE => sys_strace(args[0]),
+        SYSCALL_RMDIR => sys_rmdir(args[0] as *const u8),
+        _ => {
+            warn!("unsupported syscall_id: {}", syscall_id);
+            Err(ENOSYS)
+        }
     }
 }
@@ -1,9 +1,26 @@
-use crate::fs::{open_file, OpenFlags};
-use crate::mm::{translated_ref, translated_refmut, translated_str};
+use crate::config::{PAGE_SIZE, USER_SPACE_END};
+use crate::fs::{open, open_file, OpenFlags};
+use crate::mm::{frame_stats, translated_byte_buffer, translated_ref, translated_refmut};
+use crate::random::{fill_random, random_ready};
+use super::{
+    check_user_range, read_user_str, strace_on_exec, Errno, SyscallResult, E2BIG, EAGAIN, EBADF,
+    ECHILD, EFAULT, EINTR, EINVAL, ELOOP, ENAMETOOLONG, ENOEXEC, ENOMEM, EPERM, ESRCH,
+    MAX_ARG_STRLEN, PATH_MAX,
+};
 use crate::task::{
-    add_task, current_task, current_user_token, exit_current_and_run_next, pid2task,
-    suspend_current_and_run_next, SignalAction, SignalFlags, MAX_SIG,
+    add_task, current_cwd, current_has_interrupting_signal, current_task, current_user_token,
+    exit_current_and_run_next, exited_status, get_itimer, is_stopped_status, now_us, pid2task,
+    pid_count, process_count, set_itimer, suspend_current_and_run_next, thread_group, us_to_ticks,
+    CloneFlags, ExecError, FilterAction, ITimer, ITimerVal, RLimit, RUsage, SeccompFilterSpec,
+    SignalAction, SignalFlags, SignalFrame, SignalInfo, SignalStack, SyscallFilter,
+    TaskControlBlock, TimeSpec, TimeVal, Tms, WaitOptions, CLOCK_MONOTONIC,
+    CLOCK_PROCESS_CPUTIME_ID, CLOCK_REALTIME, CLOCK_THREAD_CPUTIME_ID, CONTINUED_STATUS,
+    ITIMER_PROF, MAX_FILTERS, MAX_FILTER_SYSCALLS, MAX_SIG, MINSIGSTKSZ, RLIMIT_NOFILE,
+    RLIMIT_NPROC, RLIM_NLIMITS, RUSAGE_CHILDREN, RUSAGE_SELF, SECCOMP_MODE_ALLOW_LIST,
+    SECCOMP_MODE_DENY_LIST, SECCOMP_SET_MODE_FILTER, SIG_IGN, SS_DISABLE, SS_ONSTACK,
 };
+use core::mem::size_of;
+use polyhal::pagetable::MappingFlags;
 use alloc::string::String;
 use alloc::sync::Arc;
 use alloc::vec::Vec;
@@ -12,183 +29,1007 @@ use log::info;
 use polyhal::trapframe::TrapFrameArgs;
 
 pub fn sys_exit(exit_code: i32) -> ! {
-    exit_current_and_run_next(exit_code);
+    exit_current_and_run_next(exited_status(exit_code));
     panic!("Unreachable in sys_exit!");
 }
 
-pub fn sys_yield() -> isize {
+/// Exit every thread of the process, the others are killed by SIGKILL.
+pub fn sys_exit_group(exit_code: i32) -> ! {
+    let task = current_task().unwrap();
+    let sigkill = SignalFlags::SIGKILL;
+    for thread in thread_group(task.tgid) {
+        if !Arc::ptr_eq(&thread, &task) {
+            let info = SignalInfo::kernel(sigkill.signum(), 0);
+            thread.inner_exclusive_access().add_signal(sigkill, info);
+        }
+    }
+    drop(task);
+    sys_exit(exit_code)
+}
+
+/// Clear `*tidptr` when the current task exits, and return its tid.
+pub fn sys_set_tid_address(tidptr: usize) -> SyscallResult {
+    let task = current_task().unwrap();
+    task.inner_exclusive_access().clear_child_tid = tidptr;
+    Ok(task.getpid())
+}
+
+/// Linux `struct utsname`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct UtsName {
+    pub sysname: [u8; 65],
+    pub nodename: [u8; 65],
+    pub release: [u8; 65],
+    pub version: [u8; 65],
+    pub machine: [u8; 65],
+    pub domainname: [u8; 65],
+}
+
+/// `string` as a null-terminated utsname field.
+fn uts_field(string: &str) -> [u8; 65] {
+    let mut field = [0u8; 65];
+    field[..string.len()].copy_from_slice(string.as_bytes());
+    field
+}
+
+/// Report the system as Linux, whose programs check the release for
+/// the syscalls they may use.
+pub fn sys_uname(buf: *mut UtsName) -> SyscallResult {
+    check_user_range(buf as usize, size_of::<UtsName>(), MappingFlags::W)?;
+    let machine = if cfg!(target_arch = "riscv64") {
+        "riscv64"
+    } else if cfg!(target_arch = "aarch64") {
+        "aarch64"
+    } else if cfg!(target_arch = "x86_64") {
+        "x86_64"
+    } else {
+        "loongarch64"
+    };
+    *translated_refmut(current_user_token(), buf) = UtsName {
+        sysname: uts_field("Linux"),
+        nodename: uts_field("rcore"),
+        release: uts_field("5.15.0"),
+        version: uts_field(concat!("rCore-Tutorial-v3 ", env!("CARGO_PKG_VERSION"))),
+        machine: uts_field(machine),
+        domainname: uts_field(""),
+    };
+    Ok(0)
+}
+
+/// Linux `struct sysinfo` on 64-bit architectures
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SysInfo {
+    pub uptime: isize,
+    pub loads: [usize; 3],
+    pub totalram: usize,
+    pub freeram: usize,
+    pub sharedram: usize,
+    pub bufferram: usize,
+    pub totalswap: usize,
+    pub freeswap: usize,
+    pub procs: u16,
+    pub pad: u16,
+    pub totalhigh: usize,
+    pub freehigh: usize,
+    pub mem_unit: u32,
+}
+
+/// Report the uptime in seconds, memory in frames and the process count.
+/// There are no load averages, swap or high memory.
+pub fn sys_sysinfo(info: *mut SysInfo) -> SyscallResult {
+    check_user_range(info as usize, size_of::<SysInfo>(), MappingFlags::W)?;
+    let (total, free) = frame_stats();
+    *translated_refmut(current_user_token(), info) = SysInfo {
+        uptime: (now_us() / 1_000_000) as isize,
+        totalram: total,
+        freeram: free,
+        procs: process_count().min(u16::MAX as usize) as u16,
+        mem_unit: PAGE_SIZE as u32,
+        ..SysInfo::default()
+    };
+    Ok(0)
+}
+
+// getrandom flags
+const GRND_NONBLOCK: u32 = 1;
+const GRND_RANDOM: u32 = 2;
+const GRND_INSECURE: u32 = 4;
+
+/// Fill `buf` from the kernel random number generator, waiting until it is
+/// seeded unless GRND_INSECURE is given. GRND_RANDOM makes no difference.
+pub fn sys_getrandom(buf: *mut u8, len: usize, flags: u32) -> SyscallResult {
+    if flags & !(GRND_NONBLOCK | GRND_RANDOM | GRND_INSECURE) != 0 {
+        return Err(EINVAL);
+    }
+    check_user_range(buf as usize, len, MappingFlags::W)?;
+    while flags & GRND_INSECURE == 0 && !random_ready() {
+        if flags & GRND_NONBLOCK != 0 {
+            return Err(EAGAIN);
+        }
+        if current_has_interrupting_signal() {
+            return Err(EINTR);
+        }
+        suspend_current_and_run_next();
+    }
+    for part in translated_byte_buffer(current_user_token(), buf, len) {
+        fill_random(part);
+    }
+    Ok(len)
+}
+
+pub fn sys_yield() -> SyscallResult {
     suspend_current_and_run_next();
-    0
+    Ok(0)
 }
 
-pub fn sys_get_time() -> isize {
-    Time::now().to_msec() as isize
+pub fn sys_get_time() -> SyscallResult {
+    Ok(Time::now().to_msec())
 }
 
-pub fn sys_getpid() -> isize {
-    current_task().unwrap().pid.0 as isize
+/// Fill in the CPU time of the current task and its reaped children.
+/// Return the clock ticks since boot.
+pub fn sys_times(tms: *mut Tms) -> SyscallResult {
+    if !tms.is_null() {
+        check_user_range(tms as usize, size_of::<Tms>(), MappingFlags::W)?;
+    }
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if !tms.is_null() {
+        *translated_refmut(inner.get_user_token(), tms) =
+            Tms::new(&inner.usage, &inner.children_usage);
+    }
+    Ok(us_to_ticks(now_us()))
+}
+
+pub fn sys_getrusage(who: isize, usage: *mut RUsage) -> SyscallResult {
+    check_user_range(usage as usize, size_of::<RUsage>(), MappingFlags::W)?;
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let rusage = match who {
+        RUSAGE_SELF => RUsage::from(&inner.usage),
+        RUSAGE_CHILDREN => RUsage::from(&inner.children_usage),
+        _ => return Err(EINVAL),
+    };
+    *translated_refmut(inner.get_user_token(), usage) = rusage;
+    Ok(0)
+}
+
+/// There is no RTC, so CLOCK_REALTIME counts from boot like CLOCK_MONOTONIC.
+pub fn sys_clock_gettime(clock_id: usize, tp: *mut TimeSpec) -> SyscallResult {
+    check_user_range(tp as usize, size_of::<TimeSpec>(), MappingFlags::W)?;
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let us = match clock_id {
+        CLOCK_REALTIME | CLOCK_MONOTONIC => now_us(),
+        // a process has a single thread
+        CLOCK_PROCESS_CPUTIME_ID | CLOCK_THREAD_CPUTIME_ID => {
+            inner.usage.user_time + inner.usage.system_time
+        }
+        _ => return Err(EINVAL),
+    };
+    *translated_refmut(inner.get_user_token(), tp) = TimeSpec::from_us(us);
+    Ok(0)
+}
+
+pub fn sys_getrlimit(resource: usize, rlim: *mut RLimit) -> SyscallResult {
+    sys_prlimit(0, resource, core::ptr::null(), rlim)
+}
+
+pub fn sys_setrlimit(resource: usize, rlim: *const RLimit) -> SyscallResult {
+    sys_prlimit(0, resource, rlim, core::ptr::null_mut())
 }
 
-pub fn sys_fork() -> isize {
+/// Get and set a resource limit of task `pid`, or of the current task if
+/// `pid` is 0. Only root can raise a hard limit.
+pub fn sys_prlimit(
+    pid: usize,
+    resource: usize,
+    new_rlim: *const RLimit,
+    old_rlim: *mut RLimit,
+) -> SyscallResult {
+    if resource >= RLIM_NLIMITS {
+        return Err(EINVAL);
+    }
+    if !new_rlim.is_null() {
+        check_user_range(new_rlim as usize, size_of::<RLimit>(), MappingFlags::R)?;
+    }
+    if !old_rlim.is_null() {
+        check_user_range(old_rlim as usize, size_of::<RLimit>(), MappingFlags::W)?;
+    }
+    let token = current_user_token();
+    let creds = current_task().unwrap().inner_exclusive_access().creds;
+    let task = match pid {
+        0 => current_task(),
+        pid => pid2task(pid),
+    };
+    let task = task.ok_or(ESRCH)?;
+    let mut inner = task.inner_exclusive_access();
+    if !creds.can_signal(&inner.creds) {
+        return Err(EPERM);
+    }
+    let old = inner.rlimits[resource];
+    if !new_rlim.is_null() {
+        let new = *translated_ref(token, new_rlim);
+        if new.cur > new.max {
+            return Err(EINVAL);
+        }
+        if new.max > old.max && !creds.is_root() {
+            return Err(EPERM);
+        }
+        inner.rlimits[resource] = new;
+    }
+    if !old_rlim.is_null() {
+        *translated_refmut(token, old_rlim) = old;
+    }
+    Ok(0)
+}
+
+/// Add the syscall filter `*args` to the current task, which keeps it for
+/// good and passes it on to its children and new images.
+pub fn sys_seccomp(
+    operation: usize,
+    flags: usize,
+    args: *const SeccompFilterSpec,
+) -> SyscallResult {
+    if operation != SECCOMP_SET_MODE_FILTER || flags != 0 {
+        return Err(EINVAL);
+    }
+    let spec_size = size_of::<SeccompFilterSpec>();
+    check_user_range(args as usize, spec_size, MappingFlags::R)?;
+    let token = current_user_token();
+    let spec = *translated_ref(token, args);
+    let allow_list = match spec.mode {
+        SECCOMP_MODE_ALLOW_LIST => true,
+        SECCOMP_MODE_DENY_LIST => false,
+        _ => return Err(EINVAL),
+    };
+    let action = FilterAction::from_ret(spec.action).ok_or(EINVAL)?;
+    if spec.syscalls_len > MAX_FILTER_SYSCALLS {
+        return Err(EINVAL);
+    }
+    let list_size = spec.syscalls_len * size_of::<usize>();
+    check_user_range(spec.syscalls as usize, list_size, MappingFlags::R)?;
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner.syscall_filters.len() >= MAX_FILTERS {
+        return Err(ENOMEM);
+    }
+    let syscalls = (0..spec.syscalls_len)
+        .map(|i| *translated_ref(token, unsafe { spec.syscalls.add(i) }))
+        .collect();
+    inner.syscall_filters.push(Arc::new(SyscallFilter {
+        allow_list,
+        action,
+        syscalls,
+    }));
+    Ok(0)
+}
+
+pub fn sys_getitimer(which: usize, curr_value: *mut ITimerVal) -> SyscallResult {
+    if which > ITIMER_PROF {
+        return Err(EINVAL);
+    }
+    check_user_range(curr_value as usize, size_of::<ITimerVal>(), MappingFlags::W)?;
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let timer = get_itimer(&inner, which);
+    *translated_refmut(token, curr_value) = ITimerVal {
+        interval: TimeVal::from_us(timer.interval),
+        value: TimeVal::from_us(timer.value),
+    };
+    Ok(0)
+}
+
+/// `old_value` may be null.
+pub fn sys_setitimer(
+    which: usize,
+    new_value: *const ITimerVal,
+    old_value: *mut ITimerVal,
+) -> SyscallResult {
+    if which > ITIMER_PROF {
+        return Err(EINVAL);
+    }
+    check_user_range(new_value as usize, size_of::<ITimerVal>(), MappingFlags::R)?;
+    if !old_value.is_null() {
+        check_user_range(old_value as usize, size_of::<ITimerVal>(), MappingFlags::W)?;
+    }
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let new_value = translated_ref(token, new_value);
+    let new = ITimer {
+        interval: new_value.interval.to_us(),
+        value: new_value.value.to_us(),
+    };
+    let old = set_itimer(task.getpid(), &mut inner, which, new);
+    if !old_value.is_null() {
+        *translated_refmut(token, old_value) = ITimerVal {
+            interval: TimeVal::from_us(old.interval),
+            value: TimeVal::from_us(old.value),
+        };
+    }
+    Ok(0)
+}
+
+/// Return the pid of the process, which all of its threads share.
+pub fn sys_getpid() -> SyscallResult {
+    Ok(current_task().unwrap().tgid)
+}
+
+/// Return the id of the calling thread.
+pub fn sys_gettid() -> SyscallResult {
+    Ok(current_task().unwrap().getpid())
+}
+
+/// Return the pid of the parent, or 0 for initproc which has none.
+pub fn sys_getppid() -> SyscallResult {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    Ok(inner
+        .parent
+        .as_ref()
+        .and_then(|parent| parent.upgrade())
+        .map_or(0, |parent| parent.tgid))
+}
+
+pub fn sys_getuid() -> SyscallResult {
+    Ok(current_task().unwrap().inner_exclusive_access().creds.uid)
+}
+
+pub fn sys_geteuid() -> SyscallResult {
+    Ok(current_task().unwrap().inner_exclusive_access().creds.euid)
+}
+
+pub fn sys_getgid() -> SyscallResult {
+    Ok(current_task().unwrap().inner_exclusive_access().creds.gid)
+}
+
+pub fn sys_getegid() -> SyscallResult {
+    Ok(current_task().unwrap().inner_exclusive_access().creds.egid)
+}
+
+pub fn sys_setuid(uid: usize) -> SyscallResult {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner.creds.set_uid(uid) {
+        Ok(0)
+    } else {
+        Err(EPERM)
+    }
+}
+
+pub fn sys_setgid(gid: usize) -> SyscallResult {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner.creds.set_gid(gid) {
+        Ok(0)
+    } else {
+        Err(EPERM)
+    }
+}
+
+/// Create a child which shares with the current task what `flags` asks
+/// for and has copies of the rest, see `CloneFlags`. The lowest byte of
+/// `flags` is the signal sent to the parent when the child exits.
+/// The child starts on `stack` unless it is 0. Return the tid of the child.
+pub fn sys_clone(
+    flags: usize,
+    stack: usize,
+    ptid: *mut u32,
+    tls: usize,
+    ctid: *mut u32,
+) -> SyscallResult {
+    let (flags, exit_signal) = match CloneFlags::parse(flags) {
+        Some((flags, exit_signal)) if exit_signal <= MAX_SIG => (flags, exit_signal),
+        _ => return Err(EINVAL),
+    };
+    if flags.contains(CloneFlags::CLONE_PARENT_SETTID) {
+        check_user_range(ptid as usize, size_of::<u32>(), MappingFlags::W)?;
+    }
     let current_task = current_task().unwrap();
-    let new_task = current_task.fork();
+    if pid_count() >= current_task.inner_exclusive_access().rlimits[RLIMIT_NPROC].cur {
+        return Err(EAGAIN);
+    }
+    let new_task = current_task.fork(flags).ok_or(ENOMEM)?;
     let new_pid = new_task.pid.0;
+    let mut new_inner = new_task.inner_exclusive_access();
+    new_inner.exit_signal = exit_signal;
+    if flags.contains(CloneFlags::CLONE_CHILD_CLEARTID) {
+        new_inner.clear_child_tid = ctid as usize;
+    }
     // modify trap context of new_task, because it returns immediately after switching
-    let trap_cx = new_task.inner_exclusive_access().get_trap_cx();
+    let trap_cx = new_inner.get_trap_cx();
     // we do not have to move to next instruction since we have done it before
-    // for child process, fork returns 0
+    // for child process, clone returns 0
     trap_cx[TrapFrameArgs::RET] = 0;
+    if stack != 0 {
+        trap_cx[TrapFrameArgs::SP] = stack;
+    }
+    if flags.contains(CloneFlags::CLONE_SETTLS) {
+        trap_cx[TrapFrameArgs::TLS] = tls;
+    }
+    drop(new_inner);
+    if flags.contains(CloneFlags::CLONE_PARENT_SETTID) {
+        *translated_refmut(current_user_token(), ptid) = new_pid as u32;
+    }
     // add new task to scheduler
-    add_task(new_task);
-    new_pid as isize
+    add_task(new_task.clone());
+    // a vfork child runs in our memory, so we sleep until it gives it back
+    if flags.contains(CloneFlags::CLONE_VFORK) {
+        while new_task.inner_exclusive_access().borrowed_vm {
+            suspend_current_and_run_next();
+        }
+    }
+    Ok(new_pid)
+}
+
+/// Like `sys_clone` for a plain fork, but the child runs in the memory of
+/// the current task, which sleeps until the child execs or exits.
+pub fn sys_vfork() -> SyscallResult {
+    let flags = CloneFlags::CLONE_VM | CloneFlags::CLONE_VFORK;
+    sys_clone(
+        flags.bits() | SignalFlags::SIGCHLD.signum(),
+        0,
+        core::ptr::null_mut(),
+        0,
+        core::ptr::null_mut(),
+    )
 }
 
-pub fn sys_exec(path: *const u8, mut args: *const usize) -> isize {
+/// Read a null-terminated array of pointers to the strings of exec, a null
+/// array is empty. Fail with E2BIG if a string is too long.
+fn read_user_str_array(mut ptrs: *const usize) -> Result<Vec<String>, Errno> {
+    let mut strings = Vec::new();
+    if ptrs.is_null() {
+        return Ok(strings);
+    }
     let token = current_user_token();
-    info!("sys_exec: {:p}  args: {:p}", path, args);
-    // log::info!("path: {:p} token: {:#x} kernel token: {:#x}", path, current_user_token(), kernel_page_table_token());
-    let path = translated_str(token, path);
-    let mut args_vec: Vec<String> = Vec::new();
     loop {
-        let arg_str_ptr = *translated_ref(token, args);
-        if arg_str_ptr == 0 {
+        check_user_range(ptrs as usize, size_of::<usize>(), MappingFlags::R)?;
+        let str_ptr = *translated_ref(token, ptrs);
+        if str_ptr == 0 {
             break;
         }
-        args_vec.push(translated_str(token, arg_str_ptr as *const u8));
+        let string = match read_user_str(str_ptr as *const u8, MAX_ARG_STRLEN) {
+            Err(ENAMETOOLONG) => Err(E2BIG),
+            string => string,
+        }?;
+        strings.push(string);
         unsafe {
-            args = args.add(1);
+            ptrs = ptrs.add(1);
         }
     }
-    if let Some(app_inode) = open_file(path.as_str(), OpenFlags::RDONLY) {
+    Ok(strings)
+}
+
+/// How many scripts may be run by interpreters which are scripts themselves
+const MAX_INTERPRETER_DEPTH: usize = 4;
+/// Like Linux, only this much of a `#!` line is read
+const MAX_SHEBANG_LEN: usize = 256;
+
+/// Parse the `#!interpreter [argument]` line at the start of a script.
+/// Return None if `data` is not a script, or Some(None) if the line names
+/// no interpreter.
+fn parse_shebang(data: &[u8]) -> Option<Option<(String, Option<String>)>> {
+    let line = data.strip_prefix(b"#!")?;
+    let line = &line[..line.len().min(MAX_SHEBANG_LEN)];
+    let line = line.split(|&c| c == b'\n').next().unwrap();
+    let line = match core::str::from_utf8(line) {
+        Ok(line) => line.trim(),
+        Err(_) => return Some(None),
+    };
+    // the rest of the line after the interpreter is one argument
+    let (interpreter, argument) = match line.split_once(|c: char| c.is_ascii_whitespace()) {
+        Some((interpreter, argument)) => (interpreter, Some(argument.trim())),
+        None => (line, None),
+    };
+    if interpreter.is_empty() {
+        return Some(None);
+    }
+    Some(Some((
+        String::from(interpreter),
+        argument.filter(|arg| !arg.is_empty()).map(String::from),
+    )))
+}
+
+/// Read the executable at `path`, following `#!` lines to the interpreter
+/// which runs it. Return the ELF data and the arguments to start it with.
+fn load_executable(
+    mut path: String,
+    mut args: Vec<String>,
+) -> Result<(Vec<u8>, Vec<String>), Errno> {
+    for _ in 0..=MAX_INTERPRETER_DEPTH {
+        let app_inode = open_file(&current_cwd(), path.as_str(), OpenFlags::RDONLY)?;
         let all_data = app_inode.read_all();
-        let task = current_task().unwrap();
-        let argc = args_vec.len();
-        task.exec(all_data.as_slice(), args_vec);
-        // return argc because cx.x[10] will be covered with it later
-        argc as isize
-    } else {
-        -1
+        match parse_shebang(&all_data) {
+            // run `interpreter [argument] path args[1..]` instead
+            Some(Some((interpreter, argument))) => {
+                let mut new_args = Vec::from([interpreter.clone()]);
+                new_args.extend(argument);
+                new_args.push(path);
+                new_args.extend(args.into_iter().skip(1));
+                args = new_args;
+                path = interpreter;
+            }
+            Some(None) => return Err(ENOEXEC),
+            None => return Ok((all_data, args)),
+        }
     }
+    Err(ELOOP)
 }
 
-/// If there is not a child process whose pid is same as given, return -1.
-/// Else if there is a child process but it is still running, return -2.
-pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32) -> isize {
-    let task = current_task().unwrap();
-    // find a child process
+fn exec_errno(err: ExecError) -> Errno {
+    match err {
+        ExecError::NotExecutable => ENOEXEC,
+        ExecError::TooBig => E2BIG,
+        ExecError::NoMemory => ENOMEM,
+    }
+}
 
-    // ---- access current PCB exclusively
+pub fn sys_execve(path: *const u8, args: *const usize, envs: *const usize) -> SyscallResult {
+    info!("sys_execve: {:p}  args: {:p} envs: {:p}", path, args, envs);
+    // log::info!("path: {:p} token: {:#x} kernel token: {:#x}", path, current_user_token(), kernel_page_table_token());
+    let path = read_user_str(path, PATH_MAX)?;
+    let args_vec = read_user_str_array(args)?;
+    let envs_vec = read_user_str_array(envs)?;
+    let strace = strace_on_exec(&path);
+    let (all_data, args_vec) = load_executable(path, args_vec)?;
+    let task = current_task().unwrap();
+    let argc = args_vec.len();
+    task.exec(all_data.as_slice(), args_vec, envs_vec)
+        .map_err(exec_errno)?;
     let mut inner = task.inner_exclusive_access();
-    if !inner
-        .children
-        .iter()
-        .any(|p| pid == -1 || pid as usize == p.getpid())
-    {
-        return -1;
-        // ---- release current PCB
+    inner.strace |= strace;
+    // a tracer gets the chance to look at the new image before it runs
+    if inner.tracer.is_some() {
+        let sigtrap = SignalFlags::SIGTRAP;
+        inner.add_signal(sigtrap, SignalInfo::kernel(sigtrap.signum(), 0));
     }
-    let pair = inner.children.iter().enumerate().find(|(_, p)| {
-        // ++++ temporarily access child PCB exclusively
-        p.inner_exclusive_access().is_zombie() && (pid == -1 || pid as usize == p.getpid())
-        // ++++ release child PCB
-    });
-    if let Some((idx, _)) = pair {
-        let child = inner.children.remove(idx);
-        // confirm that child will be deallocated after being removed from children list
-        assert_eq!(Arc::strong_count(&child), 1);
-        let found_pid = child.getpid();
-        // ++++ temporarily access child PCB exclusively
-        let exit_code = child.inner_exclusive_access().exit_code;
-        // ++++ release child PCB
-        *translated_refmut(inner.memory_set.token(), exit_code_ptr) = exit_code;
-        found_pid as isize
+    // return argc because cx.x[10] will be covered with it later
+    Ok(argc)
+}
+
+pub const SPAWN_OPEN: usize = 0;
+pub const SPAWN_CLOSE: usize = 1;
+pub const SPAWN_DUP2: usize = 2;
+/// Most file actions one spawn takes
+const MAX_SPAWN_ACTIONS: usize = 64;
+
+/// A change to the fd table of a spawned child, applied in order before
+/// it starts
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SpawnAction {
+    // SPAWN_OPEN, SPAWN_CLOSE or SPAWN_DUP2
+    pub op: usize,
+    pub fd: usize,
+    // open flags for SPAWN_OPEN, the target fd for SPAWN_DUP2
+    pub arg: usize,
+    // path for SPAWN_OPEN
+    pub path: *const u8,
+}
+
+/// Optional settings of `sys_spawn`, null pointers mean none
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SpawnAttr {
+    pub envs: *const usize,
+    pub actions: *const SpawnAction,
+    pub actions_len: usize,
+}
+
+/// Apply a spawn file action to the fd table of `child`.
+fn apply_spawn_action(child: &TaskControlBlock, action: &SpawnAction) -> Result<(), Errno> {
+    let mut inner = child.inner_exclusive_access();
+    let file = match action.op {
+        SPAWN_OPEN => {
+            let path = read_user_str(action.path, PATH_MAX)?;
+            let flags = OpenFlags::from_bits(action.arg as u32).ok_or(EINVAL)?;
+            let cwd = inner.cwd.exclusive_access().clone();
+            Some(open(&cwd, path.as_str(), flags)?)
+        }
+        SPAWN_CLOSE => None,
+        SPAWN_DUP2 => match inner.fd_table.exclusive_access().get(action.fd) {
+            Some(Some(file)) => Some(file.clone()),
+            _ => return Err(EBADF),
+        },
+        _ => return Err(EINVAL),
+    };
+    let fd = if action.op == SPAWN_DUP2 {
+        action.arg
     } else {
-        -2
+        action.fd
+    };
+    if fd >= inner.rlimits[RLIMIT_NOFILE].cur {
+        return Err(EBADF);
     }
-    // ---- release current PCB automatically
+    let mut fd_table = inner.fd_table.exclusive_access();
+    if fd >= fd_table.len() {
+        fd_table.resize(fd + 1, None);
+    }
+    fd_table[fd] = file;
+    Ok(())
 }
 
-pub fn sys_kill(pid: usize, signum: i32) -> isize {
-    if let Some(task) = pid2task(pid) {
-        if let Some(flag) = SignalFlags::from_bits(1 << signum) {
-            // insert the signal if legal
-            let mut task_ref = task.inner_exclusive_access();
-            if task_ref.signals.contains(flag) {
-                return -1;
-            }
-            task_ref.signals.insert(flag);
-            0
-        } else {
-            -1
-        }
+/// Start `path` with `args` in a new child process without copying the
+/// current one. Return the pid of the child.
+pub fn sys_spawn(path: *const u8, args: *const usize, attr: *const SpawnAttr) -> SyscallResult {
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    if pid_count() >= task.inner_exclusive_access().rlimits[RLIMIT_NPROC].cur {
+        return Err(EAGAIN);
+    }
+    let path = read_user_str(path, PATH_MAX)?;
+    let args_vec = read_user_str_array(args)?;
+    let (envs_vec, actions) = if attr.is_null() {
+        (Vec::new(), Vec::new())
     } else {
-        -1
+        check_user_range(attr as usize, size_of::<SpawnAttr>(), MappingFlags::R)?;
+        let attr = translated_ref(token, attr);
+        if attr.actions_len > MAX_SPAWN_ACTIONS {
+            return Err(EINVAL);
+        }
+        let actions_size = attr.actions_len * size_of::<SpawnAction>();
+        check_user_range(attr.actions as usize, actions_size, MappingFlags::R)?;
+        let actions: Vec<SpawnAction> = (0..attr.actions_len)
+            .map(|i| *translated_ref(token, unsafe { attr.actions.add(i) }))
+            .collect();
+        (read_user_str_array(attr.envs)?, actions)
+    };
+    let strace = strace_on_exec(&path);
+    let (all_data, args_vec) = load_executable(path, args_vec)?;
+    let child = task
+        .spawn(all_data.as_slice(), args_vec, envs_vec)
+        .map_err(exec_errno)?;
+    child.inner_exclusive_access().strace |= strace;
+    // file actions open paths in the memory of the parent
+    if let Err(errno) = actions
+        .iter()
+        .try_for_each(|action| apply_spawn_action(&child, action))
+    {
+        task.inner_exclusive_access()
+            .children
+            .retain(|c| !Arc::ptr_eq(c, &child));
+        return Err(errno);
     }
+    let pid = child.getpid();
+    add_task(child);
+    Ok(pid)
 }
 
-pub fn sys_sigprocmask(mask: u32) -> isize {
-    if let Some(task) = current_task() {
+/// If there is not a child process whose pid is same as given, fail with
+/// ECHILD. Else if there is a child process but it is still running, block
+/// until it changes state, or return 0 at once if WNOHANG is given.
+/// Fail with EINTR if a signal handler has to run while blocking.
+/// The Linux encoded wait status is written to `wstatus` when it is not null.
+pub fn sys_waitpid(
+    pid: isize,
+    wstatus: *mut i32,
+    options: u32,
+    rusage: *mut RUsage,
+) -> SyscallResult {
+    let options = WaitOptions::from_bits(options).ok_or(EINVAL)?;
+    if !wstatus.is_null() {
+        check_user_range(wstatus as usize, size_of::<i32>(), MappingFlags::W)?;
+    }
+    // only a reaped child reports what it used
+    if !rusage.is_null() {
+        check_user_range(rusage as usize, size_of::<RUsage>(), MappingFlags::W)?;
+        *translated_refmut(current_user_token(), rusage) = RUsage::default();
+    }
+    let task = current_task().unwrap();
+    loop {
+        // ---- access current PCB exclusively
         let mut inner = task.inner_exclusive_access();
-        let old_mask = inner.signal_mask;
-        if let Some(flag) = SignalFlags::from_bits(mask) {
-            inner.signal_mask = flag;
-            old_mask.bits() as isize
-        } else {
-            -1
+        // threads are reaped without being waited for, their time counts
+        // as the time of the process
+        while let Some(idx) = inner
+            .children
+            .iter()
+            .position(|p| p.is_thread() && p.inner_exclusive_access().is_zombie())
+        {
+            let thread = inner.children.remove(idx);
+            let thread_inner = thread.inner_exclusive_access();
+            inner.usage.add(&thread_inner.usage);
+            inner.children_usage.add(&thread_inner.children_usage);
         }
-    } else {
-        -1
+        // find a child process, or a task we trace
+        inner.tracees.retain(|t| t.strong_count() > 0);
+        if !inner
+            .children
+            .iter()
+            .any(|p| !p.is_thread() && (pid == -1 || pid as usize == p.getpid()))
+            && !inner
+                .tracees
+                .iter()
+                .filter_map(|t| t.upgrade())
+                .any(|t| pid == -1 || pid as usize == t.getpid())
+        {
+            return Err(ECHILD);
+            // ---- release current PCB
+        }
+        let pair = inner.children.iter().enumerate().find(|(_, p)| {
+            // ++++ temporarily access child PCB exclusively
+            !p.is_thread()
+                && p.inner_exclusive_access().is_zombie()
+                && (pid == -1 || pid as usize == p.getpid())
+            // ++++ release child PCB
+        });
+        if let Some((idx, _)) = pair {
+            let child = inner.children.remove(idx);
+            // confirm that child will be deallocated after being removed from children list
+            assert_eq!(Arc::strong_count(&child), 1);
+            let found_pid = child.getpid();
+            // ++++ temporarily access child PCB exclusively
+            let child_inner = child.inner_exclusive_access();
+            let exit_code = child_inner.exit_code;
+            // the parent takes over what the child and its children used
+            let mut child_usage = child_inner.usage;
+            child_usage.add(&child_inner.children_usage);
+            inner.children_usage.add(&child_usage);
+            drop(child_inner);
+            if !rusage.is_null() {
+                *translated_refmut(inner.get_user_token(), rusage) = RUsage::from(&child_usage);
+            }
+            // ++++ release child PCB
+            if !wstatus.is_null() {
+                *translated_refmut(inner.get_user_token(), wstatus) = exit_code;
+            }
+            return Ok(found_pid);
+        }
+        // report stopped or continued children if asked to
+        for child in inner.children.iter() {
+            if child.is_thread() || (pid != -1 && pid as usize != child.getpid()) {
+                continue;
+            }
+            // ++++ temporarily access child PCB exclusively
+            let mut child_inner = child.inner_exclusive_access();
+            let status = match child_inner.state_change {
+                Some(status) => status,
+                None => continue,
+            };
+            // a stop for us as tracer is reported without WUNTRACED
+            if (is_stopped_status(status)
+                && (options.contains(WaitOptions::WUNTRACED) || child_inner.trace_stopped))
+                || (status == CONTINUED_STATUS && options.contains(WaitOptions::WCONTINUED))
+            {
+                child_inner.state_change = None;
+                if !wstatus.is_null() {
+                    *translated_refmut(inner.get_user_token(), wstatus) = status;
+                }
+                return Ok(child.getpid());
+            }
+            // ++++ release child PCB
+        }
+        // tasks we trace but are not the parent of report their trace stops
+        // and their exit to us as well
+        let mut reported = None;
+        for (idx, tracee) in inner.tracees.iter().enumerate() {
+            let tracee = match tracee.upgrade() {
+                Some(tracee) => tracee,
+                None => continue,
+            };
+            if (pid != -1 && pid as usize != tracee.getpid())
+                || inner.children.iter().any(|c| Arc::ptr_eq(c, &tracee))
+            {
+                continue;
+            }
+            let mut tracee_inner = tracee.inner_exclusive_access();
+            if tracee_inner.is_zombie() {
+                reported = Some((Some(idx), tracee.getpid(), tracee_inner.exit_code));
+                break;
+            }
+            if let (true, Some(status)) = (tracee_inner.trace_stopped, tracee_inner.state_change) {
+                tracee_inner.state_change = None;
+                reported = Some((None, tracee.getpid(), status));
+                break;
+            }
+        }
+        if let Some((idx, found_pid, status)) = reported {
+            // an exited tracee is only reported once
+            if let Some(idx) = idx {
+                inner.tracees.remove(idx);
+            }
+            if !wstatus.is_null() {
+                *translated_refmut(inner.get_user_token(), wstatus) = status;
+            }
+            return Ok(found_pid);
+        }
+        if options.contains(WaitOptions::WNOHANG) {
+            return Ok(0);
+        }
+        drop(inner);
+        if current_has_interrupting_signal() {
+            return Err(EINTR);
+        }
+        // ---- release current PCB
+        suspend_current_and_run_next();
     }
 }
 
-pub fn sys_sigreturn() -> isize {
-    if let Some(task) = current_task() {
-        let mut inner = task.inner_exclusive_access();
-        inner.handling_sig = -1;
-        // restore the trap context
-        let trap_ctx = inner.get_trap_cx();
-        *trap_ctx = inner.trap_ctx_backup.clone().unwrap();
-        // Here we return the value of a0 in the trap_ctx,
-        // otherwise it will be overwritten after we trap
-        // back to the original execution of the application.
-        trap_ctx[TrapFrameArgs::RET] as isize
+/// Standard signals which are already pending collapse into one,
+/// real-time signals are queued.
+pub fn sys_kill(pid: usize, signum: i32) -> SyscallResult {
+    let sender = current_task().unwrap().getpid();
+    send_signal(pid, signum, SignalInfo::user(signum as usize, sender))
+}
+
+/// Queue signal `signum` with `value` to process `pid`.
+pub fn sys_sigqueue(pid: usize, signum: i32, value: usize) -> SyscallResult {
+    let sender = current_task().unwrap().getpid();
+    send_signal(pid, signum, SignalInfo::queue(signum as usize, sender, value))
+}
+
+fn send_signal(pid: usize, signum: i32, info: SignalInfo) -> SyscallResult {
+    let creds = current_task().unwrap().inner_exclusive_access().creds;
+    let task = pid2task(pid).ok_or(ESRCH)?;
+    if signum < 0 {
+        return Err(EINVAL);
+    }
+    // only processes of the same user can be signaled, unless by root
+    if !creds.can_signal(&task.inner_exclusive_access().creds) {
+        return Err(EPERM);
+    }
+    if signum == 0 {
+        // only check that the process exists
+        return Ok(0);
+    }
+    let flag = SignalFlags::from_signum(signum as usize).ok_or(EINVAL)?;
+    // insert the signal if legal
+    let mut task_ref = task.inner_exclusive_access();
+    if task_ref.add_signal(flag, info) {
+        Ok(0)
     } else {
-        -1
+        // the real-time signal queue is full
+        Err(EAGAIN)
     }
 }
 
-fn check_sigaction_error(signal: SignalFlags, action: usize, old_action: usize) -> bool {
-    if action == 0
-        || old_action == 0
-        || signal == SignalFlags::SIGKILL
-        || signal == SignalFlags::SIGSTOP
-    {
-        true
-    } else {
-        false
+/// Set the signal mask to `mask`, and report the old one in `old_mask`
+/// unless it is null. A mask does not fit in the return value, as bit 63
+/// would make it look like an error. SIGKILL and SIGSTOP are left out of
+/// the mask.
+pub fn sys_sigprocmask(mask: usize, old_mask: *mut u64) -> SyscallResult {
+    let flag = SignalFlags::from_bits(mask as u64).ok_or(EINVAL)?;
+    if !old_mask.is_null() {
+        check_user_range(old_mask as usize, size_of::<u64>(), MappingFlags::W)?;
+    }
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if !old_mask.is_null() {
+        *translated_refmut(inner.get_user_token(), old_mask) = inner.signal_mask.bits();
+    }
+    inner.signal_mask = flag.blockable();
+    Ok(0)
+}
+
+/// Set the alternate signal stack from `ss` and report the old one in
+/// `old_ss`, either may be null. It cannot be changed while in use.
+pub fn sys_sigaltstack(ss: *const SignalStack, old_ss: *mut SignalStack) -> SyscallResult {
+    if !ss.is_null() {
+        check_user_range(ss as usize, size_of::<SignalStack>(), MappingFlags::R)?;
+    }
+    if !old_ss.is_null() {
+        check_user_range(old_ss as usize, size_of::<SignalStack>(), MappingFlags::W)?;
+    }
+    let token = current_user_token();
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let user_sp = inner.get_trap_cx()[TrapFrameArgs::SP];
+    let on_stack = inner.alt_stack.contains(user_sp);
+    if !old_ss.is_null() {
+        let mut old = inner.alt_stack;
+        if on_stack {
+            old.flags |= SS_ONSTACK;
+        }
+        *translated_refmut(token, old_ss) = old;
+    }
+    if !ss.is_null() {
+        let new = *translated_ref(token, ss);
+        if on_stack {
+            return Err(EPERM);
+        }
+        if new.flags & !(SS_DISABLE | SS_ONSTACK) != 0 {
+            return Err(EINVAL);
+        }
+        if new.flags & SS_DISABLE != 0 {
+            inner.alt_stack = SignalStack::default();
+        } else if new.size < MINSIGSTKSZ {
+            return Err(ENOMEM);
+        } else if new.sp.checked_add(new.size).ok_or(EINVAL)? > USER_SPACE_END {
+            return Err(EFAULT);
+        } else {
+            inner.alt_stack = SignalStack {
+                sp: new.sp,
+                flags: 0,
+                size: new.size,
+            };
+        }
     }
+    Ok(0)
 }
 
+/// Return from a signal handler by restoring the context saved in the
+/// innermost signal frame on the user stack.
+pub fn sys_sigreturn() -> SyscallResult {
+    let task = current_task().unwrap();
+    let frame_addr = task.inner_exclusive_access().sig_frame;
+    if frame_addr == 0 {
+        return Err(EINVAL);
+    }
+    check_user_range(frame_addr, size_of::<SignalFrame>(), MappingFlags::R)?;
+    let mut inner = task.inner_exclusive_access();
+    let frame = translated_ref(inner.get_user_token(), frame_addr as *const SignalFrame);
+    // the frame may have been forged to block SIGKILL or SIGSTOP
+    inner.signal_mask = frame.mask.blockable();
+    inner.handling_sig = frame.handling_sig;
+    inner.sig_frame = frame.prev;
+    // restore the user registers, not the privileged state the frame
+    // might have been forged to hold
+    let trap_ctx = inner.get_trap_cx();
+    frame.restore_user_regs(trap_ctx);
+    // Here we return the value of a0 in the trap_ctx,
+    // otherwise it will be overwritten after we trap
+    // back to the original execution of the application.
+    Ok(trap_ctx[TrapFrameArgs::RET])
+}
+
+/// Either `action` or `old_action` may be null.
+/// SIGKILL and SIGSTOP always fail since their actions are fixed.
 pub fn sys_sigaction(
     signum: i32,
     action: *const SignalAction,
     old_action: *mut SignalAction,
-) -> isize {
+) -> SyscallResult {
+    check_sigaction_signal(signum)?;
+    let action_size = size_of::<SignalAction>();
+    if !action.is_null() {
+        check_user_range(action as usize, action_size, MappingFlags::R)?;
+    }
+    if !old_action.is_null() {
+        check_user_range(old_action as usize, action_size, MappingFlags::W)?;
+    }
     let token = current_user_token();
+    let action = if action.is_null() {
+        None
+    } else {
+        Some(*translated_ref(token, action))
+    };
+    let prev_action = set_sigaction(signum, action)?;
+    if !old_action.is_null() {
+        *translated_refmut(token, old_action) = prev_action;
+    }
+    Ok(0)
+}
+
+/// The signal `signum` if its action may be changed.
+fn check_sigaction_signal(signum: i32) -> Result<SignalFlags, Errno> {
+    if signum < 0 || signum as usize > MAX_SIG {
+        return Err(EINVAL);
+    }
+    let flag = SignalFlags::from_signum(signum as usize).ok_or(EINVAL)?;
+    if flag == SignalFlags::SIGKILL || flag == SignalFlags::SIGSTOP {
+        return Err(EINVAL);
+    }
+    Ok(flag)
+}
+
+/// Set the action of `signum` to `action` unless it is None, and return
+/// the previous action.
+pub fn set_sigaction(signum: i32, action: Option<SignalAction>) -> Result<SignalAction, Errno> {
+    let flag = check_sigaction_signal(signum)?;
     let task = current_task().unwrap();
     let mut inner = task.inner_exclusive_access();
-    if signum as usize > MAX_SIG {
-        return -1;
-    }
-    if let Some(flag) = SignalFlags::from_bits(1 << signum) {
-        if check_sigaction_error(flag, action as usize, old_action as usize) {
-            return -1;
+    let prev_action = inner.signal_actions.exclusive_access().table[signum as usize];
+    if let Some(action) = action {
+        // a pending signal which is now ignored is discarded
+        if action.handler == SIG_IGN {
+            inner.discard_signal(flag);
         }
-        let prev_action = inner.signal_actions.table[signum as usize];
-        *translated_refmut(token, old_action) = prev_action;
-        inner.signal_actions.table[signum as usize] = *translated_ref(token, action);
-        0
-    } else {
-        -1
+        inner.signal_actions.exclusive_access().table[signum as usize] = action;
     }
+    Ok(prev_action)
 }
@@ -0,0 +1,161 @@
+use super::{check_user_range, SyscallResult, EIO, EPERM, ESRCH};
+use crate::mm::{translated_ref, translated_refmut};
+use crate::task::{
+    current_task, current_user_token, pid2task, SignalFlags, SignalInfo, TaskControlBlock,
+    UserRegs, INITPROC, MAX_SIG,
+};
+use alloc::sync::Arc;
+use core::mem::size_of;
+use polyhal::pagetable::MappingFlags;
+
+pub const PTRACE_TRACEME: usize = 0;
+pub const PTRACE_PEEKTEXT: usize = 1;
+pub const PTRACE_PEEKDATA: usize = 2;
+pub const PTRACE_POKETEXT: usize = 4;
+pub const PTRACE_POKEDATA: usize = 5;
+pub const PTRACE_CONT: usize = 7;
+pub const PTRACE_KILL: usize = 8;
+pub const PTRACE_SINGLESTEP: usize = 9;
+pub const PTRACE_GETREGS: usize = 12;
+pub const PTRACE_SETREGS: usize = 13;
+pub const PTRACE_ATTACH: usize = 16;
+pub const PTRACE_DETACH: usize = 17;
+
+/// Make the parent of the current task its tracer.
+fn trace_me() -> SyscallResult {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    if inner.tracer.is_some() {
+        return Err(EPERM);
+    }
+    let parent = inner
+        .parent
+        .as_ref()
+        .and_then(|p| p.upgrade())
+        .ok_or(EPERM)?;
+    inner.tracer = Some(Arc::downgrade(&parent));
+    drop(inner);
+    parent
+        .inner_exclusive_access()
+        .tracees
+        .push(Arc::downgrade(&task));
+    Ok(0)
+}
+
+/// Trace `tracee` from the current task, which it may signal.
+/// The tracee is stopped with SIGSTOP for the tracer to wait for.
+fn attach(tracee: Arc<TaskControlBlock>) -> SyscallResult {
+    let task = current_task().unwrap();
+    if tracee.tgid == task.tgid || Arc::ptr_eq(&tracee, &INITPROC) {
+        return Err(EPERM);
+    }
+    let creds = task.inner_exclusive_access().creds;
+    let mut tracee_inner = tracee.inner_exclusive_access();
+    if tracee_inner.tracer.is_some() || !creds.can_signal(&tracee_inner.creds) {
+        return Err(EPERM);
+    }
+    tracee_inner.tracer = Some(Arc::downgrade(&task));
+    let sigstop = SignalFlags::SIGSTOP;
+    tracee_inner.add_signal(sigstop, SignalInfo::user(sigstop.signum(), task.tgid));
+    drop(tracee_inner);
+    task.inner_exclusive_access()
+        .tracees
+        .push(Arc::downgrade(&tracee));
+    Ok(0)
+}
+
+/// Trace or control task `pid` like Linux `ptrace`. Apart from
+/// PTRACE_TRACEME, PTRACE_ATTACH and PTRACE_KILL the tracee must be in a
+/// trace stop, which the tracer learns from `waitpid`.
+/// PTRACE_PEEK* store the word at `addr` of the tracee at `data` in the
+/// tracer, PTRACE_CONT and PTRACE_DETACH deliver signal `data` unless it
+/// is 0, and PTRACE_SINGLESTEP does the same but stops the tracee again
+/// with SIGTRAP after one instruction.
+pub fn sys_ptrace(request: usize, pid: usize, addr: usize, data: usize) -> SyscallResult {
+    if request == PTRACE_TRACEME {
+        return trace_me();
+    }
+    let tracee = pid2task(pid).ok_or(ESRCH)?;
+    if request == PTRACE_ATTACH {
+        return attach(tracee);
+    }
+    let task = current_task().unwrap();
+    let mut tracee_inner = tracee.inner_exclusive_access();
+    let traced_by_us = tracee_inner
+        .tracer
+        .as_ref()
+        .and_then(|tracer| tracer.upgrade())
+        .map_or(false, |tracer| Arc::ptr_eq(&tracer, &task));
+    if !traced_by_us {
+        return Err(ESRCH);
+    }
+    if request == PTRACE_KILL {
+        tracee_inner.trace_resume(SignalFlags::SIGKILL.signum());
+        return Ok(0);
+    }
+    if !tracee_inner.trace_stopped {
+        return Err(ESRCH);
+    }
+    match request {
+        PTRACE_PEEKTEXT | PTRACE_PEEKDATA => {
+            let mut word = [0u8; size_of::<usize>()];
+            if !tracee_inner
+                .memory_set
+                .exclusive_access()
+                .read_bytes(addr, &mut word)
+            {
+                return Err(EIO);
+            }
+            drop(tracee_inner);
+            check_user_range(data, size_of::<usize>(), MappingFlags::W)?;
+            *translated_refmut(current_user_token(), data as *mut usize) =
+                usize::from_ne_bytes(word);
+            Ok(0)
+        }
+        PTRACE_POKETEXT | PTRACE_POKEDATA => {
+            let memory_set = tracee_inner.memory_set.exclusive_access();
+            if memory_set.write_bytes(addr, &data.to_ne_bytes()) {
+                Ok(0)
+            } else {
+                Err(EIO)
+            }
+        }
+        PTRACE_GETREGS => {
+            let regs = UserRegs::from_trap_cx(tracee_inner.get_trap_cx());
+            drop(tracee_inner);
+            check_user_range(data, size_of::<UserRegs>(), MappingFlags::W)?;
+            *translated_refmut(current_user_token(), data as *mut UserRegs) = regs;
+            Ok(0)
+        }
+        PTRACE_SETREGS => {
+            check_user_range(data, size_of::<UserRegs>(), MappingFlags::R)?;
+            let regs = *translated_ref(current_user_token(), data as *const UserRegs);
+            if regs.apply(tracee_inner.get_trap_cx()) {
+                Ok(0)
+            } else {
+                Err(EIO)
+            }
+        }
+        PTRACE_CONT | PTRACE_SINGLESTEP | PTRACE_DETACH if data > MAX_SIG => Err(EIO),
+        PTRACE_SINGLESTEP => {
+            if !tracee_inner.trace_step() {
+                return Err(EIO);
+            }
+            tracee_inner.trace_resume(data);
+            Ok(0)
+        }
+        PTRACE_CONT => {
+            tracee_inner.trace_resume(data);
+            Ok(0)
+        }
+        PTRACE_DETACH => {
+            tracee_inner.trace_detach(data);
+            drop(tracee_inner);
+            task.inner_exclusive_access()
+                .tracees
+                .retain(|t| t.as_ptr() != Arc::as_ptr(&tracee));
+            Ok(0)
+        }
+        _ => Err(EIO),
+    }
+}
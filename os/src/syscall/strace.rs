@@ -0,0 +1,413 @@
+//! Syscall tracing like strace. Each syscall of a traced task is printed
+//! when it returns, with its decoded arguments and result, such as
+//! `[3] open("notes", O_WRONLY|O_CREAT) = 3` or `[3] close(9) = -1 EBADF`.
+//! A task is traced once it calls `sys_strace`, which its children inherit.
+//! The kernel command line option `strace` traces every task from boot on,
+//! and `strace=<name>[,<name>...]` the listed programs from exec on.
+
+use super::linux::{
+    SYSCALL_DUP3, SYSCALL_GETTIMEOFDAY, SYSCALL_OPENAT, SYSCALL_PIPE2, SYSCALL_RT_SIGACTION,
+    SYSCALL_RT_SIGPROCMASK,
+};
+use super::{check_user_range, SyscallResult, AT_FDCWD, EINVAL};
+use super::{MAP_ANONYMOUS, MAP_FIXED, MAP_PRIVATE, MAP_SHARED, PROT_EXEC, PROT_READ, PROT_WRITE};
+use super::{
+    SYSCALL_BRK, SYSCALL_CHDIR, SYSCALL_CLOCK_GETTIME, SYSCALL_CLONE, SYSCALL_CLOSE, SYSCALL_DUP,
+    SYSCALL_EXECVE, SYSCALL_EXIT, SYSCALL_EXIT_GROUP, SYSCALL_FCNTL, SYSCALL_FSTAT, SYSCALL_GETCWD,
+    SYSCALL_GETEGID, SYSCALL_GETEUID, SYSCALL_GETGID, SYSCALL_GETITIMER, SYSCALL_GETPID,
+    SYSCALL_GETPPID, SYSCALL_GETRANDOM, SYSCALL_GETRLIMIT, SYSCALL_GETRUSAGE, SYSCALL_GETTID,
+    SYSCALL_GETUID, SYSCALL_GET_TIME, SYSCALL_IOCTL, SYSCALL_KILL, SYSCALL_LINKAT, SYSCALL_MKDIRAT,
+    SYSCALL_MMAP, SYSCALL_MOUNT, SYSCALL_MUNMAP, SYSCALL_NEWFSTATAT, SYSCALL_OPEN, SYSCALL_PIPE,
+    SYSCALL_PRLIMIT, SYSCALL_PTRACE, SYSCALL_READ, SYSCALL_READV, SYSCALL_RENAMEAT2, SYSCALL_RMDIR,
+    SYSCALL_SECCOMP, SYSCALL_SETGID, SYSCALL_SETITIMER, SYSCALL_SETRLIMIT, SYSCALL_SETUID,
+    SYSCALL_SET_TID_ADDRESS, SYSCALL_SIGACTION, SYSCALL_SIGALTSTACK, SYSCALL_SIGPROCMASK,
+    SYSCALL_SIGQUEUE, SYSCALL_SIGRETURN, SYSCALL_SPAWN, SYSCALL_STRACE, SYSCALL_SYSINFO,
+    SYSCALL_TIMES, SYSCALL_UMOUNT2, SYSCALL_UNAME, SYSCALL_UNLINKAT, SYSCALL_VFORK,
+    SYSCALL_WAITPID, SYSCALL_WRITE, SYSCALL_WRITEV, SYSCALL_YIELD,
+};
+use crate::cmdline;
+use crate::fs::OpenFlags;
+use crate::mm::{translated_byte_buffer, translated_ref};
+use crate::task::{current_task, current_user_token, Personality, SignalFlags, SIGRTMAX, SIGRTMIN};
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::mem::size_of;
+use polyhal::pagetable::MappingFlags;
+
+/// At most this many bytes of a string or buffer are printed
+const MAX_STR_LEN: usize = 32;
+/// At most this many strings of an array are printed
+const MAX_ARRAY_LEN: usize = 8;
+
+/// How an argument is printed
+#[derive(Debug, Clone, Copy)]
+enum Arg {
+    /// signed decimal
+    Int,
+    /// hex, for addresses and masks
+    Hex,
+    /// a file descriptor or AT_FDCWD
+    Fd,
+    /// a string
+    Str,
+    /// bytes to be written, the next argument is their length
+    Buf,
+    /// a null-terminated array of strings, such as argv
+    StrArray,
+    /// native open flags
+    Open,
+    /// PROT_* of mmap
+    Prot,
+    /// MAP_* of mmap
+    Map,
+    /// a signal number
+    Signal,
+}
+
+use Arg::*;
+
+/// Name and arguments of syscall `id`, None if there is no such syscall.
+fn syscall_spec(id: usize, personality: Personality) -> Option<(&'static str, &'static [Arg])> {
+    if personality == Personality::Linux {
+        if let Some(spec) = linux_syscall_spec(id) {
+            return Some(spec);
+        }
+    }
+    let spec: (&str, &[Arg]) = match id {
+        SYSCALL_GETCWD => ("getcwd", &[Hex, Int]),
+        SYSCALL_DUP => ("dup", &[Fd]),
+        SYSCALL_FCNTL => ("fcntl", &[Fd, Int, Hex]),
+        SYSCALL_IOCTL => ("ioctl", &[Fd, Hex, Hex]),
+        SYSCALL_MKDIRAT => ("mkdirat", &[Fd, Str, Hex]),
+        SYSCALL_UNLINKAT => ("unlinkat", &[Fd, Str, Hex]),
+        SYSCALL_LINKAT => ("linkat", &[Fd, Str, Fd, Str, Hex]),
+        SYSCALL_UMOUNT2 => ("umount2", &[Str, Hex]),
+        SYSCALL_MOUNT => ("mount", &[Str, Str, Str, Hex, Hex]),
+        SYSCALL_CHDIR => ("chdir", &[Str]),
+        SYSCALL_OPEN => ("open", &[Str, Open]),
+        SYSCALL_CLOSE => ("close", &[Fd]),
+        SYSCALL_PIPE => ("pipe", &[Hex]),
+        SYSCALL_READ => ("read", &[Fd, Hex, Int]),
+        SYSCALL_WRITE => ("write", &[Fd, Buf, Int]),
+        SYSCALL_READV => ("readv", &[Fd, Hex, Int]),
+        SYSCALL_WRITEV => ("writev", &[Fd, Hex, Int]),
+        SYSCALL_NEWFSTATAT => ("newfstatat", &[Fd, Str, Hex, Hex]),
+        SYSCALL_FSTAT => ("fstat", &[Fd, Hex]),
+        SYSCALL_EXIT => ("exit", &[Int]),
+        SYSCALL_EXIT_GROUP => ("exit_group", &[Int]),
+        SYSCALL_SET_TID_ADDRESS => ("set_tid_address", &[Hex]),
+        SYSCALL_GETITIMER => ("getitimer", &[Int, Hex]),
+        SYSCALL_SETITIMER => ("setitimer", &[Int, Hex, Hex]),
+        SYSCALL_CLOCK_GETTIME => ("clock_gettime", &[Int, Hex]),
+        SYSCALL_PTRACE => ("ptrace", &[Int, Int, Hex, Hex]),
+        SYSCALL_YIELD => ("sched_yield", &[]),
+        SYSCALL_KILL => ("kill", &[Int, Signal]),
+        SYSCALL_SIGALTSTACK => ("sigaltstack", &[Hex, Hex]),
+        SYSCALL_SIGACTION => ("sigaction", &[Signal, Hex, Hex]),
+        SYSCALL_SIGPROCMASK => ("sigprocmask", &[Hex, Hex]),
+        SYSCALL_SIGQUEUE => ("sigqueue", &[Int, Signal, Hex]),
+        SYSCALL_SIGRETURN => ("sigreturn", &[]),
+        SYSCALL_SETGID => ("setgid", &[Int]),
+        SYSCALL_SETUID => ("setuid", &[Int]),
+        SYSCALL_TIMES => ("times", &[Hex]),
+        SYSCALL_UNAME => ("uname", &[Hex]),
+        SYSCALL_GETRLIMIT => ("getrlimit", &[Int, Hex]),
+        SYSCALL_SETRLIMIT => ("setrlimit", &[Int, Hex]),
+        SYSCALL_GETRUSAGE => ("getrusage", &[Int, Hex]),
+        SYSCALL_GET_TIME => ("get_time", &[]),
+        SYSCALL_GETPID => ("getpid", &[]),
+        SYSCALL_GETPPID => ("getppid", &[]),
+        SYSCALL_GETUID => ("getuid", &[]),
+        SYSCALL_GETEUID => ("geteuid", &[]),
+        SYSCALL_GETGID => ("getgid", &[]),
+        SYSCALL_GETEGID => ("getegid", &[]),
+        SYSCALL_GETTID => ("gettid", &[]),
+        SYSCALL_SYSINFO => ("sysinfo", &[Hex]),
+        SYSCALL_BRK => ("brk", &[Hex]),
+        SYSCALL_MUNMAP => ("munmap", &[Hex, Hex]),
+        SYSCALL_CLONE => ("clone", &[Hex, Hex, Hex, Hex, Hex]),
+        SYSCALL_EXECVE => ("execve", &[Str, StrArray, StrArray]),
+        SYSCALL_MMAP => ("mmap", &[Hex, Hex, Prot, Map, Fd, Hex]),
+        SYSCALL_WAITPID => ("wait4", &[Int, Hex, Hex, Hex]),
+        SYSCALL_PRLIMIT => ("prlimit64", &[Int, Int, Hex, Hex]),
+        SYSCALL_RENAMEAT2 => ("renameat2", &[Fd, Str, Fd, Str, Hex]),
+        SYSCALL_SECCOMP => ("seccomp", &[Int, Int, Hex]),
+        SYSCALL_GETRANDOM => ("getrandom", &[Hex, Int, Hex]),
+        SYSCALL_VFORK => ("vfork", &[]),
+        SYSCALL_SPAWN => ("spawn", &[Str, StrArray, Hex]),
+        SYSCALL_STRACE => ("strace", &[Int]),
+        SYSCALL_RMDIR => ("rmdir", &[Str]),
+        _ => return None,
+    };
+    Some(spec)
+}
+
+/// Like `syscall_spec` for the numbers which Linux programs use differently.
+fn linux_syscall_spec(id: usize) -> Option<(&'static str, &'static [Arg])> {
+    let spec: (&str, &[Arg]) = match id {
+        SYSCALL_DUP3 => ("dup3", &[Fd, Fd, Hex]),
+        SYSCALL_OPENAT => ("openat", &[Fd, Str, Hex]),
+        SYSCALL_PIPE2 => ("pipe2", &[Hex, Hex]),
+        SYSCALL_RT_SIGACTION => ("rt_sigaction", &[Signal, Hex, Hex, Int]),
+        SYSCALL_RT_SIGPROCMASK => ("rt_sigprocmask", &[Int, Hex, Hex, Int]),
+        SYSCALL_GETTIMEOFDAY => ("gettimeofday", &[Hex, Hex]),
+        _ => return None,
+    };
+    Some(spec)
+}
+
+/// Start or stop tracing the current task if `enable` is 1 or 0, return
+/// 1 if it was traced before.
+pub fn sys_strace(enable: usize) -> SyscallResult {
+    if enable > 1 {
+        return Err(EINVAL);
+    }
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    let was_traced = inner.strace;
+    inner.strace = enable == 1;
+    Ok(was_traced as usize)
+}
+
+/// If the kernel command line traces every task.
+pub fn strace_all() -> bool {
+    cmdline::option("strace") == Some("")
+}
+
+/// If the kernel command line traces the program at `path` once it is
+/// executed.
+pub fn strace_on_exec(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    cmdline::option("strace").map_or(false, |names| names.split(',').any(|n| n == name))
+}
+
+/// If the current task is traced, the line describing syscall `id` with
+/// `args`, which is completed and printed by `strace_exit` once the call
+/// returns. Calls which never return are printed at once.
+pub fn strace_enter(id: usize, args: &[usize; 6]) -> Option<String> {
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    if !inner.strace {
+        return None;
+    }
+    let personality = inner.personality;
+    drop(inner);
+    let (name, specs) = match syscall_spec(id, personality) {
+        Some((name, specs)) => (String::from(name), specs),
+        // all argument registers of unknown syscalls are shown
+        None => (format!("syscall_{}", id), &[Hex; 6] as &[Arg]),
+    };
+    let mut line = format!("[{}] {}(", task.pid.0, name);
+    for (i, spec) in specs.iter().enumerate() {
+        if i > 0 {
+            line.push_str(", ");
+        }
+        push_arg(&mut line, *spec, args, i);
+    }
+    line.push(')');
+    if id == SYSCALL_EXIT || id == SYSCALL_EXIT_GROUP {
+        println!("{} = ?", line);
+        return None;
+    }
+    Some(line)
+}
+
+/// Print the traced syscall `line` from `strace_enter` with its result.
+pub fn strace_exit(line: String, id: usize, ret: &SyscallResult) {
+    match ret {
+        Ok(ret) if id == SYSCALL_BRK || id == SYSCALL_MMAP => println!("{} = {:#x}", line, ret),
+        Ok(ret) => println!("{} = {}", line, *ret as isize),
+        Err(errno) => match errno.name() {
+            Some(name) => println!("{} = -1 {}", line, name),
+            None => println!("{} = -1 errno {}", line, errno.0),
+        },
+    }
+}
+
+fn push_arg(line: &mut String, spec: Arg, args: &[usize; 6], i: usize) {
+    let arg = args[i];
+    match spec {
+        Int => {
+            let _ = write!(line, "{}", arg as isize);
+        }
+        Hex => {
+            let _ = write!(line, "{:#x}", arg);
+        }
+        Fd if arg as isize == AT_FDCWD => line.push_str("AT_FDCWD"),
+        Fd => {
+            let _ = write!(line, "{}", arg as i32);
+        }
+        Str => push_user_str(line, arg),
+        Buf => {
+            let len = args.get(i + 1).copied().unwrap_or(0);
+            let shown = len.min(MAX_STR_LEN);
+            match check_user_range(arg, shown, MappingFlags::R) {
+                Ok(()) => {
+                    let bytes = translated_byte_buffer(current_user_token(), arg as *mut u8, shown);
+                    push_quoted(line, bytes, len > shown);
+                }
+                Err(_) => {
+                    let _ = write!(line, "{:#x}", arg);
+                }
+            }
+        }
+        StrArray => push_user_str_array(line, arg),
+        Open => push_open_flags(line, arg),
+        Prot => push_flags(
+            line,
+            arg,
+            &[
+                (PROT_READ, "PROT_READ"),
+                (PROT_WRITE, "PROT_WRITE"),
+                (PROT_EXEC, "PROT_EXEC"),
+            ],
+            "PROT_NONE",
+        ),
+        Map => push_flags(
+            line,
+            arg,
+            &[
+                (MAP_SHARED, "MAP_SHARED"),
+                (MAP_PRIVATE, "MAP_PRIVATE"),
+                (MAP_FIXED, "MAP_FIXED"),
+                (MAP_ANONYMOUS, "MAP_ANONYMOUS"),
+            ],
+            "0",
+        ),
+        Signal => push_signal(line, arg),
+    }
+}
+
+/// Read a `T` at user address `addr`, None if it is not mapped.
+fn read_user<T: Copy>(addr: usize) -> Option<T> {
+    check_user_range(addr, size_of::<T>(), MappingFlags::R).ok()?;
+    Some(*translated_ref(current_user_token(), addr as *const T))
+}
+
+/// Append `bytes` quoted and escaped as in C, followed by "..." if they are
+/// cut off.
+fn push_quoted(line: &mut String, bytes: &[u8], cut: bool) {
+    line.push('"');
+    for byte in bytes {
+        match byte {
+            b'\n' => line.push_str("\\n"),
+            b'\r' => line.push_str("\\r"),
+            b'\t' => line.push_str("\\t"),
+            b'"' => line.push_str("\\\""),
+            b'\\' => line.push_str("\\\\"),
+            0x20..=0x7e => line.push(*byte as char),
+            _ => {
+                let _ = write!(line, "\\x{:02x}", byte);
+            }
+        }
+    }
+    line.push('"');
+    if cut {
+        line.push_str("...");
+    }
+}
+
+/// Append the string at user address `addr`, or the address if it cannot
+/// be read.
+fn push_user_str(line: &mut String, addr: usize) {
+    if read_user::<u8>(addr).is_none() {
+        let _ = write!(line, "{:#x}", addr);
+        return;
+    }
+    let mut bytes = Vec::new();
+    let mut cut = false;
+    while let Some(byte) = read_user::<u8>(addr + bytes.len()) {
+        if byte == 0 {
+            break;
+        }
+        if bytes.len() == MAX_STR_LEN {
+            cut = true;
+            break;
+        }
+        bytes.push(byte);
+    }
+    push_quoted(line, &bytes, cut);
+}
+
+/// Append the null-terminated array of strings at user address `addr`.
+fn push_user_str_array(line: &mut String, addr: usize) {
+    if addr == 0 {
+        line.push_str("NULL");
+        return;
+    }
+    line.push('[');
+    for i in 0..=MAX_ARRAY_LEN {
+        let ptr = match read_user::<usize>(addr + i * size_of::<usize>()) {
+            Some(0) | None => break,
+            Some(ptr) => ptr,
+        };
+        if i > 0 {
+            line.push_str(", ");
+        }
+        if i == MAX_ARRAY_LEN {
+            line.push_str("...");
+            break;
+        }
+        push_user_str(line, ptr);
+    }
+    line.push(']');
+}
+
+/// Append the names of the bits of `value` joined by '|', the bits without
+/// a name in hex, or `zero` if no bit is set.
+fn push_flags(line: &mut String, value: usize, names: &[(usize, &str)], zero: &str) {
+    if value == 0 {
+        line.push_str(zero);
+        return;
+    }
+    let mut rest = value;
+    let mut parts = Vec::new();
+    for (bit, name) in names {
+        if value & bit != 0 {
+            parts.push(String::from(*name));
+            rest &= !bit;
+        }
+    }
+    if rest != 0 {
+        parts.push(format!("{:#x}", rest));
+    }
+    line.push_str(&parts.join("|"));
+}
+
+fn push_open_flags(line: &mut String, value: usize) {
+    let flags = OpenFlags::from_bits_truncate(value as u32);
+    // the access mode is a number rather than a bit
+    let mode = if flags.contains(OpenFlags::RDWR) {
+        "O_RDWR"
+    } else if flags.contains(OpenFlags::WRONLY) {
+        "O_WRONLY"
+    } else {
+        "O_RDONLY"
+    };
+    line.push_str(mode);
+    for (flag, name) in [
+        (OpenFlags::CREATE, "O_CREAT"),
+        (OpenFlags::TRUNC, "O_TRUNC"),
+    ] {
+        if flags.contains(flag) {
+            line.push('|');
+            line.push_str(name);
+        }
+    }
+    let rest = value & !(flags.bits() as usize);
+    if rest != 0 {
+        let _ = write!(line, "|{:#x}", rest);
+    }
+}
+
+fn push_signal(line: &mut String, signum: usize) {
+    if (SIGRTMIN..=SIGRTMAX).contains(&signum) {
+        let _ = write!(line, "SIGRTMIN+{}", signum - SIGRTMIN);
+    } else if let Some(signal) = SignalFlags::from_signum(signum).filter(|_| signum > 0) {
+        let _ = write!(line, "{:?}", signal);
+    } else {
+        let _ = write!(line, "{}", signum);
+    }
+}
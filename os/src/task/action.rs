@@ -1,4 +1,26 @@
 use crate::task::{SignalFlags, MAX_SIG};
+use bitflags::*;
+
+bitflags! {
+    /// Flags of `SignalAction`, same values as Linux
+    pub struct SignalActionFlags: u32 {
+        /// pass siginfo and the signal frame to the handler
+        const SA_SIGINFO = 4;
+        /// run the handler on the alternate signal stack
+        const SA_ONSTACK = 0x0800_0000;
+        /// restart a syscall interrupted by this signal
+        const SA_RESTART = 0x1000_0000;
+        /// do not mask the signal while its handler runs
+        const SA_NODEFER = 0x4000_0000;
+        /// reset to the default action once the handler is called
+        const SA_RESETHAND = 0x8000_0000;
+    }
+}
+
+/// `SignalAction::handler` for the default action
+pub const SIG_DFL: usize = 0;
+/// `SignalAction::handler` to ignore the signal
+pub const SIG_IGN: usize = 1;
 
 /// Action for a signal
 #[repr(C, align(16))]
@@ -6,6 +28,9 @@ use crate::task::{SignalFlags, MAX_SIG};
 pub struct SignalAction {
     pub handler: usize,
     pub mask: SignalFlags,
+    pub flags: SignalActionFlags,
+    /// where the handler returns to, it should call `sigreturn`
+    pub restorer: usize,
 }
 
 impl Default for SignalAction {
@@ -13,6 +38,8 @@ impl Default for SignalAction {
         Self {
             handler: 0,
             mask: SignalFlags::from_bits(40).unwrap(),
+            flags: SignalActionFlags::empty(),
+            restorer: 0,
         }
     }
 }
@@ -0,0 +1,44 @@
+use bitflags::*;
+
+bitflags! {
+    /// What a child made by `clone` shares with its parent, same values as Linux.
+    /// The lowest byte is not a flag but the signal sent to the parent on exit.
+    pub struct CloneFlags: usize {
+        /// share the address space
+        const CLONE_VM = 0x100;
+        /// share the current directory
+        const CLONE_FS = 0x200;
+        /// share the fd table
+        const CLONE_FILES = 0x400;
+        /// share the signal actions, requires CLONE_VM
+        const CLONE_SIGHAND = 0x800;
+        /// suspend the parent until the child execs or exits
+        const CLONE_VFORK = 0x4000;
+        /// put the child in the thread group of the parent, requires CLONE_SIGHAND
+        const CLONE_THREAD = 0x10000;
+        /// set the thread pointer of the child
+        const CLONE_SETTLS = 0x80000;
+        /// store the tid of the child at `ptid` in the parent
+        const CLONE_PARENT_SETTID = 0x100000;
+        /// clear the tid at `ctid` in the child when it exits
+        const CLONE_CHILD_CLEARTID = 0x200000;
+    }
+}
+
+/// Mask of the exit signal in the flags of `clone`
+pub const CSIGNAL: usize = 0xff;
+
+impl CloneFlags {
+    /// Split the `flags` argument of `clone` into known flags and the exit
+    /// signal, None if it has unknown or inconsistent flags.
+    pub fn parse(flags: usize) -> Option<(Self, usize)> {
+        let clone_flags = Self::from_bits(flags & !CSIGNAL)?;
+        if clone_flags.contains(Self::CLONE_SIGHAND) && !clone_flags.contains(Self::CLONE_VM)
+            || clone_flags.contains(Self::CLONE_THREAD)
+                && !clone_flags.contains(Self::CLONE_SIGHAND)
+        {
+            return None;
+        }
+        Some((clone_flags, flags & CSIGNAL))
+    }
+}
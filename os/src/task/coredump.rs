@@ -0,0 +1,244 @@
+use super::signal::SI_KERNEL;
+use super::{current_task, DefaultAction, SignalFlags, SignalInfo, UserRegs, RLIMIT_CORE};
+use crate::config::PAGE_SIZE;
+use crate::fs::{open_file, File, OpenFlags};
+use alloc::format;
+use alloc::vec;
+use alloc::vec::Vec;
+use polyhal::pagetable::MappingFlags;
+
+const ET_CORE: u16 = 4;
+const EV_CURRENT: u8 = 1;
+const ELFCLASS64: u8 = 2;
+const ELFDATA2LSB: u8 = 1;
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const PF_X: u32 = 1;
+const PF_W: u32 = 2;
+const PF_R: u32 = 4;
+const NT_PRSTATUS: u32 = 1;
+const NT_SIGINFO: u32 = 0x5349_4749;
+const EHDR_SIZE: usize = 64;
+const PHDR_SIZE: usize = 56;
+/// Size of Linux `siginfo_t`
+const SIGINFO_SIZE: usize = 128;
+
+#[cfg(target_arch = "riscv64")]
+const EM_MACHINE: u16 = 243;
+#[cfg(target_arch = "x86_64")]
+const EM_MACHINE: u16 = 62;
+#[cfg(target_arch = "aarch64")]
+const EM_MACHINE: u16 = 183;
+#[cfg(target_arch = "loongarch64")]
+const EM_MACHINE: u16 = 258;
+
+/// Little-endian writer of the ELF structures.
+#[derive(Default)]
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u16(&mut self, v: u16) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+    fn u32(&mut self, v: u32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+    fn u64(&mut self, v: u64) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+    fn pad_to(&mut self, align: usize) {
+        let len = (self.0.len() + align - 1) / align * align;
+        self.0.resize(len, 0);
+    }
+    /// Append a note of the "CORE" owner.
+    fn note(&mut self, note_type: u32, desc: &[u8]) {
+        let name = b"CORE\0";
+        self.u32(name.len() as u32);
+        self.u32(desc.len() as u32);
+        self.u32(note_type);
+        self.0.extend_from_slice(name);
+        self.pad_to(4);
+        self.0.extend_from_slice(desc);
+        self.pad_to(4);
+    }
+}
+
+/// Linux `elf_prstatus`: the signal, signal sets, ids, times and registers.
+fn prstatus(
+    info: &SignalInfo,
+    pending: SignalFlags,
+    blocked: SignalFlags,
+    ids: [usize; 2],
+    times_us: [usize; 4],
+    regs: &UserRegs,
+) -> Vec<u8> {
+    let mut w = Writer::default();
+    w.u32(info.signo as u32);
+    w.u32(info.code as u32);
+    w.u32(info.errno as u32);
+    w.u16(info.signo as u16);
+    w.pad_to(8);
+    w.u64(pending.bits());
+    w.u64(blocked.bits());
+    // pid and ppid, no process groups or sessions yet
+    w.u32(ids[0] as u32);
+    w.u32(ids[1] as u32);
+    w.u32(0);
+    w.u32(0);
+    // user, system and the children's times, as timevals
+    for us in times_us {
+        w.u64((us / 1_000_000) as u64);
+        w.u64((us % 1_000_000) as u64);
+    }
+    for &reg in regs.as_words() {
+        w.u64(reg as u64);
+    }
+    // no floating point registers
+    w.u32(0);
+    w.pad_to(8);
+    w.0
+}
+
+/// Linux `siginfo_t` of the fatal signal.
+fn siginfo(info: &SignalInfo) -> Vec<u8> {
+    let mut w = Writer::default();
+    w.u32(info.signo as u32);
+    w.u32(info.errno as u32);
+    w.u32(info.code as u32);
+    w.pad_to(8);
+    if info.code == SI_KERNEL {
+        w.u64(info.addr as u64);
+    } else {
+        w.u32(info.pid as u32);
+        // uid of the sender is not recorded
+        w.u32(0);
+        w.u64(info.value as u64);
+    }
+    w.0.resize(SIGINFO_SIZE, 0);
+    w.0
+}
+
+fn segment_flags(flags: MappingFlags) -> u32 {
+    let mut pf = 0;
+    if flags.contains(MappingFlags::R) {
+        pf |= PF_R;
+    }
+    if flags.contains(MappingFlags::W) {
+        pf |= PF_W;
+    }
+    if flags.contains(MappingFlags::X) {
+        pf |= PF_X;
+    }
+    pf
+}
+
+/// Write an ELF core file `core.<pid>` of the current task, which is being
+/// killed by signal `sig`: a PT_LOAD segment for every mapped area and a
+/// PT_NOTE with its registers and the siginfo. Return whether it was
+/// written, which needs a signal dumping core by default and a core no
+/// larger than RLIMIT_CORE.
+pub fn current_dump_core(sig: usize) -> bool {
+    let signal = match SignalFlags::from_signum(sig) {
+        Some(signal) if signal.default_action() == DefaultAction::Core => signal,
+        _ => return false,
+    };
+    let task = current_task().unwrap();
+    let inner = task.inner_exclusive_access();
+    let info = if inner.killed_info.signo as usize == sig {
+        inner.killed_info
+    } else {
+        SignalInfo::kernel(signal.signum(), 0)
+    };
+    let ppid = inner
+        .parent
+        .as_ref()
+        .and_then(|p| p.upgrade())
+        .map_or(0, |p| p.tgid);
+    let times_us = [
+        inner.usage.user_time,
+        inner.usage.system_time,
+        inner.children_usage.user_time,
+        inner.children_usage.system_time,
+    ];
+    let regs = UserRegs::from_trap_cx(inner.get_trap_cx());
+    let (pending, blocked) = (inner.signals, inner.signal_mask);
+    let status = prstatus(&info, pending, blocked, [task.tgid, ppid], times_us, &regs);
+    let mut notes = Writer::default();
+    notes.note(NT_PRSTATUS, &status);
+    notes.note(NT_SIGINFO, &siginfo(&info));
+    let limit = inner.rlimits[RLIMIT_CORE].cur;
+    let memory_set = inner.memory_set.clone();
+    // the core goes to the current directory
+    let cwd = inner.cwd.exclusive_access().clone();
+    // writing the file looks up RLIMIT_FSIZE of the task
+    drop(inner);
+    let areas: Vec<_> = memory_set.exclusive_access().user_areas().collect();
+
+    // headers and notes, then the areas from a page boundary on
+    let notes_offset = EHDR_SIZE + PHDR_SIZE * (areas.len() + 1);
+    let data_offset = (notes_offset + notes.0.len() + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE;
+    let size: usize = areas.iter().map(|(start, end, _)| end - start).sum();
+    if data_offset + size > limit {
+        return false;
+    }
+    let mut w = Writer::default();
+    w.0.extend_from_slice(&[0x7f, b'E', b'L', b'F', ELFCLASS64, ELFDATA2LSB, EV_CURRENT]);
+    w.pad_to(16);
+    w.u16(ET_CORE);
+    w.u16(EM_MACHINE);
+    w.u32(EV_CURRENT as u32);
+    // no entry point and no section headers
+    w.u64(0);
+    w.u64(EHDR_SIZE as u64);
+    w.u64(0);
+    w.u32(0);
+    w.u16(EHDR_SIZE as u16);
+    w.u16(PHDR_SIZE as u16);
+    w.u16((areas.len() + 1) as u16);
+    w.u16(0);
+    w.u16(0);
+    w.u16(0);
+    w.u32(PT_NOTE);
+    w.u32(0);
+    w.u64(notes_offset as u64);
+    w.u64(0);
+    w.u64(0);
+    w.u64(notes.0.len() as u64);
+    w.u64(0);
+    w.u64(4);
+    let mut offset = data_offset;
+    for &(start, end, flags) in areas.iter() {
+        w.u32(PT_LOAD);
+        w.u32(segment_flags(flags));
+        w.u64(offset as u64);
+        w.u64(start as u64);
+        w.u64(0);
+        w.u64((end - start) as u64);
+        w.u64((end - start) as u64);
+        w.u64(PAGE_SIZE as u64);
+        offset += end - start;
+    }
+    w.0.extend_from_slice(&notes.0);
+    w.0.resize(data_offset, 0);
+
+    let name = format!("core.{}", task.tgid);
+    let file = match open_file(&cwd, &name, OpenFlags::CREATE | OpenFlags::WRONLY) {
+        Ok(file) => file,
+        Err(_) => return false,
+    };
+    if file.write(&mut w.0) != data_offset {
+        return false;
+    }
+    let mut page = vec![0u8; PAGE_SIZE];
+    for (start, end, _) in areas {
+        for addr in (start..end).step_by(PAGE_SIZE) {
+            if !memory_set.exclusive_access().read_bytes(addr, &mut page) {
+                page.fill(0);
+            }
+            if file.write(&mut page) != PAGE_SIZE {
+                return false;
+            }
+        }
+    }
+    true
+}
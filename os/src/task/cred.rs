@@ -0,0 +1,57 @@
+/// User identity of a task. There is no login, so every task descends from
+/// the root-owned initproc until one of them drops privileges.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Credentials {
+    // real ids, who owns the task
+    pub uid: usize,
+    pub gid: usize,
+    // effective ids, what permission checks use
+    pub euid: usize,
+    pub egid: usize,
+    // saved ids, what an unprivileged task may switch its effective ids back to
+    pub suid: usize,
+    pub sgid: usize,
+}
+
+pub const ROOT_UID: usize = 0;
+
+impl Credentials {
+    pub fn is_root(&self) -> bool {
+        self.euid == ROOT_UID
+    }
+    /// Whether a task with these credentials may signal one with `target`.
+    pub fn can_signal(&self, target: &Credentials) -> bool {
+        self.is_root()
+            || self.uid == target.uid
+            || self.uid == target.suid
+            || self.euid == target.uid
+            || self.euid == target.suid
+    }
+    /// `setuid`: root sets every id, others may only switch the effective
+    /// id between the real and saved ones. Return false if not permitted.
+    pub fn set_uid(&mut self, uid: usize) -> bool {
+        if self.is_root() {
+            self.uid = uid;
+            self.euid = uid;
+            self.suid = uid;
+        } else if uid == self.uid || uid == self.suid {
+            self.euid = uid;
+        } else {
+            return false;
+        }
+        true
+    }
+    /// `setgid`, same rules as `set_uid` with root decided by the euid.
+    pub fn set_gid(&mut self, gid: usize) -> bool {
+        if self.is_root() {
+            self.gid = gid;
+            self.egid = gid;
+            self.sgid = gid;
+        } else if gid == self.gid || gid == self.sgid {
+            self.egid = gid;
+        } else {
+            return false;
+        }
+        true
+    }
+}
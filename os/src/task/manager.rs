@@ -2,6 +2,7 @@ use super::TaskControlBlock;
 use crate::sync::UPSafeCell;
 use alloc::collections::{BTreeMap, VecDeque};
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use lazy_static::*;
 use log::*;
 pub struct TaskManager {
@@ -48,6 +49,27 @@ pub fn pid2task(pid: usize) -> Option<Arc<TaskControlBlock>> {
     map.get(&pid).map(Arc::clone)
 }
 
+/// Every task in thread group `tgid`.
+pub fn thread_group(tgid: usize) -> Vec<Arc<TaskControlBlock>> {
+    let map = PID2TCB.exclusive_access();
+    map.values().filter(|t| t.tgid == tgid).cloned().collect()
+}
+
+/// How many processes there are, counting each thread group once.
+pub fn process_count() -> usize {
+    let map = PID2TCB.exclusive_access();
+    map.values().filter(|t| t.tgid == t.getpid()).count()
+}
+
+/// The pid of every process, that is of each thread group leader.
+pub fn process_ids() -> Vec<usize> {
+    let map = PID2TCB.exclusive_access();
+    map.values()
+        .filter(|t| t.tgid == t.getpid())
+        .map(|t| t.tgid)
+        .collect()
+}
+
 pub fn remove_from_pid2task(pid: usize) {
     let mut map = PID2TCB.exclusive_access();
     if map.remove(&pid).is_none() {
@@ -1,35 +1,100 @@
 mod action;
+mod clone;
+mod coredump;
+mod cred;
 mod manager;
+mod personality;
 mod pid;
 mod processor;
+mod ptrace;
+mod rlimit;
+mod seccomp;
 mod signal;
 #[allow(clippy::module_inception)]
 mod task;
+mod timer;
+mod usage;
+mod wait;
 
 use crate::fs::{open_file, OpenFlags};
-use alloc::sync::Arc;
+use crate::mm::translated_refmut;
+use alloc::string::String;
+use alloc::sync::{Arc, Weak};
+use core::mem::size_of;
 use lazy_static::*;
 use log::*;
 use manager::fetch_task;
 use manager::remove_from_pid2task;
 use polyhal::instruction::Instruction;
 use polyhal::kcontext::KContext;
-use polyhal::trapframe::TrapFrameArgs;
-use task::{TaskControlBlock, TaskStatus};
+use polyhal::pagetable::MappingFlags;
+use polyhal::trapframe::{TrapFrame, TrapFrameArgs};
+use task::TaskControlBlockInner;
 
-pub use action::{SignalAction, SignalActions};
-pub use manager::{add_task, pid2task};
-pub use pid::{pid_alloc, PidHandle};
+pub use action::{SignalAction, SignalActionFlags, SignalActions, SIG_DFL, SIG_IGN};
+pub use clone::{CloneFlags, CSIGNAL};
+pub use coredump::current_dump_core;
+pub use cred::{Credentials, ROOT_UID};
+pub use manager::{add_task, pid2task, process_count, process_ids, thread_group};
+pub use personality::Personality;
+pub use pid::{pid_alloc, pid_count, PidHandle};
 pub use processor::{current_task, current_user_token, run_tasks, schedule, take_current_task};
-pub use signal::{SignalFlags, MAX_SIG};
+pub use ptrace::{is_step_trap, UserRegs};
+pub use rlimit::{
+    RLimit, RLIMIT_AS, RLIMIT_CORE, RLIMIT_CPU, RLIMIT_FSIZE, RLIMIT_NOFILE, RLIMIT_NPROC,
+    RLIMIT_STACK, RLIM_INFINITY, RLIM_NLIMITS,
+};
+pub use seccomp::{
+    current_filter_syscall, FilterAction, SeccompFilterSpec, SyscallFilter, MAX_FILTERS,
+    MAX_FILTER_SYSCALLS, SECCOMP_MODE_ALLOW_LIST, SECCOMP_MODE_DENY_LIST, SECCOMP_SET_MODE_FILTER,
+};
+pub use signal::{
+    signal_description, DefaultAction, SignalFlags, SignalFrame, SignalInfo, SignalStack,
+    MAX_QUEUED_SIGNALS, MAX_SIG, MINSIGSTKSZ, SIGRTMAX, SIGRTMIN, SS_DISABLE, SS_ONSTACK,
+};
+pub use task::{ExecError, TaskControlBlock, TaskStatus};
+pub use timer::{
+    account_time, check_real_timers, get_itimer, now_us, set_itimer, ITimer, ITimerVal, TimeVal,
+    ITIMER_PROF, ITIMER_REAL, ITIMER_VIRTUAL,
+};
+pub use usage::{
+    us_to_ticks, RUsage, TaskUsage, TimeSpec, Tms, CLOCK_MONOTONIC, CLOCK_PROCESS_CPUTIME_ID,
+    CLOCK_REALTIME, CLOCK_THREAD_CPUTIME_ID, RUSAGE_CHILDREN, RUSAGE_SELF,
+};
+pub use wait::{
+    exited_status, is_stopped_status, signaled_status, stopped_status, WaitOptions,
+    CONTINUED_STATUS, WCOREFLAG,
+};
 
+/// Give up the CPU because the current task blocks or yields.
 pub fn suspend_current_and_run_next() {
+    switch_current_and_run_next(true);
+}
+
+/// Take the CPU away from the current task when its time slice is used up.
+pub fn preempt_current_and_run_next() {
+    switch_current_and_run_next(false);
+}
+
+fn switch_current_and_run_next(voluntary: bool) {
     //trace!("os::task::suspend_current_and_run_next");
+    // this may be the only chance to fire timers if every task is blocked
+    check_real_timers();
     // There must be an application running.
     let task = take_current_task().unwrap();
 
     // ---- access current TCB exclusively
     let mut task_inner = task.inner_exclusive_access();
+    // charge the kernel time before giving up the CPU
+    let now = now_us();
+    let system = now - task_inner.time_mark;
+    task_inner.time_mark = now;
+    account_time(&mut task_inner, 0, system);
+    if voluntary {
+        task_inner.usage.nvcsw += 1;
+    } else {
+        task_inner.usage.nivcsw += 1;
+    }
     let task_cx_ptr = &mut task_inner.task_cx as *mut KContext;
     // Change status to Ready
     task_inner.task_status = TaskStatus::Ready;
@@ -46,6 +111,8 @@ pub fn suspend_current_and_run_next() {
 pub const IDLE_PID: usize = 0;
 
 /// Exit the current 'Running' task and run the next task in task list.
+///
+/// `exit_code` is the encoded wait status reported to the parent.
 pub fn exit_current_and_run_next(exit_code: i32) {
     trace!("os::task::exit_current_and_run_next");
     // take from Processor
@@ -68,6 +135,34 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     inner.task_status = TaskStatus::Zombie;
     // Record exit code
     inner.exit_code = exit_code;
+    // a real timer must not fire for a recycled pid
+    set_itimer(pid, &mut inner, ITIMER_REAL, ITimer::default());
+    // tell the parent that one of its children exited, a thread is reaped
+    // without being waited for
+    if !task.is_thread() && inner.exit_signal != 0 {
+        notify_parent(&inner.parent, pid, inner.exit_signal);
+    }
+    // a tracer which is not the parent waits for the exit too
+    if let Some(tracer) = inner.tracer.as_ref() {
+        if !inner.parent.as_ref().map_or(false, |p| p.ptr_eq(tracer)) {
+            notify_parent(&inner.tracer, pid, SignalFlags::SIGCHLD.signum());
+        }
+    }
+    // and tasks traced by this one go on untraced
+    for tracee in inner.tracees.drain(..).filter_map(|t| t.upgrade()) {
+        tracee.inner_exclusive_access().trace_detach(0);
+    }
+    // wake up whoever waits for the thread to exit
+    if inner.clear_child_tid != 0 {
+        let clear_child_tid = inner.clear_child_tid;
+        if inner.memory_set.exclusive_access().check_user_range(
+            clear_child_tid,
+            size_of::<u32>(),
+            MappingFlags::W,
+        ) {
+            *translated_refmut(inner.get_user_token(), clear_child_tid as *mut u32) = 0;
+        }
+    }
     // do not move to its parent but under initproc
 
     // ++++++ access initproc TCB exclusively
@@ -81,10 +176,16 @@ pub fn exit_current_and_run_next(exit_code: i32) {
     // ++++++ release parent PCB
 
     inner.children.clear();
-    // deallocate user space
-    inner.memory_set.recycle_data_pages();
-    // drop file descriptors
-    inner.fd_table.clear();
+    // deallocate user space unless a thread or the parent of a vfork child
+    // still runs in it, which then goes on
+    if Arc::strong_count(&inner.memory_set) == 1 {
+        inner.memory_set.exclusive_access().recycle_data_pages();
+    }
+    inner.borrowed_vm = false;
+    // drop file descriptors unless they are shared
+    if Arc::strong_count(&inner.fd_table) == 1 {
+        inner.fd_table.exclusive_access().clear();
+    }
     drop(inner);
     // **** release current PCB
     // drop task manually to maintain rc correctly
@@ -96,7 +197,7 @@ pub fn exit_current_and_run_next(exit_code: i32) {
 
 lazy_static! {
     pub static ref INITPROC: Arc<TaskControlBlock> = Arc::new({
-        let inode = open_file("initproc", OpenFlags::RDONLY).unwrap();
+        let inode = open_file("/", "initproc", OpenFlags::RDONLY).unwrap();
         let v = inode.read_all();
         TaskControlBlock::new(v.as_slice())
     });
@@ -107,6 +208,58 @@ pub fn add_initproc() {
     add_task(INITPROC.clone());
 }
 
+/// Send `sig` to the parent of task `pid` which exited, stopped or continued.
+fn notify_parent(parent: &Option<Weak<TaskControlBlock>>, pid: usize, sig: usize) {
+    if let Some(parent) = parent.as_ref().and_then(|p| p.upgrade()) {
+        let signal = SignalFlags::from_bits(1 << sig).unwrap();
+        parent
+            .inner_exclusive_access()
+            .add_signal(signal, SignalInfo::user(sig, pid));
+    }
+}
+
+/// Charge the time since the current task left user mode as user time.
+pub fn current_trap_enter() {
+    let task = current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    let now = now_us();
+    let user = now - task_inner.time_mark;
+    task_inner.time_mark = now;
+    account_time(&mut task_inner, user, 0);
+}
+
+/// Charge the time spent in the kernel before the current task returns
+/// to user mode.
+pub fn current_trap_exit() {
+    let task = current_task().unwrap();
+    let mut task_inner = task.inner_exclusive_access();
+    let now = now_us();
+    let system = now - task_inner.time_mark;
+    task_inner.time_mark = now;
+    account_time(&mut task_inner, 0, system);
+}
+
+/// Soft limit of `resource` for the current task.
+pub fn current_rlimit(resource: usize) -> usize {
+    let task = current_task().unwrap();
+    let limit = task.inner_exclusive_access().rlimits[resource].cur;
+    limit
+}
+
+/// Current directory of the current task.
+pub fn current_cwd() -> String {
+    let task = current_task().unwrap();
+    let cwd = task.inner_exclusive_access().cwd.exclusive_access().clone();
+    cwd
+}
+
+/// Count a page fault taken by the current task. Every page is mapped up
+/// front, so these are the faults which end in SIGSEGV.
+pub fn current_count_page_fault() {
+    let task = current_task().unwrap();
+    task.inner_exclusive_access().usage.page_faults += 1;
+}
+
 pub fn check_signals_error_of_current() -> Option<(i32, &'static str)> {
     trace!("os::task::check_signals_error_of_current");
     let task = current_task().unwrap();
@@ -115,42 +268,146 @@ pub fn check_signals_error_of_current() -> Option<(i32, &'static str)> {
     //     "[K] check_signals_error_of_current {:?}",
     //     task_inner.signals
     // );
-    task_inner.signals.check_error()
+    task_inner
+        .killed
+        .map(|signum| (signum as i32, signal_description(signum)))
 }
 
-pub fn current_add_signal(signal: SignalFlags) {
+pub fn current_add_signal(signal: SignalFlags, info: SignalInfo) {
     trace!("os::task::current_add_signal");
     let task = current_task().unwrap();
     let mut task_inner = task.inner_exclusive_access();
-    task_inner.signals |= signal;
+    task_inner.add_signal(signal, info);
+    // a fault would happen again if its signal were blocked or ignored
+    let sig = info.signo as usize;
+    task_inner.signal_mask.remove(signal);
+    let mut signal_actions = task_inner.signal_actions.exclusive_access();
+    if signal_actions.table[sig].handler == SIG_IGN {
+        signal_actions.table[sig].handler = SIG_DFL;
+    }
     // println!(
     //     "[K] current_add_signal:: current task sigflag {:?}",
     //     task_inner.signals
     // );
 }
 
-fn call_kernel_signal_handler(signal: SignalFlags) {
+/// Whether a pending signal should interrupt a blocking syscall of the
+/// current task, i.e. it is not masked and will run a handler or kill it.
+pub fn current_has_interrupting_signal() -> bool {
+    let task = current_task().unwrap();
+    let task_inner = task.inner_exclusive_access();
+    let pending = task_inner.signals & !task_inner.signal_mask.blockable();
+    (0..=MAX_SIG).any(|sig| {
+        let signal = SignalFlags::from_bits(1 << sig).unwrap();
+        if !pending.contains(signal) {
+            return false;
+        }
+        // the tracer has to see it first
+        if task_inner.tracer.is_some()
+            && signal != SignalFlags::SIGKILL
+            && !task_inner.trace_passed.contains(signal)
+        {
+            return true;
+        }
+        match task_inner.signal_actions.exclusive_access().table[sig].handler {
+            _ if signal == SignalFlags::SIGKILL => true,
+            SIG_DFL => matches!(
+                signal.default_action(),
+                DefaultAction::Terminate | DefaultAction::Core
+            ),
+            SIG_IGN => false,
+            _ => true,
+        }
+    })
+}
+
+/// Remember the last syscall of the current task if it was interrupted,
+/// so that it can be restarted after a SA_RESTART handler.
+pub fn current_set_interrupted_syscall(syscall: Option<(usize, usize)>) {
+    let task = current_task().unwrap();
+    task.inner_exclusive_access().interrupted_syscall = syscall;
+}
+
+/// Length of the syscall instruction, to step back over it on restart.
+#[cfg(target_arch = "x86_64")]
+const SYSCALL_INSN_LEN: usize = 2;
+#[cfg(not(target_arch = "x86_64"))]
+const SYSCALL_INSN_LEN: usize = 4;
+
+/// Bytes below the user sp which leaf functions may use without moving sp.
+#[cfg(target_arch = "x86_64")]
+const RED_ZONE: usize = 128;
+#[cfg(not(target_arch = "x86_64"))]
+const RED_ZONE: usize = 0;
+
+/// Make the user handler return to `restorer`, which calls `sigreturn`.
+/// Return false if the return address can't be pushed.
+#[cfg(target_arch = "x86_64")]
+fn set_signal_return(
+    task_inner: &TaskControlBlockInner,
+    trap_ctx: &mut TrapFrame,
+    restorer: usize,
+) -> bool {
+    // the return address lives on the stack
+    let sp = trap_ctx[TrapFrameArgs::SP] - size_of::<usize>();
+    if !task_inner.memory_set.exclusive_access().check_user_range(
+        sp,
+        size_of::<usize>(),
+        MappingFlags::W,
+    ) {
+        return false;
+    }
+    *translated_refmut(task_inner.get_user_token(), sp as *mut usize) = restorer;
+    trap_ctx[TrapFrameArgs::SP] = sp;
+    true
+}
+#[cfg(not(target_arch = "x86_64"))]
+fn set_signal_return(
+    _task_inner: &TaskControlBlockInner,
+    trap_ctx: &mut TrapFrame,
+    restorer: usize,
+) -> bool {
+    trap_ctx[TrapFrameArgs::RA] = restorer;
+    true
+}
+
+/// Resume a task stopped by a signal, SIGCONT does this whatever its action is.
+fn continue_task(pid: usize, task_inner: &mut TaskControlBlockInner) {
+    if task_inner.frozen {
+        task_inner.frozen = false;
+        task_inner.state_change = Some(CONTINUED_STATUS);
+        notify_parent(&task_inner.parent, pid, SignalFlags::SIGCHLD.signum());
+    }
+}
+
+/// Carry out the default action of a signal, or ignore it.
+fn call_kernel_signal_handler(sig: usize, signal: SignalFlags) {
     trace!("os::task::call_kernel_signal_handler");
     let task = current_task().unwrap();
     let mut task_inner = task.inner_exclusive_access();
-    match signal {
-        SignalFlags::SIGSTOP => {
-            task_inner.frozen = true;
-            task_inner.signals ^= SignalFlags::SIGSTOP;
-        }
-        SignalFlags::SIGCONT => {
-            if task_inner.signals.contains(SignalFlags::SIGCONT) {
-                task_inner.signals ^= SignalFlags::SIGCONT;
-                task_inner.frozen = false;
-            }
+    let info = task_inner.take_signal(signal);
+    if signal == SignalFlags::SIGCONT {
+        continue_task(task.getpid(), &mut task_inner);
+    }
+    // SIGKILL and SIGSTOP can never be ignored
+    if task_inner.signal_actions.exclusive_access().table[sig].handler == SIG_IGN
+        && signal != SignalFlags::SIGKILL
+        && signal != SignalFlags::SIGSTOP
+    {
+        return;
+    }
+    match signal.default_action() {
+        DefaultAction::Terminate | DefaultAction::Core => {
+            task_inner.killed = Some(sig);
+            task_inner.killed_info = info;
         }
-        _ => {
-            // println!(
-            //     "[K] call_kernel_signal_handler:: current task sigflag {:?}",
-            //     task_inner.signals
-            // );
-            task_inner.killed = true;
+        DefaultAction::Stop => {
+            task_inner.frozen = true;
+            task_inner.state_change = Some(stopped_status(sig));
+            let sigchld = SignalFlags::SIGCHLD.signum();
+            notify_parent(&task_inner.parent, task.getpid(), sigchld);
         }
+        DefaultAction::Ignore | DefaultAction::Continue => {}
     }
 }
 
@@ -159,66 +416,130 @@ fn call_user_signal_handler(sig: usize, signal: SignalFlags) {
     let task = current_task().unwrap();
     let mut task_inner = task.inner_exclusive_access();
 
-    let handler = task_inner.signal_actions.table[sig].handler;
-    if handler != 0 {
-        // user handler
-
-        // handle flag
-        task_inner.handling_sig = sig as isize;
-        task_inner.signals ^= signal;
+    if signal == SignalFlags::SIGCONT {
+        continue_task(task.getpid(), &mut task_inner);
+    }
+    let action = task_inner.signal_actions.exclusive_access().table[sig];
 
-        // backup trapframe
-        let trap_ctx = task_inner.get_trap_cx();
-        task_inner.trap_ctx_backup = Some(trap_ctx.clone());
+    // handle flag
+    let info = task_inner.take_signal(signal);
 
-        // modify trapframe
-        trap_ctx[TrapFrameArgs::SEPC] = handler;
+    // the context to go back to, restart an interrupted syscall if asked
+    let trap_ctx = task_inner.get_trap_cx();
+    let mut saved_ctx = trap_ctx.clone();
+    if let Some((syscall_id, arg0)) = task_inner.interrupted_syscall.take() {
+        if action.flags.contains(SignalActionFlags::SA_RESTART) {
+            saved_ctx[TrapFrameArgs::SEPC] -= SYSCALL_INSN_LEN;
+            saved_ctx[TrapFrameArgs::RET] = arg0;
+            saved_ctx[TrapFrameArgs::SYSCALL] = syscall_id;
+        }
+    }
 
-        // put args (a0)
-        trap_ctx[TrapFrameArgs::ARG0] = sig;
+    // push the signal frame on the user stack, or on the alternate stack
+    // if the handler wants it and we are not running on it yet
+    let user_sp = trap_ctx[TrapFrameArgs::SP];
+    let stack_top = if action.flags.contains(SignalActionFlags::SA_ONSTACK)
+        && task_inner.alt_stack.flags & SS_DISABLE == 0
+        && !task_inner.alt_stack.contains(user_sp)
+    {
+        task_inner.alt_stack.sp + task_inner.alt_stack.size
     } else {
-        info!("task id: {}", task.getpid());
-        info!("{:#x?}", task_inner.get_trap_cx());
-        // default action
-        println!("[K] task/call_user_signal_handler: default action: ignore it or kill process");
+        // the sp is the user's, saturating ends up on the unmapped page 0
+        user_sp.saturating_sub(RED_ZONE)
+    };
+    let frame_size = size_of::<SignalFrame>();
+    let frame_addr = stack_top.saturating_sub(frame_size) & !0xf;
+    if !task_inner.memory_set.exclusive_access().check_user_range(
+        frame_addr,
+        frame_size,
+        MappingFlags::W,
+    ) {
+        // no room for the frame, the task can only be killed
+        task_inner.killed = Some(SignalFlags::SIGSEGV.signum());
+        return;
+    }
+    let token = task_inner.get_user_token();
+    *translated_refmut(token, frame_addr as *mut SignalFrame) = SignalFrame {
+        info,
+        mask: task_inner.signal_mask,
+        handling_sig: task_inner.handling_sig,
+        prev: task_inner.sig_frame,
+        trap_cx: saved_ctx,
+    };
+    task_inner.sig_frame = frame_addr;
+    task_inner.handling_sig = sig as isize;
+
+    // block signals while the handler runs
+    task_inner.signal_mask |= action.mask.blockable();
+    if !action.flags.contains(SignalActionFlags::SA_NODEFER) {
+        task_inner.signal_mask |= signal;
+    }
+    if action.flags.contains(SignalActionFlags::SA_RESETHAND) {
+        task_inner.signal_actions.exclusive_access().table[sig] = SignalAction::default();
+    }
+
+    // modify trapframe
+    trap_ctx[TrapFrameArgs::SEPC] = action.handler;
+    trap_ctx[TrapFrameArgs::SP] = frame_addr;
+    if !set_signal_return(&task_inner, trap_ctx, action.restorer) {
+        task_inner.killed = Some(SignalFlags::SIGSEGV.signum());
+        return;
+    }
+
+    // put args (a0, a1, a2)
+    trap_ctx[TrapFrameArgs::ARG0] = sig;
+    if action.flags.contains(SignalActionFlags::SA_SIGINFO) {
+        // info is the first field of the frame
+        trap_ctx[TrapFrameArgs::ARG1] = frame_addr;
+        trap_ctx[TrapFrameArgs::ARG2] = frame_addr;
     }
 }
 
 fn check_pending_signals() {
     trace!("os::task::check_pending_signals");
+    {
+        let task = current_task().unwrap();
+        let mut task_inner = task.inner_exclusive_access();
+        // only SIGKILL gets through to a task stopped for its tracer
+        if task_inner.trace_stopped {
+            if task_inner.signals.contains(SignalFlags::SIGKILL) {
+                task_inner.killed = Some(SignalFlags::SIGKILL.signum());
+            }
+            return;
+        }
+    }
     for sig in 0..(MAX_SIG + 1) {
         let task = current_task().unwrap();
-        let task_inner = task.inner_exclusive_access();
+        let mut task_inner = task.inner_exclusive_access();
         let signal = SignalFlags::from_bits(1 << sig).unwrap();
-        if task_inner.signals.contains(signal) && (!task_inner.signal_mask.contains(signal)) {
-            let mut masked = true;
-            let handling_sig = task_inner.handling_sig;
-            if handling_sig == -1 {
-                masked = false;
-            } else {
-                let handling_sig = handling_sig as usize;
-                if !task_inner.signal_actions.table[handling_sig]
-                    .mask
-                    .contains(signal)
-                {
-                    masked = false;
-                }
+        // the mask already covers the signals blocked by running handlers,
+        // but never SIGKILL or SIGSTOP
+        let masked = task_inner.signal_mask.blockable().contains(signal);
+        if task_inner.signals.contains(signal) && !masked {
+            // a traced task stops for every signal but SIGKILL, which the
+            // tracer may pass on or not
+            if task_inner.tracer.is_some()
+                && signal != SignalFlags::SIGKILL
+                && !task_inner.trace_passed.contains(signal)
+            {
+                task_inner.trace_stop(task.getpid(), signal);
+                return;
             }
-            if !masked {
-                drop(task_inner);
-                drop(task);
-                if signal == SignalFlags::SIGKILL
-                    || signal == SignalFlags::SIGSTOP
-                    || signal == SignalFlags::SIGCONT
-                    || signal == SignalFlags::SIGDEF
-                {
-                    // signal is a kernel signal
-                    call_kernel_signal_handler(signal);
-                } else {
-                    // signal is a user signal
-                    call_user_signal_handler(sig, signal);
-                    return;
-                }
+            task_inner.trace_passed.remove(signal);
+            let handler = task_inner.signal_actions.exclusive_access().table[sig].handler;
+            drop(task_inner);
+            drop(task);
+            if signal == SignalFlags::SIGKILL
+                || signal == SignalFlags::SIGSTOP
+                || handler == SIG_DFL
+                || handler == SIG_IGN
+            {
+                // signal is a kernel signal
+                call_kernel_signal_handler(sig, signal);
+            } else {
+                // signal is a user signal
+                call_user_signal_handler(sig, signal);
+                return;
             }
         }
     }
@@ -228,12 +549,12 @@ pub fn handle_signals() {
     trace!("os::task::handle_signals");
     loop {
         check_pending_signals();
-        let (frozen, killed) = {
+        let (stopped, killed) = {
             let task = current_task().unwrap();
             let task_inner = task.inner_exclusive_access();
-            (task_inner.frozen, task_inner.killed)
+            (task_inner.frozen || task_inner.trace_stopped, task_inner.killed)
         };
-        if !frozen || killed {
+        if !stopped || killed.is_some() {
             break;
         }
         suspend_current_and_run_next();
@@ -0,0 +1,24 @@
+/// Which syscall ABI a task speaks, chosen by exec from the ELF image
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Personality {
+    /// programs built with user_lib, which marks them with a note section
+    Native,
+    /// any other static ELF file, such as a musl busybox
+    Linux,
+}
+
+// entries of the auxiliary vector, same values as Linux
+pub const AT_NULL: usize = 0;
+pub const AT_PHDR: usize = 3;
+pub const AT_PHENT: usize = 4;
+pub const AT_PHNUM: usize = 5;
+pub const AT_PAGESZ: usize = 6;
+pub const AT_ENTRY: usize = 9;
+pub const AT_UID: usize = 11;
+pub const AT_EUID: usize = 12;
+pub const AT_GID: usize = 13;
+pub const AT_EGID: usize = 14;
+pub const AT_CLKTCK: usize = 17;
+pub const AT_SECURE: usize = 23;
+pub const AT_RANDOM: usize = 25;
+pub const AT_SYSINFO_EHDR: usize = 33;
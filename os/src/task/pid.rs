@@ -50,3 +50,9 @@ impl Drop for PidHandle {
 pub fn pid_alloc() -> PidHandle {
     PID_ALLOCATOR.exclusive_access().alloc()
 }
+
+/// Number of pids in use, zombies included.
+pub fn pid_count() -> usize {
+    let allocator = PID_ALLOCATOR.exclusive_access();
+    allocator.current - allocator.recycled.len()
+}
@@ -1,5 +1,5 @@
 use super::TaskControlBlock;
-use super::{fetch_task, TaskStatus};
+use super::{fetch_task, now_us, TaskStatus};
 use crate::sync::UPSafeCell;
 use alloc::sync::Arc;
 use polyhal::boot::boot_page_table;
@@ -45,8 +45,10 @@ pub fn run_tasks() {
             let mut task_inner = task.inner_exclusive_access();
             let next_task_cx_ptr = &task_inner.task_cx as *const KContext;
             task_inner.task_status = TaskStatus::Running;
+            // time before now was spent waiting, not running
+            task_inner.time_mark = now_us();
             // task_inner.memory_set.activate();
-            let token = task_inner.memory_set.token();
+            let token = task_inner.get_user_token();
             drop(task_inner);
             // release coming task TCB manually
             processor.current = Some(task);
@@ -0,0 +1,440 @@
+#[cfg(target_arch = "x86_64")]
+use super::signal::USER_RFLAGS;
+#[cfg(target_arch = "aarch64")]
+use super::signal::USER_SPSR;
+use super::task::TaskControlBlockInner;
+use super::{notify_parent, stopped_status, SignalFlags, SignalInfo};
+#[cfg(target_arch = "x86_64")]
+use crate::config::USER_SPACE_END;
+use crate::mm::MemorySet;
+use core::mem::size_of;
+use polyhal::trapframe::{TrapFrame, TrapFrameArgs};
+
+/// User registers of a traced task, in the layout of the Linux
+/// `user_regs_struct` of the arch, which is also its `elf_gregset_t`
+#[cfg(target_arch = "riscv64")]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UserRegs {
+    pub pc: usize,
+    // x1 to x31
+    pub x: [usize; 31],
+}
+
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UserRegs {
+    pub r15: usize,
+    pub r14: usize,
+    pub r13: usize,
+    pub r12: usize,
+    pub rbp: usize,
+    pub rbx: usize,
+    pub r11: usize,
+    pub r10: usize,
+    pub r9: usize,
+    pub r8: usize,
+    pub rax: usize,
+    pub rcx: usize,
+    pub rdx: usize,
+    pub rsi: usize,
+    pub rdi: usize,
+    pub orig_rax: usize,
+    pub rip: usize,
+    pub cs: usize,
+    pub eflags: usize,
+    pub rsp: usize,
+    pub ss: usize,
+    pub fs_base: usize,
+    pub gs_base: usize,
+    pub ds: usize,
+    pub es: usize,
+    pub fs: usize,
+    pub gs: usize,
+}
+
+#[cfg(target_arch = "aarch64")]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UserRegs {
+    pub regs: [usize; 31],
+    pub sp: usize,
+    pub pc: usize,
+    pub pstate: usize,
+}
+
+#[cfg(target_arch = "loongarch64")]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UserRegs {
+    pub regs: [usize; 32],
+    pub orig_a0: usize,
+    pub csr_era: usize,
+    pub csr_badv: usize,
+    pub reserved: [usize; 10],
+}
+
+impl UserRegs {
+    /// The registers as the words of an `elf_gregset_t`.
+    pub fn as_words(&self) -> &[usize] {
+        let len = size_of::<Self>() / size_of::<usize>();
+        unsafe { core::slice::from_raw_parts(self as *const Self as *const usize, len) }
+    }
+}
+
+#[cfg(target_arch = "riscv64")]
+impl UserRegs {
+    pub fn from_trap_cx(cx: &TrapFrame) -> Self {
+        let mut regs = Self {
+            pc: cx.sepc,
+            ..Default::default()
+        };
+        regs.x.copy_from_slice(&cx.x[1..]);
+        regs
+    }
+    /// Write the registers to `cx`, returning false if one of them may
+    /// not be set from user space.
+    pub fn apply(&self, cx: &mut TrapFrame) -> bool {
+        cx.sepc = self.pc;
+        cx.x[1..].copy_from_slice(&self.x);
+        true
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+impl UserRegs {
+    pub fn from_trap_cx(cx: &TrapFrame) -> Self {
+        Self {
+            r15: cx.r15,
+            r14: cx.r14,
+            r13: cx.r13,
+            r12: cx.r12,
+            rbp: cx.rbp,
+            rbx: cx.rbx,
+            r11: cx.r11,
+            r10: cx.r10,
+            r9: cx.r9,
+            r8: cx.r8,
+            rax: cx.rax,
+            rcx: cx.rcx,
+            rdx: cx.rdx,
+            rsi: cx.rsi,
+            rdi: cx.rdi,
+            // trace stops come after any syscall has finished
+            orig_rax: usize::MAX,
+            rip: cx.rip,
+            cs: cx.cs,
+            eflags: cx.rflags,
+            rsp: cx.rsp,
+            ss: cx.ss,
+            fs_base: cx.fs_base,
+            gs_base: cx.gs_base,
+            ..Default::default()
+        }
+    }
+    /// Write the registers to `cx`, returning false if one of them may
+    /// not be set from user space. Segments and gs_base are left alone,
+    /// and of eflags only the bits user code may change are taken.
+    pub fn apply(&self, cx: &mut TrapFrame) -> bool {
+        // returning to a non-canonical rip faults in the kernel
+        if self.rip >= USER_SPACE_END || self.fs_base >= USER_SPACE_END {
+            return false;
+        }
+        cx.r15 = self.r15;
+        cx.r14 = self.r14;
+        cx.r13 = self.r13;
+        cx.r12 = self.r12;
+        cx.rbp = self.rbp;
+        cx.rbx = self.rbx;
+        cx.r11 = self.r11;
+        cx.r10 = self.r10;
+        cx.r9 = self.r9;
+        cx.r8 = self.r8;
+        cx.rax = self.rax;
+        cx.rcx = self.rcx;
+        cx.rdx = self.rdx;
+        cx.rsi = self.rsi;
+        cx.rdi = self.rdi;
+        cx.rip = self.rip;
+        cx.rflags = (cx.rflags & !USER_RFLAGS) | (self.eflags & USER_RFLAGS);
+        cx.rsp = self.rsp;
+        cx.fs_base = self.fs_base;
+        true
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+impl UserRegs {
+    pub fn from_trap_cx(cx: &TrapFrame) -> Self {
+        Self {
+            regs: cx.regs,
+            sp: cx.sp,
+            pc: cx.elr,
+            pstate: cx.spsr,
+        }
+    }
+    /// Write the registers to `cx`, returning false if one of them may
+    /// not be set from user space. Of pstate only the condition flags are
+    /// taken.
+    pub fn apply(&self, cx: &mut TrapFrame) -> bool {
+        cx.regs = self.regs;
+        cx.sp = self.sp;
+        cx.elr = self.pc;
+        cx.spsr = (cx.spsr & !USER_SPSR) | (self.pstate & USER_SPSR);
+        true
+    }
+}
+
+#[cfg(target_arch = "loongarch64")]
+impl UserRegs {
+    pub fn from_trap_cx(cx: &TrapFrame) -> Self {
+        Self {
+            regs: cx.regs,
+            csr_era: cx.era,
+            ..Default::default()
+        }
+    }
+    /// Write the registers to `cx`, returning false if one of them may
+    /// not be set from user space.
+    pub fn apply(&self, cx: &mut TrapFrame) -> bool {
+        // r0 is hardwired to zero
+        cx.regs[1..].copy_from_slice(&self.regs[1..]);
+        cx.era = self.csr_era;
+        true
+    }
+}
+
+/// A breakpoint planted at `addr` for a single step, over the first `len`
+/// bytes of `saved`
+#[derive(Debug, Clone, Copy)]
+pub struct StepBreakpoint {
+    addr: usize,
+    saved: [u8; 2],
+    len: usize,
+}
+
+impl StepBreakpoint {
+    #[cfg(any(target_arch = "riscv64", target_arch = "x86_64"))]
+    fn plant(memory_set: &MemorySet, addr: usize) -> Option<Self> {
+        let mut saved = [0u8; 2];
+        let len = STEP_BREAK.len();
+        (memory_set.read_bytes(addr, &mut saved[..len]) && memory_set.write_bytes(addr, STEP_BREAK))
+            .then_some(Self { addr, saved, len })
+    }
+    /// Put back the bytes under the breakpoint, and move `cx` back to its
+    /// start if it trapped on it.
+    fn remove(&self, memory_set: &MemorySet, cx: &mut TrapFrame) {
+        memory_set.write_bytes(self.addr, &self.saved[..self.len]);
+        if (self.addr..=self.addr + self.len).contains(&cx[TrapFrameArgs::SEPC]) {
+            cx[TrapFrameArgs::SEPC] = self.addr;
+        }
+    }
+}
+
+/// A single step the task is in the middle of: the breakpoint planted at
+/// the next instruction, or none if rflags.TF traps after the instruction
+/// on x86_64
+#[derive(Debug, Clone, Copy)]
+pub struct SingleStep {
+    breakpoint: Option<StepBreakpoint>,
+}
+
+// c.ebreak, which fits in front of any instruction
+#[cfg(target_arch = "riscv64")]
+const STEP_BREAK: &[u8] = &0x9002u16.to_le_bytes();
+#[cfg(target_arch = "x86_64")]
+const STEP_BREAK: &[u8] = &[0xcc];
+
+/// rflags.TF, which raises a debug exception after the next instruction
+#[cfg(target_arch = "x86_64")]
+const RFLAGS_TF: usize = 1 << 8;
+#[cfg(target_arch = "x86_64")]
+const DEBUG_VECTOR: usize = 1;
+
+/// Whether the trap in `cx` is the debug exception rflags.TF raised after
+/// a single step, which other archs report as a breakpoint.
+#[cfg(target_arch = "x86_64")]
+pub fn is_step_trap(cx: &TrapFrame) -> bool {
+    cx.vector == DEBUG_VECTOR
+}
+#[cfg(not(target_arch = "x86_64"))]
+pub fn is_step_trap(_cx: &TrapFrame) -> bool {
+    false
+}
+
+/// Sign-extend the low `bits` bits of `imm`.
+#[cfg(target_arch = "riscv64")]
+fn sext(imm: u32, bits: u32) -> isize {
+    ((imm << (32 - bits)) as i32 >> (32 - bits)) as isize
+}
+
+/// Address of the instruction run after `inst` at the pc of `cx`, which
+/// only jumps and taken branches of RV64GC move anywhere but right after.
+#[cfg(target_arch = "riscv64")]
+fn next_pc(cx: &TrapFrame, inst: u32) -> usize {
+    let pc = cx.sepc;
+    let reg = |i: u32| if i == 0 { 0 } else { cx.x[i as usize] };
+    let bit = |i: u32| (inst >> i) & 1;
+    let bits = |lo: u32, len: u32| (inst >> lo) & ((1 << len) - 1);
+    if inst & 0b11 != 0b11 {
+        let rs1_prime = reg(8 + bits(7, 3));
+        let offset = match (inst & 0b11, bits(13, 3)) {
+            // c.j
+            (0b01, 0b101) => sext(
+                bit(12) << 11
+                    | bit(11) << 4
+                    | bits(9, 2) << 8
+                    | bit(8) << 10
+                    | bit(7) << 6
+                    | bit(6) << 7
+                    | bits(3, 3) << 1
+                    | bit(2) << 5,
+                12,
+            ),
+            // c.beqz and c.bnez
+            (0b01, funct3 @ (0b110 | 0b111)) if (rs1_prime == 0) == (funct3 == 0b110) => sext(
+                bit(12) << 8 | bits(10, 2) << 3 | bits(5, 2) << 6 | bits(3, 2) << 1 | bit(2) << 5,
+                9,
+            ),
+            // c.jr and c.jalr
+            (0b10, 0b100) if bits(2, 5) == 0 && bits(7, 5) != 0 => {
+                return reg(bits(7, 5)) & !1;
+            }
+            _ => 2,
+        };
+        return pc.wrapping_add_signed(offset);
+    }
+    let (rs1, rs2) = (reg(bits(15, 5)), reg(bits(20, 5)));
+    let offset = match inst & 0x7f {
+        // jal
+        0x6f => sext(
+            bit(31) << 20 | bits(21, 10) << 1 | bit(20) << 11 | bits(12, 8) << 12,
+            21,
+        ),
+        // jalr
+        0x67 => return rs1.wrapping_add_signed(sext(bits(20, 12), 12)) & !1,
+        // branches
+        0x63 => {
+            let taken = match bits(12, 3) {
+                0b000 => rs1 == rs2,
+                0b001 => rs1 != rs2,
+                0b100 => (rs1 as isize) < rs2 as isize,
+                0b101 => rs1 as isize >= rs2 as isize,
+                0b110 => rs1 < rs2,
+                0b111 => rs1 >= rs2,
+                _ => false,
+            };
+            if taken {
+                sext(
+                    bit(31) << 12 | bits(25, 6) << 5 | bits(8, 4) << 1 | bit(7) << 11,
+                    13,
+                )
+            } else {
+                4
+            }
+        }
+        _ => 4,
+    };
+    pc.wrapping_add_signed(offset)
+}
+
+impl TaskControlBlockInner {
+    /// Stop task `pid` for its tracer instead of delivering `signal`, which
+    /// the tracer may pass on when it resumes the task.
+    pub fn trace_stop(&mut self, pid: usize, signal: SignalFlags) {
+        self.trace_step_done();
+        self.trace_info = self.take_signal(signal);
+        self.trace_stopped = true;
+        self.state_change = Some(stopped_status(signal.signum()));
+        notify_parent(&self.tracer, pid, SignalFlags::SIGCHLD.signum());
+    }
+    /// Leave a trace stop and deliver signal `sig` unless it is 0.
+    pub fn trace_resume(&mut self, sig: usize) {
+        self.trace_stopped = false;
+        self.state_change = None;
+        if sig == 0 {
+            return;
+        }
+        let signal = SignalFlags::from_bits(1 << sig).unwrap();
+        let info = if self.trace_info.signo as usize == sig {
+            self.trace_info
+        } else {
+            SignalInfo::kernel(sig, 0)
+        };
+        self.add_signal(signal, info);
+        self.trace_passed |= signal;
+    }
+    /// Arrange for the task to trap with SIGTRAP after its next instruction
+    /// once resumed, returning false if the arch or its memory does not
+    /// allow it.
+    #[cfg(target_arch = "riscv64")]
+    pub fn trace_step(&mut self) -> bool {
+        let cx = self.get_trap_cx();
+        let memory_set = self.memory_set.exclusive_access();
+        let mut inst = [0u8; 4];
+        if !memory_set.read_bytes(cx.sepc, &mut inst[..2])
+            || (inst[0] & 0b11 == 0b11 && !memory_set.read_bytes(cx.sepc + 2, &mut inst[2..]))
+        {
+            return false;
+        }
+        let addr = next_pc(cx, u32::from_le_bytes(inst));
+        let breakpoint = match StepBreakpoint::plant(&memory_set, addr) {
+            Some(breakpoint) => breakpoint,
+            None => return false,
+        };
+        drop(memory_set);
+        self.single_step = Some(SingleStep {
+            breakpoint: Some(breakpoint),
+        });
+        true
+    }
+    #[cfg(target_arch = "x86_64")]
+    pub fn trace_step(&mut self) -> bool {
+        let cx = self.get_trap_cx();
+        let memory_set = self.memory_set.exclusive_access();
+        let mut inst = [0u8; 2];
+        // TF would trap in the kernel right after a syscall instruction
+        let breakpoint = if memory_set.read_bytes(cx.rip, &mut inst) && inst == [0x0f, 0x05] {
+            match StepBreakpoint::plant(&memory_set, cx.rip + 2) {
+                Some(breakpoint) => Some(breakpoint),
+                None => return false,
+            }
+        } else {
+            cx.rflags |= RFLAGS_TF;
+            None
+        };
+        drop(memory_set);
+        self.single_step = Some(SingleStep { breakpoint });
+        true
+    }
+    // not done on these yet, which need their own debug hardware or
+    // branch decoding
+    #[cfg(any(target_arch = "aarch64", target_arch = "loongarch64"))]
+    pub fn trace_step(&mut self) -> bool {
+        false
+    }
+    /// Undo `trace_step` once the task has stopped, taking the pc back to
+    /// the start of the breakpoint it may have trapped on.
+    fn trace_step_done(&mut self) {
+        let step = match self.single_step.take() {
+            Some(step) => step,
+            None => return,
+        };
+        let cx = self.get_trap_cx();
+        #[cfg(target_arch = "x86_64")]
+        {
+            cx.rflags &= !RFLAGS_TF;
+        }
+        if let Some(breakpoint) = step.breakpoint {
+            breakpoint.remove(&self.memory_set.exclusive_access(), cx);
+        }
+    }
+    /// Stop being traced, leaving a trace stop with `sig` like `trace_resume`.
+    pub fn trace_detach(&mut self, sig: usize) {
+        self.tracer = None;
+        if self.trace_stopped {
+            self.trace_resume(sig);
+        }
+    }
+}
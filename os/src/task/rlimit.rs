@@ -0,0 +1,44 @@
+use crate::config::USER_STACK_SIZE;
+
+pub const RLIMIT_CPU: usize = 0;
+pub const RLIMIT_FSIZE: usize = 1;
+pub const RLIMIT_STACK: usize = 3;
+pub const RLIMIT_CORE: usize = 4;
+pub const RLIMIT_NPROC: usize = 6;
+pub const RLIMIT_NOFILE: usize = 7;
+pub const RLIMIT_AS: usize = 9;
+/// Number of resources, same as Linux although only the above are enforced
+pub const RLIM_NLIMITS: usize = 16;
+
+pub const RLIM_INFINITY: usize = usize::MAX;
+
+/// Limit of a resource, same layout as Linux `struct rlimit`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RLimit {
+    // soft limit, what is enforced
+    pub cur: usize,
+    // hard limit, the ceiling of cur which can only be lowered
+    pub max: usize,
+}
+
+impl RLimit {
+    pub const fn new(cur: usize, max: usize) -> Self {
+        Self { cur, max }
+    }
+    pub const fn unlimited() -> Self {
+        Self::new(RLIM_INFINITY, RLIM_INFINITY)
+    }
+}
+
+/// Limits of the initial process, every other process inherits them.
+pub fn default_rlimits() -> [RLimit; RLIM_NLIMITS] {
+    let mut rlimits = [RLimit::unlimited(); RLIM_NLIMITS];
+    // the user stack is mapped eagerly, so it has to be bounded
+    rlimits[RLIMIT_STACK] = RLimit::new(USER_STACK_SIZE, 8 * 1024 * 1024);
+    rlimits[RLIMIT_NPROC] = RLimit::new(256, 256);
+    rlimits[RLIMIT_NOFILE] = RLimit::new(1024, 4096);
+    // as on Linux, a crash only leaves a core file when asked to
+    rlimits[RLIMIT_CORE] = RLimit::new(0, RLIM_INFINITY);
+    rlimits
+}
@@ -0,0 +1,129 @@
+use super::{current_add_signal, current_task, SignalFlags, SignalInfo};
+use crate::syscall::Errno;
+use alloc::vec::Vec;
+
+/// Install a filter with `sys_seccomp`, same value as Linux
+pub const SECCOMP_SET_MODE_FILTER: usize = 1;
+
+/// Only the listed syscalls are let through
+pub const SECCOMP_MODE_ALLOW_LIST: usize = 0;
+/// The listed syscalls are turned down
+pub const SECCOMP_MODE_DENY_LIST: usize = 1;
+
+/// Actions on a syscall turned down, same values as Linux `SECCOMP_RET_*`
+pub const SECCOMP_RET_KILL_PROCESS: usize = 0x8000_0000;
+pub const SECCOMP_RET_TRAP: usize = 0x0003_0000;
+/// The low 16 bits are the errno the syscall fails with
+pub const SECCOMP_RET_ERRNO: usize = 0x0005_0000;
+const SECCOMP_RET_DATA: usize = 0xffff;
+
+/// `code` of the SIGSYS sent by SECCOMP_RET_TRAP
+pub const SYS_SECCOMP: i32 = 1;
+
+/// At most this many syscalls in one filter
+pub const MAX_FILTER_SYSCALLS: usize = 1024;
+/// At most this many filters on a task
+pub const MAX_FILTERS: usize = 32;
+
+/// Filter passed to `sys_seccomp`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SeccompFilterSpec {
+    // SECCOMP_MODE_ALLOW_LIST or SECCOMP_MODE_DENY_LIST
+    pub mode: usize,
+    // SECCOMP_RET_* of the syscalls turned down
+    pub action: usize,
+    pub syscalls: *const usize,
+    pub syscalls_len: usize,
+}
+
+/// What happens to a syscall a filter turns down
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FilterAction {
+    /// fail with this errno
+    Errno(Errno),
+    /// send SIGSYS, the syscall fails with ENOSYS
+    Trap,
+    /// kill the task with SIGSYS
+    Kill,
+}
+
+impl FilterAction {
+    /// Parse a SECCOMP_RET_* action, None if unknown.
+    pub fn from_ret(ret: usize) -> Option<Self> {
+        match ret & !SECCOMP_RET_DATA {
+            SECCOMP_RET_ERRNO => Some(Self::Errno(Errno((ret & SECCOMP_RET_DATA) as isize))),
+            SECCOMP_RET_TRAP if ret & SECCOMP_RET_DATA == 0 => Some(Self::Trap),
+            SECCOMP_RET_KILL_PROCESS if ret & SECCOMP_RET_DATA == 0 => Some(Self::Kill),
+            _ => None,
+        }
+    }
+    /// Rank among the actions, the strictest one is taken.
+    fn severity(&self) -> u8 {
+        match self {
+            Self::Errno(_) => 0,
+            Self::Trap => 1,
+            Self::Kill => 2,
+        }
+    }
+}
+
+/// A syscall filter of a task, kept across fork and exec. Filters can only
+/// be added, so a sandboxed program cannot lift its own restrictions.
+#[derive(Debug, Clone)]
+pub struct SyscallFilter {
+    pub allow_list: bool,
+    pub action: FilterAction,
+    pub syscalls: Vec<usize>,
+}
+
+impl SyscallFilter {
+    /// The action on `syscall_id`, None if it is let through.
+    pub fn check(&self, syscall_id: usize) -> Option<FilterAction> {
+        if self.syscalls.contains(&syscall_id) != self.allow_list {
+            Some(self.action)
+        } else {
+            None
+        }
+    }
+}
+
+/// Check `syscall_id` against the filters of the current task. If some
+/// turn it down, carry out the strictest of their actions and return it.
+pub fn current_filter_syscall(syscall_id: usize) -> Option<FilterAction> {
+    let task = current_task().unwrap();
+    let mut inner = task.inner_exclusive_access();
+    // the newest filter wins among equal actions
+    let action = inner
+        .syscall_filters
+        .iter()
+        .rev()
+        .filter_map(|filter| filter.check(syscall_id))
+        .reduce(|strictest, action| {
+            if action.severity() > strictest.severity() {
+                action
+            } else {
+                strictest
+            }
+        })?;
+    let sigsys = SignalFlags::SIGSYS;
+    let info = SignalInfo {
+        signo: sigsys.signum() as i32,
+        code: SYS_SECCOMP,
+        // the syscall turned down
+        value: syscall_id,
+        ..Default::default()
+    };
+    match action {
+        FilterAction::Errno(_) => {}
+        FilterAction::Trap => {
+            drop(inner);
+            current_add_signal(sigsys, info);
+        }
+        FilterAction::Kill => {
+            inner.killed = Some(sigsys.signum());
+            inner.killed_info = info;
+        }
+    }
+    Some(action)
+}
@@ -1,9 +1,16 @@
 use bitflags::*;
+use polyhal::trapframe::TrapFrame;
 
-pub const MAX_SIG: usize = 31;
+pub const MAX_SIG: usize = 63;
+/// Signals from SIGRTMIN to SIGRTMAX are real-time signals, every instance
+/// of them is queued instead of collapsing into one pending bit.
+pub const SIGRTMIN: usize = 32;
+pub const SIGRTMAX: usize = MAX_SIG;
+/// At most this many real-time signals can be queued on a task
+pub const MAX_QUEUED_SIGNALS: usize = 64;
 
 bitflags! {
-    pub struct SignalFlags: u32 {
+    pub struct SignalFlags: u64 {
         const SIGDEF = 1; // Default signal handling
         const SIGHUP = 1 << 1;
         const SIGINT = 1 << 2;
@@ -36,26 +43,255 @@ bitflags! {
         const SIGIO = 1 << 29;
         const SIGPWR = 1 << 30;
         const SIGSYS = 1 << 31;
+        // SIGRTMIN..=SIGRTMAX
+        const SIGRT = 0xffff_ffff_0000_0000;
     }
 }
 
+/// What happens to a task when a signal without a handler is delivered
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DefaultAction {
+    Terminate,
+    /// terminate and dump core
+    Core,
+    Ignore,
+    Stop,
+    Continue,
+}
+
 impl SignalFlags {
-    pub fn check_error(&self) -> Option<(i32, &'static str)> {
-        if self.contains(Self::SIGINT) {
-            Some((-2, "Killed, SIGINT=2"))
-        } else if self.contains(Self::SIGILL) {
-            Some((-4, "Illegal Instruction, SIGILL=4"))
-        } else if self.contains(Self::SIGABRT) {
-            Some((-6, "Aborted, SIGABRT=6"))
-        } else if self.contains(Self::SIGFPE) {
-            Some((-8, "Erroneous Arithmetic Operation, SIGFPE=8"))
-        } else if self.contains(Self::SIGKILL) {
-            Some((-9, "Killed, SIGKILL=9"))
-        } else if self.contains(Self::SIGSEGV) {
-            Some((-11, "Segmentation Fault, SIGSEGV=11"))
-        } else {
-            //println!("[K] signalflags check_error  {:?}", self);
+    /// Number of the lowest signal in the set.
+    pub fn signum(&self) -> usize {
+        self.bits().trailing_zeros() as usize
+    }
+    /// Set of the single signal `signum`, `None` if it is out of range.
+    pub fn from_signum(signum: usize) -> Option<Self> {
+        if signum > MAX_SIG {
             None
+        } else {
+            Self::from_bits(1 << signum)
         }
     }
+    pub fn is_realtime(&self) -> bool {
+        Self::SIGRT.contains(*self)
+    }
+    /// The signals of the set which a mask can block, all but SIGKILL and
+    /// SIGSTOP.
+    pub fn blockable(self) -> Self {
+        self - (Self::SIGKILL | Self::SIGSTOP)
+    }
+    /// POSIX default action of a single signal.
+    pub fn default_action(&self) -> DefaultAction {
+        if self.intersects(
+            Self::SIGQUIT
+                | Self::SIGILL
+                | Self::SIGTRAP
+                | Self::SIGABRT
+                | Self::SIGBUS
+                | Self::SIGFPE
+                | Self::SIGSEGV
+                | Self::SIGXCPU
+                | Self::SIGXFSZ
+                | Self::SIGSYS,
+        ) {
+            DefaultAction::Core
+        } else if self.intersects(Self::SIGDEF | Self::SIGCHLD | Self::SIGURG | Self::SIGWINCH) {
+            DefaultAction::Ignore
+        } else if self.intersects(Self::SIGSTOP | Self::SIGTSTP | Self::SIGTTIN | Self::SIGTTOU) {
+            DefaultAction::Stop
+        } else if self.contains(Self::SIGCONT) {
+            DefaultAction::Continue
+        } else {
+            DefaultAction::Terminate
+        }
+    }
+}
+
+/// Message printed when a task is killed by signal `signum`.
+pub fn signal_description(signum: usize) -> &'static str {
+    match signum {
+        1 => "Hangup, SIGHUP=1",
+        2 => "Killed, SIGINT=2",
+        3 => "Quit, SIGQUIT=3",
+        4 => "Illegal Instruction, SIGILL=4",
+        5 => "Trace/Breakpoint Trap, SIGTRAP=5",
+        6 => "Aborted, SIGABRT=6",
+        7 => "Bus Error, SIGBUS=7",
+        8 => "Erroneous Arithmetic Operation, SIGFPE=8",
+        9 => "Killed, SIGKILL=9",
+        10 => "User Defined Signal 1, SIGUSR1=10",
+        11 => "Segmentation Fault, SIGSEGV=11",
+        12 => "User Defined Signal 2, SIGUSR2=12",
+        13 => "Broken Pipe, SIGPIPE=13",
+        14 => "Alarm Clock, SIGALRM=14",
+        15 => "Terminated, SIGTERM=15",
+        16 => "Stack Fault, SIGSTKFLT=16",
+        24 => "CPU Time Limit Exceeded, SIGXCPU=24",
+        25 => "File Size Limit Exceeded, SIGXFSZ=25",
+        26 => "Virtual Timer Expired, SIGVTALRM=26",
+        27 => "Profiling Timer Expired, SIGPROF=27",
+        29 => "I/O Possible, SIGIO=29",
+        30 => "Power Failure, SIGPWR=30",
+        31 => "Bad System Call, SIGSYS=31",
+        _ => "Killed by a signal",
+    }
+}
+
+/// Signal sent by a process via `kill`
+pub const SI_USER: i32 = 0;
+/// Signal sent by a process via `sigqueue`
+pub const SI_QUEUE: i32 = -1;
+/// Signal raised by the kernel, such as a page fault
+pub const SI_KERNEL: i32 = 0x80;
+
+/// Information about a signal, passed to handlers installed with SA_SIGINFO
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignalInfo {
+    pub signo: i32,
+    pub errno: i32,
+    pub code: i32,
+    /// pid of the sending process
+    pub pid: i32,
+    /// faulting address for SIGSEGV and SIGILL
+    pub addr: usize,
+    /// value sent with `sigqueue`
+    pub value: usize,
+}
+
+impl SignalInfo {
+    pub fn user(signo: usize, pid: usize) -> Self {
+        Self {
+            signo: signo as i32,
+            code: SI_USER,
+            pid: pid as i32,
+            ..Default::default()
+        }
+    }
+    pub fn queue(signo: usize, pid: usize, value: usize) -> Self {
+        Self {
+            signo: signo as i32,
+            code: SI_QUEUE,
+            pid: pid as i32,
+            value,
+            ..Default::default()
+        }
+    }
+    pub fn kernel(signo: usize, addr: usize) -> Self {
+        Self {
+            signo: signo as i32,
+            code: SI_KERNEL,
+            addr,
+            ..Default::default()
+        }
+    }
+}
+
+/// Frame pushed on the user stack before a user signal handler is called,
+/// `sys_sigreturn` restores the task from it. The context comes last, so
+/// that the fields before it are at the same place on every arch.
+#[repr(C)]
+pub struct SignalFrame {
+    pub info: SignalInfo,
+    /// signal mask before the handler was called
+    pub mask: SignalFlags,
+    pub handling_sig: isize,
+    /// user address of the frame of the outer handler, 0 if none
+    pub prev: usize,
+    /// the interrupted user context
+    pub trap_cx: TrapFrame,
+}
+
+impl SignalFrame {
+    /// Put the saved registers back into `cx`. The frame lives in user
+    /// memory, so only what user code could set anyway is taken from it,
+    /// and the privileged state of `cx` stays as it is.
+    pub fn restore_user_regs(&self, cx: &mut TrapFrame) {
+        restore_user_regs(cx, &self.trap_cx);
+    }
+}
+
+#[cfg(target_arch = "riscv64")]
+fn restore_user_regs(cx: &mut TrapFrame, saved: &TrapFrame) {
+    // x2 is sp, x0 is hardwired to zero
+    cx.x[1..].copy_from_slice(&saved.x[1..]);
+    cx.sepc = saved.sepc;
+}
+
+/// rflags bits which user code may change: CF, PF, AF, ZF, SF, DF and OF
+#[cfg(target_arch = "x86_64")]
+pub(super) const USER_RFLAGS: usize = 0xcd5;
+
+#[cfg(target_arch = "x86_64")]
+fn restore_user_regs(cx: &mut TrapFrame, saved: &TrapFrame) {
+    cx.rax = saved.rax;
+    cx.rcx = saved.rcx;
+    cx.rdx = saved.rdx;
+    cx.rbx = saved.rbx;
+    cx.rbp = saved.rbp;
+    cx.rsi = saved.rsi;
+    cx.rdi = saved.rdi;
+    cx.r8 = saved.r8;
+    cx.r9 = saved.r9;
+    cx.r10 = saved.r10;
+    cx.r11 = saved.r11;
+    cx.r12 = saved.r12;
+    cx.r13 = saved.r13;
+    cx.r14 = saved.r14;
+    cx.r15 = saved.r15;
+    cx.rip = saved.rip;
+    cx.rsp = saved.rsp;
+    // the interrupted code may be between a compare and a branch
+    cx.rflags = (cx.rflags & !USER_RFLAGS) | (saved.rflags & USER_RFLAGS);
+}
+
+/// spsr bits which user code may change: the condition flags N, Z, C and V
+#[cfg(target_arch = "aarch64")]
+pub(super) const USER_SPSR: usize = 0xf000_0000;
+
+#[cfg(target_arch = "aarch64")]
+fn restore_user_regs(cx: &mut TrapFrame, saved: &TrapFrame) {
+    cx.regs = saved.regs;
+    cx.sp = saved.sp;
+    cx.elr = saved.elr;
+    cx.spsr = (cx.spsr & !USER_SPSR) | (saved.spsr & USER_SPSR);
+}
+
+#[cfg(target_arch = "loongarch64")]
+fn restore_user_regs(cx: &mut TrapFrame, saved: &TrapFrame) {
+    // r3 is sp, r0 is hardwired to zero
+    cx.regs[1..].copy_from_slice(&saved.regs[1..]);
+    cx.era = saved.era;
+}
+
+/// Alternate signal stack, same layout as Linux `stack_t`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SignalStack {
+    pub sp: usize,
+    pub flags: i32,
+    pub size: usize,
+}
+
+/// `SignalStack::flags`: the task is running on the alternate stack
+pub const SS_ONSTACK: i32 = 1;
+/// `SignalStack::flags`: the alternate stack is disabled
+pub const SS_DISABLE: i32 = 2;
+/// Smallest alternate stack accepted by `sigaltstack`
+pub const MINSIGSTKSZ: usize = 2048;
+
+impl Default for SignalStack {
+    fn default() -> Self {
+        Self {
+            sp: 0,
+            flags: SS_DISABLE,
+            size: 0,
+        }
+    }
+}
+
+impl SignalStack {
+    pub fn contains(&self, sp: usize) -> bool {
+        self.flags & SS_DISABLE == 0 && sp > self.sp && sp <= self.sp + self.size
+    }
 }
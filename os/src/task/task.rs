@@ -1,9 +1,22 @@
-use super::{current_task, SignalActions};
-use super::{pid_alloc, PidHandle, SignalFlags};
-use crate::config::KERNEL_STACK_SIZE;
+use super::clone::CloneFlags;
+use super::cred::Credentials;
+use super::personality::*;
+use super::ptrace::SingleStep;
+use super::rlimit::{
+    default_rlimits, RLimit, RLIMIT_AS, RLIMIT_NOFILE, RLIMIT_STACK, RLIM_INFINITY, RLIM_NLIMITS,
+};
+use super::seccomp::SyscallFilter;
+use super::timer::{now_us, ITimer};
+use super::usage::{TaskUsage, CLK_TCK};
+use super::{current_task, SignalAction, SignalActions, MAX_SIG, SIG_IGN};
+use super::{pid_alloc, PidHandle, SignalFlags, SignalInfo, SignalStack, MAX_QUEUED_SIGNALS};
+use crate::config::{KERNEL_STACK_SIZE, PAGE_SIZE, USER_STACK_SIZE, VDSO_BASE};
 use crate::fs::{File, Stdin, Stdout};
-use crate::mm::{translated_refmut, MemorySet};
+use crate::mm::{translated_refmut, ElfError, ElfInfo, MemorySet};
+use crate::random::fill_random;
 use crate::sync::UPSafeCell;
+use crate::syscall::strace_all;
+use alloc::collections::VecDeque;
 use alloc::string::String;
 use alloc::sync::{Arc, Weak};
 use alloc::vec;
@@ -18,6 +31,8 @@ use core::mem::size_of;
 pub struct TaskControlBlock {
     // immutable
     pub pid: PidHandle,
+    // pid of the thread group leader, the pid which user space sees
+    pub tgid: usize,
     // mutable
     inner: UPSafeCell<TaskControlBlockInner>,
 }
@@ -50,25 +65,78 @@ pub struct TaskControlBlockInner {
     pub base_size: usize,
     pub task_cx: KContext,
     pub task_status: TaskStatus,
-    pub memory_set: MemorySet,
+    // shared by the threads and vfork children which run in it
+    pub memory_set: Arc<UPSafeCell<MemorySet>>,
     pub kernel_stack: KernelStack,
     pub parent: Option<Weak<TaskControlBlock>>,
     pub children: Vec<Arc<TaskControlBlock>>,
     pub exit_code: i32,
-    pub fd_table: Vec<Option<Arc<dyn File + Send + Sync>>>,
+    // stop/continue event not yet collected by waitpid, as a wait status
+    pub state_change: Option<i32>,
+    pub fd_table: Arc<UPSafeCell<FdTable>>,
+    // current directory, an absolute path without `.` or `..`
+    pub cwd: Arc<UPSafeCell<String>>,
     pub signals: SignalFlags,
     pub signal_mask: SignalFlags,
     // the signal which is being handling
     pub handling_sig: isize,
     // Signal actions
-    pub signal_actions: SignalActions,
-    // if the task is killed
-    pub killed: bool,
+    pub signal_actions: Arc<UPSafeCell<SignalActions>>,
+    // the signal which killed the task
+    pub killed: Option<usize>,
+    // info of that signal, saved for the core dump
+    pub killed_info: SignalInfo,
     // if the task is frozen by a signal
     pub frozen: bool,
-    pub trap_ctx_backup: Option<TrapFrame>,
+    // siginfo of each pending standard signal
+    pub signal_infos: [SignalInfo; MAX_SIG + 1],
+    // pending real-time signals in the order they were sent
+    pub queued_signals: VecDeque<SignalInfo>,
+    // alternate stack for handlers with SA_ONSTACK
+    pub alt_stack: SignalStack,
+    // user address of the innermost signal frame, 0 if no handler is running
+    pub sig_frame: usize,
+    // (syscall id, first argument) of the last syscall if it returned EINTR
+    pub interrupted_syscall: Option<(usize, usize)>,
+    // ITIMER_REAL, ITIMER_VIRTUAL and ITIMER_PROF
+    pub itimers: [ITimer; 3],
+    // resources used by the task itself and by its reaped children
+    pub usage: TaskUsage,
+    pub children_usage: TaskUsage,
+    // when the time since then was last charged to usage
+    pub time_mark: usize,
+    // resource limits, indexed by RLIMIT_*
+    pub rlimits: [RLimit; RLIM_NLIMITS],
+    // user and group ids, kept across exec
+    pub creds: Credentials,
+    // if a vfork child still runs in the memory of its suspended parent
+    pub borrowed_vm: bool,
+    // signal sent to the parent on exit, 0 for none
+    pub exit_signal: usize,
+    // user address of the tid which is cleared on exit, 0 for none
+    pub clear_child_tid: usize,
+    // the task which traces this one with ptrace
+    pub tracer: Option<Weak<TaskControlBlock>>,
+    // tasks this one traces
+    pub tracees: Vec<Weak<TaskControlBlock>>,
+    // if stopped until the tracer resumes it
+    pub trace_stopped: bool,
+    // info of the signal which caused the trace stop
+    pub trace_info: SignalInfo,
+    // signals the tracer let through, delivered without another stop
+    pub trace_passed: SignalFlags,
+    // the single step the tracer resumed it for
+    pub single_step: Option<SingleStep>,
+    // seccomp filters in the order they were installed
+    pub syscall_filters: Vec<Arc<SyscallFilter>>,
+    // syscall ABI of the running image
+    pub personality: Personality,
+    // if its syscalls are printed like strace, kept across fork and exec
+    pub strace: bool,
 }
 
+pub type FdTable = Vec<Option<Arc<dyn File + Send + Sync>>>;
+
 impl TaskControlBlockInner {
     pub fn get_trap_cx(&self) -> &'static mut TrapFrame {
         let paddr = &self.trap_cx as *const TrapFrame as usize as *mut TrapFrame;
@@ -77,7 +145,7 @@ impl TaskControlBlockInner {
         unsafe { paddr.as_mut().unwrap() }
     }
     pub fn get_user_token(&self) -> PageTable {
-        self.memory_set.token()
+        self.memory_set.exclusive_access().token()
     }
     fn get_status(&self) -> TaskStatus {
         self.task_status
@@ -85,13 +153,68 @@ impl TaskControlBlockInner {
     pub fn is_zombie(&self) -> bool {
         self.get_status() == TaskStatus::Zombie
     }
-    pub fn alloc_fd(&mut self) -> usize {
-        if let Some(fd) = (0..self.fd_table.len()).find(|fd| self.fd_table[*fd].is_none()) {
-            fd
+    /// Return the lowest free fd, or None if RLIMIT_NOFILE is reached.
+    pub fn alloc_fd(&mut self) -> Option<usize> {
+        self.alloc_fd_from(0)
+    }
+    /// Return the lowest free fd not below `min`, or None if there is none
+    /// below RLIMIT_NOFILE.
+    pub fn alloc_fd_from(&mut self, min: usize) -> Option<usize> {
+        let limit = self.rlimits[RLIMIT_NOFILE].cur;
+        let mut fd_table = self.fd_table.exclusive_access();
+        let len = fd_table.len().min(limit);
+        if let Some(fd) = (min..len).find(|fd| fd_table[*fd].is_none()) {
+            Some(fd)
+        } else if fd_table.len().max(min) < limit {
+            fd_table.resize(fd_table.len().max(min) + 1, None);
+            Some(fd_table.len() - 1)
         } else {
-            self.fd_table.push(None);
-            self.fd_table.len() - 1
+            None
+        }
+    }
+    /// Make `signal` pending. Real-time signals are queued with their info,
+    /// a standard signal which is already pending keeps its first info.
+    /// Return false if the queue of real-time signals is full.
+    pub fn add_signal(&mut self, signal: SignalFlags, info: SignalInfo) -> bool {
+        if signal.is_realtime() {
+            if self.queued_signals.len() >= MAX_QUEUED_SIGNALS {
+                return false;
+            }
+            self.queued_signals.push_back(info);
+        } else if !self.signals.contains(signal) {
+            self.signal_infos[signal.signum()] = info;
+        }
+        self.signals |= signal;
+        true
+    }
+    /// Remove one instance of the pending `signal` and return its info.
+    pub fn take_signal(&mut self, signal: SignalFlags) -> SignalInfo {
+        let sig = signal.signum();
+        self.signals.remove(signal);
+        if !signal.is_realtime() {
+            return self.signal_infos[sig];
         }
+        let info = self
+            .queued_signals
+            .iter()
+            .position(|info| info.signo as usize == sig)
+            .and_then(|idx| self.queued_signals.remove(idx))
+            .unwrap_or_default();
+        // more instances of it are still queued
+        if self
+            .queued_signals
+            .iter()
+            .any(|info| info.signo as usize == sig)
+        {
+            self.signals |= signal;
+        }
+        info
+    }
+    /// Drop every pending instance of `signal`.
+    pub fn discard_signal(&mut self, signal: SignalFlags) {
+        let sig = signal.signum();
+        self.signals.remove(signal);
+        self.queued_signals.retain(|info| info.signo as usize != sig);
     }
 }
 
@@ -123,116 +246,263 @@ impl TaskControlBlock {
     }
     pub fn new(elf_data: &[u8]) -> Self {
         trace!("os::task::TaskControlBlock::new");
-        // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, user_sp, entry_point) = MemorySet::from_elf(elf_data);
         // alloc a pid and a kernel stack in kernel space
         let pid_handle = pid_alloc();
         let kstack = KernelStack::new();
         let task_control_block = Self {
+            tgid: pid_handle.0,
             pid: pid_handle,
             inner: unsafe {
                 UPSafeCell::new(TaskControlBlockInner {
                     trap_cx: TrapFrame::new(),
-                    base_size: user_sp,
+                    base_size: 0,
                     task_cx: blank_kcontext(kstack.get_position().1), // Set task_cx's Kernel Stack Top
                     task_status: TaskStatus::Ready,
-                    memory_set,
+                    memory_set: Arc::new(UPSafeCell::new(MemorySet::new_bare())),
                     parent: None,
                     children: Vec::new(),
                     exit_code: 0,
-                    fd_table: vec![
+                    state_change: None,
+                    fd_table: Arc::new(UPSafeCell::new(vec![
                         // 0 -> stdin
                         Some(Arc::new(Stdin)),
                         // 1 -> stdout
                         Some(Arc::new(Stdout)),
                         // 2 -> stderr
                         Some(Arc::new(Stdout)),
-                    ],
+                    ])),
+                    cwd: Arc::new(UPSafeCell::new(String::from("/"))),
                     signals: SignalFlags::empty(),
                     signal_mask: SignalFlags::empty(),
                     handling_sig: -1,
-                    signal_actions: SignalActions::default(),
-                    killed: false,
+                    signal_actions: Arc::new(UPSafeCell::new(SignalActions::default())),
+                    killed: None,
+                    killed_info: SignalInfo::default(),
                     frozen: false,
-                    trap_ctx_backup: None,
+                    signal_infos: [SignalInfo::default(); MAX_SIG + 1],
+                    queued_signals: VecDeque::new(),
+                    alt_stack: SignalStack::default(),
+                    sig_frame: 0,
+                    interrupted_syscall: None,
+                    itimers: [ITimer::default(); 3],
+                    usage: TaskUsage::default(),
+                    children_usage: TaskUsage::default(),
+                    time_mark: now_us(),
+                    rlimits: default_rlimits(),
+                    creds: Credentials::default(),
+                    borrowed_vm: false,
+                    exit_signal: 0,
+                    clear_child_tid: 0,
+                    tracer: None,
+                    tracees: Vec::new(),
+                    trace_stopped: false,
+                    trace_info: SignalInfo::default(),
+                    trace_passed: SignalFlags::empty(),
+                    single_step: None,
+                    syscall_filters: Vec::new(),
+                    personality: Personality::Native,
+                    strace: strace_all(),
                     kernel_stack: kstack,
                 })
             },
         };
-        // prepare TrapContext in user space
-        let trap_cx = task_control_block.inner_exclusive_access().get_trap_cx();
-        trap_cx[TrapFrameArgs::SEPC] = entry_point;
-        trap_cx[TrapFrameArgs::SP] = user_sp;
+        // the image and its initial stack are set up like for any other program
+        task_control_block
+            .exec(elf_data, vec![String::from("initproc")], Vec::new())
+            .expect("can't load initproc");
         task_control_block
     }
-    pub fn exec(&self, elf_data: &[u8], args: Vec<String>) {
+    /// Replace the user image with `elf_data`, started with `args` and the
+    /// environment `envs`. On error the old image is kept.
+    pub fn exec(
+        &self,
+        elf_data: &[u8],
+        args: Vec<String>,
+        envs: Vec<String>,
+    ) -> Result<(), ExecError> {
         trace!("os::task::TaskControlBlock::exec");
+        let rlimits = self.inner_exclusive_access().rlimits;
+        // the stack is mapped eagerly, so an unlimited one gets the default size
+        let stack_size = match rlimits[RLIMIT_STACK].cur {
+            RLIM_INFINITY => USER_STACK_SIZE,
+            size => (size + PAGE_SIZE - 1) / PAGE_SIZE * PAGE_SIZE,
+        };
+        // like Linux, strings and pointers may take up a quarter of the stack
+        let strings_size: usize = args.iter().chain(envs.iter()).map(|s| s.len() + 1).sum();
+        let pointers_size = (args.len() + envs.len() + 3) * size_of::<usize>()
+            + MAX_AUXV * 2 * size_of::<usize>()
+            + AT_RANDOM_SIZE;
+        if strings_size + pointers_size > stack_size / 4 {
+            return Err(ExecError::TooBig);
+        }
         // memory_set with elf program headers/trampoline/trap context/user stack
-        let (memory_set, mut user_sp, entry_point) = MemorySet::from_elf(elf_data);
+        let (memory_set, mut user_sp, elf_info) =
+            MemorySet::from_elf(elf_data, stack_size, rlimits[RLIMIT_AS].cur)?;
+        let personality = if elf_info.native {
+            Personality::Native
+        } else {
+            Personality::Linux
+        };
+        // Linux on x86_64 numbers its syscalls differently from the table we use
+        if cfg!(target_arch = "x86_64") && personality == Personality::Linux {
+            return Err(ExecError::NotExecutable);
+        }
         memory_set.activate();
-        // push arguments on user stack
-        user_sp -= (args.len() + 1) * core::mem::size_of::<usize>();
-        let argv_base = user_sp;
-        let mut argv: Vec<_> = (0..=args.len())
-            .map(|arg| {
-                translated_refmut(
-                    memory_set.token(),
-                    (argv_base + arg * core::mem::size_of::<usize>()) as *mut usize,
-                )
-            })
-            .collect();
-        *argv[args.len()] = 0;
-        for i in 0..args.len() {
-            user_sp -= args[i].len() + 1;
-            *argv[i] = user_sp;
-            let mut p = user_sp;
-            for c in args[i].as_bytes() {
-                *translated_refmut(memory_set.token(), p as *mut u8) = *c;
-                p += 1;
+        let token = memory_set.token();
+        let mut push_bytes = |bytes: &[u8]| {
+            user_sp -= bytes.len();
+            for (i, c) in bytes.iter().enumerate() {
+                *translated_refmut(token, (user_sp + i) as *mut u8) = *c;
             }
-            *translated_refmut(memory_set.token(), p as *mut u8) = 0;
+            user_sp
+        };
+        // the strings go on top of user stack
+        let mut push_string = |string: &String| {
+            push_bytes(&[0]);
+            push_bytes(string.as_bytes())
+        };
+        let arg_ptrs: Vec<_> = args.iter().map(&mut push_string).collect();
+        let env_ptrs: Vec<_> = envs.iter().map(&mut push_string).collect();
+        // then bytes for AT_RANDOM
+        let mut random = [0u8; AT_RANDOM_SIZE];
+        fill_random(&mut random);
+        let random_addr = push_bytes(&random);
+        // then argc, argv, envp and the auxiliary vector, which start at
+        // user_sp, argv and envp each end with a null pointer
+        let mut words = vec![args.len()];
+        words.extend(arg_ptrs);
+        words.push(0);
+        words.extend(env_ptrs);
+        words.push(0);
+        let creds = self.inner_exclusive_access().creds;
+        for (key, value) in auxv(&elf_info, &creds, random_addr) {
+            words.push(key);
+            words.push(value);
+        }
+        user_sp = (user_sp - words.len() * size_of::<usize>()) & !0xf;
+        for (i, word) in words.iter().enumerate() {
+            *translated_refmut(token, (user_sp + i * size_of::<usize>()) as *mut usize) = *word;
         }
-        // make the user_sp aligned to 8B for k210 platform
-        user_sp -= user_sp % core::mem::size_of::<usize>();
+        let argv_base = user_sp + size_of::<usize>();
+        let envp_base = argv_base + (args.len() + 1) * size_of::<usize>();
         memory_set.activate();
 
         // **** access current TCB exclusively
         let mut inner = self.inner_exclusive_access();
-        // substitute memory_set
-        inner.memory_set = memory_set;
+        // substitute memory_set, a vfork child gives the memory of its parent back
+        // and a thread leaves the memory of its group
+        inner.memory_set = Arc::new(unsafe { UPSafeCell::new(memory_set) });
+        inner.borrowed_vm = false;
+        inner.personality = personality;
+        // the new image gets its own fd table and signal actions
+        let fd_table = inner.fd_table.exclusive_access().clone();
+        inner.fd_table = Arc::new(unsafe { UPSafeCell::new(fd_table) });
+        let signal_actions = inner.signal_actions.exclusive_access().clone();
+        inner.signal_actions = Arc::new(unsafe { UPSafeCell::new(signal_actions) });
+        // signal frames lived in the old user stack
+        inner.sig_frame = 0;
+        inner.handling_sig = -1;
+        inner.alt_stack = SignalStack::default();
+        // handlers are gone with the old image, pending and blocked signals
+        // as well as ignored ones are kept
+        for action in inner.signal_actions.exclusive_access().table.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SignalAction::default();
+            }
+        }
         // update trap_cx ppn
         // FIXME: This is a temporary solution
         inner.trap_cx = TrapFrame::new();
         // initialize trap_cx
         let mut trap_cx = TrapFrame::new();
-        trap_cx[TrapFrameArgs::SEPC] = entry_point;
+        trap_cx[TrapFrameArgs::SEPC] = elf_info.entry;
         trap_cx[TrapFrameArgs::SP] = user_sp;
+        // native programs find their arguments in registers, Linux ones on
+        // the stack
         trap_cx[TrapFrameArgs::ARG0] = args.len();
         trap_cx[TrapFrameArgs::ARG1] = argv_base;
+        trap_cx[TrapFrameArgs::ARG2] = envp_base;
         *inner.get_trap_cx() = trap_cx;
         // **** release current PCB
+        Ok(())
     }
-    pub fn fork(self: &Arc<TaskControlBlock>) -> Arc<TaskControlBlock> {
+    /// Create a child which shares the resources selected by `flags` with
+    /// this task and has copies of the others. Return None if there are not
+    /// enough free frames to copy the address space.
+    pub fn fork(self: &Arc<TaskControlBlock>, flags: CloneFlags) -> Option<Arc<TaskControlBlock>> {
         trace!("os::task::TaskControlBlock::fork");
+        let memory_set = if flags.contains(CloneFlags::CLONE_VM) {
+            self.inner_exclusive_access().memory_set.clone()
+        } else {
+            // copy user space(include trap context)
+            let inner = self.inner_exclusive_access();
+            let memory_set = MemorySet::from_existed_user(&inner.memory_set.exclusive_access())?;
+            Arc::new(unsafe { UPSafeCell::new(memory_set) })
+        };
+        Some(self.fork_with(memory_set, flags))
+    }
+    /// Create a child running `elf_data` without copying this task first.
+    /// Return the child, which is not scheduled yet.
+    pub fn spawn(
+        self: &Arc<TaskControlBlock>,
+        elf_data: &[u8],
+        args: Vec<String>,
+        envs: Vec<String>,
+    ) -> Result<Arc<TaskControlBlock>, ExecError> {
+        trace!("os::task::TaskControlBlock::spawn");
+        let memory_set = Arc::new(unsafe { UPSafeCell::new(MemorySet::new_bare()) });
+        let child = self.fork_with(memory_set, CloneFlags::empty());
+        let result = child.exec(elf_data, args, envs);
+        // exec switched to the page table of the child
+        let mut parent_inner = self.inner_exclusive_access();
+        parent_inner.memory_set.exclusive_access().activate();
+        match result {
+            Ok(()) => Ok(child),
+            Err(err) => {
+                parent_inner.children.retain(|c| !Arc::ptr_eq(c, &child));
+                Err(err)
+            }
+        }
+    }
+    /// A child which runs in `memory_set` and shares the other resources
+    /// selected by `flags` with this task.
+    fn fork_with(
+        self: &Arc<TaskControlBlock>,
+        memory_set: Arc<UPSafeCell<MemorySet>>,
+        flags: CloneFlags,
+    ) -> Arc<TaskControlBlock> {
         // ---- hold parent PCB lock
         let mut parent_inner = self.inner_exclusive_access();
-        // copy user space(include trap context)
-        let memory_set = MemorySet::from_existed_user(&parent_inner.memory_set);
 
         // alloc a pid and a kernel stack in kernel space
         let pid_handle = pid_alloc();
         let kstack = KernelStack::new();
-        // copy fd table
-        let mut new_fd_table: Vec<Option<Arc<dyn File + Send + Sync>>> = Vec::new();
-        for fd in parent_inner.fd_table.iter() {
-            if let Some(file) = fd {
-                new_fd_table.push(Some(file.clone()));
-            } else {
-                new_fd_table.push(None);
-            }
-        }
+        let tgid = if flags.contains(CloneFlags::CLONE_THREAD) {
+            self.tgid
+        } else {
+            pid_handle.0
+        };
+        let fd_table = if flags.contains(CloneFlags::CLONE_FILES) {
+            parent_inner.fd_table.clone()
+        } else {
+            let fd_table = parent_inner.fd_table.exclusive_access().clone();
+            Arc::new(unsafe { UPSafeCell::new(fd_table) })
+        };
+        let cwd = if flags.contains(CloneFlags::CLONE_FS) {
+            parent_inner.cwd.clone()
+        } else {
+            let cwd = parent_inner.cwd.exclusive_access().clone();
+            Arc::new(unsafe { UPSafeCell::new(cwd) })
+        };
+        let signal_actions = if flags.contains(CloneFlags::CLONE_SIGHAND) {
+            parent_inner.signal_actions.clone()
+        } else {
+            let signal_actions = parent_inner.signal_actions.exclusive_access().clone();
+            Arc::new(unsafe { UPSafeCell::new(signal_actions) })
+        };
         let task_control_block = Arc::new(TaskControlBlock {
             pid: pid_handle,
+            tgid,
             inner: unsafe {
                 UPSafeCell::new(TaskControlBlockInner {
                     trap_cx: parent_inner.trap_cx.clone(),
@@ -243,15 +513,42 @@ impl TaskControlBlock {
                     parent: Some(Arc::downgrade(self)),
                     children: Vec::new(),
                     exit_code: 0,
-                    fd_table: new_fd_table,
+                    state_change: None,
+                    fd_table,
+                    cwd,
                     signals: SignalFlags::empty(),
                     // inherit the signal_mask and signal_action
                     signal_mask: parent_inner.signal_mask,
-                    handling_sig: -1,
-                    signal_actions: parent_inner.signal_actions.clone(),
-                    killed: false,
+                    handling_sig: parent_inner.handling_sig,
+                    signal_actions,
+                    killed: None,
+                    killed_info: SignalInfo::default(),
                     frozen: false,
-                    trap_ctx_backup: None,
+                    signal_infos: [SignalInfo::default(); MAX_SIG + 1],
+                    queued_signals: VecDeque::new(),
+                    alt_stack: parent_inner.alt_stack,
+                    // a fork in a signal handler returns through the same frames
+                    sig_frame: parent_inner.sig_frame,
+                    interrupted_syscall: None,
+                    itimers: [ITimer::default(); 3],
+                    usage: TaskUsage::default(),
+                    children_usage: TaskUsage::default(),
+                    time_mark: now_us(),
+                    rlimits: parent_inner.rlimits,
+                    creds: parent_inner.creds,
+                    borrowed_vm: flags.contains(CloneFlags::CLONE_VFORK),
+                    exit_signal: SignalFlags::SIGCHLD.signum(),
+                    clear_child_tid: 0,
+                    // children are not traced along with their parent
+                    tracer: None,
+                    tracees: Vec::new(),
+                    trace_stopped: false,
+                    trace_info: SignalInfo::default(),
+                    trace_passed: SignalFlags::empty(),
+                    single_step: None,
+                    syscall_filters: parent_inner.syscall_filters.clone(),
+                    personality: parent_inner.personality,
+                    strace: parent_inner.strace,
                     kernel_stack: kstack,
                 })
             },
@@ -267,6 +564,64 @@ impl TaskControlBlock {
     pub fn getpid(&self) -> usize {
         self.pid.0
     }
+    /// Whether this task was created by `clone` with CLONE_THREAD, so it is
+    /// reaped on its own instead of being waited for.
+    pub fn is_thread(&self) -> bool {
+        self.tgid != self.pid.0
+    }
+}
+
+/// Entries the auxiliary vector may have, including AT_NULL
+const MAX_AUXV: usize = 14;
+/// Bytes which AT_RANDOM points to
+const AT_RANDOM_SIZE: usize = 16;
+
+/// The auxiliary vector for an image, ended by AT_NULL.
+fn auxv(info: &ElfInfo, creds: &Credentials, random: usize) -> Vec<(usize, usize)> {
+    let mut auxv = Vec::new();
+    // the program headers are only there if a segment loaded them
+    if info.phdr != 0 {
+        auxv.push((AT_PHDR, info.phdr));
+        auxv.push((AT_PHENT, info.phent));
+        auxv.push((AT_PHNUM, info.phnum));
+    }
+    // Linux programs would take the vDSO for an ELF image
+    if info.native {
+        auxv.push((AT_SYSINFO_EHDR, VDSO_BASE));
+    }
+    auxv.extend([
+        (AT_PAGESZ, PAGE_SIZE),
+        (AT_ENTRY, info.entry),
+        (AT_UID, creds.uid),
+        (AT_EUID, creds.euid),
+        (AT_GID, creds.gid),
+        (AT_EGID, creds.egid),
+        (AT_SECURE, 0),
+        (AT_CLKTCK, CLK_TCK),
+        (AT_RANDOM, random),
+        (AT_NULL, 0),
+    ]);
+    auxv
+}
+
+/// Why `exec` failed and kept the old image
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExecError {
+    /// the file is not in a format that can be run
+    NotExecutable,
+    /// arguments and environment do not fit in a quarter of the stack
+    TooBig,
+    /// the new image would exceed RLIMIT_AS
+    NoMemory,
+}
+
+impl From<ElfError> for ExecError {
+    fn from(err: ElfError) -> Self {
+        match err {
+            ElfError::Malformed => ExecError::NotExecutable,
+            ElfError::TooLarge | ElfError::NoMemory => ExecError::NoMemory,
+        }
+    }
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -0,0 +1,171 @@
+use super::{
+    pid2task, SignalFlags, SignalInfo, TaskControlBlockInner, RLIMIT_CPU, RLIM_INFINITY,
+};
+use crate::sync::UPSafeCell;
+use alloc::collections::BTreeSet;
+use lazy_static::*;
+use polyhal::time::Time;
+
+pub const ITIMER_REAL: usize = 0;
+pub const ITIMER_VIRTUAL: usize = 1;
+pub const ITIMER_PROF: usize = 2;
+
+const USEC_PER_SEC: usize = 1_000_000;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+impl TimeVal {
+    pub fn from_us(us: usize) -> Self {
+        Self {
+            sec: us / USEC_PER_SEC,
+            usec: us % USEC_PER_SEC,
+        }
+    }
+    pub fn to_us(&self) -> usize {
+        self.sec * USEC_PER_SEC + self.usec
+    }
+}
+
+/// Setting of an interval timer, same layout as Linux `itimerval`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ITimerVal {
+    pub interval: TimeVal,
+    pub value: TimeVal,
+}
+
+/// An interval timer of a task in microseconds, disarmed if `value` is 0.
+/// `value` is the absolute expiry time for ITIMER_REAL and the time left
+/// for the CPU timers.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ITimer {
+    pub interval: usize,
+    pub value: usize,
+}
+
+pub fn now_us() -> usize {
+    Time::now().to_usec()
+}
+
+lazy_static! {
+    /// (expiry time, pid) of every armed ITIMER_REAL
+    static ref REAL_TIMERS: UPSafeCell<BTreeSet<(usize, usize)>> =
+        unsafe { UPSafeCell::new(BTreeSet::new()) };
+}
+
+/// Setting of timer `which` with `value` relative to now.
+pub fn get_itimer(inner: &TaskControlBlockInner, which: usize) -> ITimer {
+    let timer = inner.itimers[which];
+    if which == ITIMER_REAL && timer.value != 0 {
+        ITimer {
+            interval: timer.interval,
+            value: timer.value.saturating_sub(now_us()).max(1),
+        }
+    } else {
+        timer
+    }
+}
+
+/// Arm timer `which` of task `pid` with a relative `new.value`, or disarm it
+/// if that is 0. Return the old setting.
+pub fn set_itimer(
+    pid: usize,
+    inner: &mut TaskControlBlockInner,
+    which: usize,
+    new: ITimer,
+) -> ITimer {
+    let old = get_itimer(inner, which);
+    if which != ITIMER_REAL {
+        inner.itimers[which] = new;
+        return old;
+    }
+    let mut timers = REAL_TIMERS.exclusive_access();
+    let deadline = inner.itimers[ITIMER_REAL].value;
+    if deadline != 0 {
+        timers.remove(&(deadline, pid));
+    }
+    inner.itimers[ITIMER_REAL] = if new.value != 0 {
+        let deadline = now_us() + new.value;
+        timers.insert((deadline, pid));
+        ITimer {
+            interval: new.interval,
+            value: deadline,
+        }
+    } else {
+        ITimer::default()
+    };
+    old
+}
+
+/// Send SIGALRM to every task whose real timer expired and rearm it.
+pub fn check_real_timers() {
+    let now = now_us();
+    loop {
+        let (deadline, pid) = match REAL_TIMERS.exclusive_access().first() {
+            Some(&(deadline, pid)) if deadline <= now => (deadline, pid),
+            _ => break,
+        };
+        REAL_TIMERS.exclusive_access().remove(&(deadline, pid));
+        if let Some(task) = pid2task(pid) {
+            let mut inner = task.inner_exclusive_access();
+            let sigalrm = SignalFlags::SIGALRM;
+            inner.add_signal(sigalrm, SignalInfo::kernel(sigalrm.signum(), 0));
+            let interval = inner.itimers[ITIMER_REAL].interval;
+            inner.itimers[ITIMER_REAL] = if interval != 0 {
+                REAL_TIMERS.exclusive_access().insert((now + interval, pid));
+                ITimer {
+                    interval,
+                    value: now + interval,
+                }
+            } else {
+                ITimer::default()
+            };
+        }
+    }
+}
+
+/// Charge `user` and `system` microseconds to a task, run its CPU timers
+/// and enforce RLIMIT_CPU.
+pub fn account_time(inner: &mut TaskControlBlockInner, user: usize, system: usize) {
+    let before = inner.usage.user_time + inner.usage.system_time;
+    inner.usage.user_time += user;
+    inner.usage.system_time += system;
+    let (before, after) = (before / USEC_PER_SEC, (before + user + system) / USEC_PER_SEC);
+    let limit = inner.rlimits[RLIMIT_CPU];
+    if limit.max != RLIM_INFINITY && after >= limit.max {
+        // killed outright, as a queued signal could still be blocked or caught
+        let sigkill = SignalFlags::SIGKILL.signum();
+        inner.killed = Some(sigkill);
+        inner.killed_info = SignalInfo::kernel(sigkill, 0);
+    } else if limit.cur != RLIM_INFINITY && after >= limit.cur && after > before {
+        // once at the soft limit and every second after it
+        let sigxcpu = SignalFlags::SIGXCPU;
+        inner.add_signal(sigxcpu, SignalInfo::kernel(sigxcpu.signum(), 0));
+    }
+    if tick(&mut inner.itimers[ITIMER_VIRTUAL], user) {
+        let sigvtalrm = SignalFlags::SIGVTALRM;
+        inner.add_signal(sigvtalrm, SignalInfo::kernel(sigvtalrm.signum(), 0));
+    }
+    if tick(&mut inner.itimers[ITIMER_PROF], user + system) {
+        let sigprof = SignalFlags::SIGPROF;
+        inner.add_signal(sigprof, SignalInfo::kernel(sigprof.signum(), 0));
+    }
+}
+
+/// Count `elapsed` down on a CPU timer, return true if it expired.
+fn tick(timer: &mut ITimer, elapsed: usize) -> bool {
+    if timer.value == 0 {
+        false
+    } else if timer.value > elapsed {
+        timer.value -= elapsed;
+        false
+    } else {
+        timer.value = timer.interval;
+        true
+    }
+}
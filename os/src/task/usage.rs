@@ -0,0 +1,116 @@
+use super::timer::TimeVal;
+
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+pub const CLOCK_PROCESS_CPUTIME_ID: usize = 2;
+pub const CLOCK_THREAD_CPUTIME_ID: usize = 3;
+
+pub const RUSAGE_SELF: isize = 0;
+pub const RUSAGE_CHILDREN: isize = -1;
+
+/// Clock ticks per second reported by `times`, the value of Linux USER_HZ
+pub const CLK_TCK: usize = 100;
+
+const USEC_PER_TICK: usize = 1_000_000 / CLK_TCK;
+
+/// Resources consumed by a task, times are in microseconds.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TaskUsage {
+    pub user_time: usize,
+    pub system_time: usize,
+    // voluntary context switches, when the task blocked or yielded
+    pub nvcsw: usize,
+    // involuntary context switches, when the task was preempted
+    pub nivcsw: usize,
+    // page faults, which are all fatal, reported as minor ones
+    pub page_faults: usize,
+}
+
+impl TaskUsage {
+    /// Add the usage of a reaped child.
+    pub fn add(&mut self, other: &TaskUsage) {
+        self.user_time += other.user_time;
+        self.system_time += other.system_time;
+        self.nvcsw += other.nvcsw;
+        self.nivcsw += other.nivcsw;
+        self.page_faults += other.page_faults;
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    pub fn from_us(us: usize) -> Self {
+        Self {
+            sec: us / 1_000_000,
+            nsec: us % 1_000_000 * 1000,
+        }
+    }
+}
+
+/// Result of `times`, same layout as Linux `struct tms`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tms {
+    pub utime: usize,
+    pub stime: usize,
+    pub cutime: usize,
+    pub cstime: usize,
+}
+
+impl Tms {
+    pub fn new(usage: &TaskUsage, children: &TaskUsage) -> Self {
+        Self {
+            utime: usage.user_time / USEC_PER_TICK,
+            stime: usage.system_time / USEC_PER_TICK,
+            cutime: children.user_time / USEC_PER_TICK,
+            cstime: children.system_time / USEC_PER_TICK,
+        }
+    }
+}
+
+/// Result of `getrusage`, same layout as Linux `struct rusage`.
+/// Fields this kernel does not track are left 0.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RUsage {
+    pub utime: TimeVal,
+    pub stime: TimeVal,
+    pub maxrss: usize,
+    pub ixrss: usize,
+    pub idrss: usize,
+    pub isrss: usize,
+    pub minflt: usize,
+    pub majflt: usize,
+    pub nswap: usize,
+    pub inblock: usize,
+    pub oublock: usize,
+    pub msgsnd: usize,
+    pub msgrcv: usize,
+    pub nsignals: usize,
+    pub nvcsw: usize,
+    pub nivcsw: usize,
+}
+
+impl From<&TaskUsage> for RUsage {
+    fn from(usage: &TaskUsage) -> Self {
+        Self {
+            utime: TimeVal::from_us(usage.user_time),
+            stime: TimeVal::from_us(usage.system_time),
+            minflt: usage.page_faults,
+            nvcsw: usage.nvcsw,
+            nivcsw: usage.nivcsw,
+            ..Default::default()
+        }
+    }
+}
+
+/// Convert microseconds since boot to clock ticks.
+pub fn us_to_ticks(us: usize) -> usize {
+    us / USEC_PER_TICK
+}
@@ -0,0 +1,35 @@
+use bitflags::*;
+
+bitflags! {
+    /// Options of `waitpid`, same values as Linux
+    pub struct WaitOptions: u32 {
+        const WNOHANG = 1;
+        const WUNTRACED = 1 << 1;
+        const WCONTINUED = 1 << 3;
+    }
+}
+
+/// Wait status of a task that exited with `exit_code`.
+pub fn exited_status(exit_code: i32) -> i32 {
+    (exit_code & 0xff) << 8
+}
+
+/// Wait status of a task that was terminated by signal `signum`.
+pub fn signaled_status(signum: usize) -> i32 {
+    (signum & 0x7f) as i32
+}
+
+/// Set in the wait status of a task killed by a signal if it dumped core.
+pub const WCOREFLAG: i32 = 0x80;
+
+/// Wait status of a task that was stopped by signal `signum`.
+pub fn stopped_status(signum: usize) -> i32 {
+    ((signum as i32 & 0xff) << 8) | 0x7f
+}
+
+/// Wait status of a task that was resumed by SIGCONT.
+pub const CONTINUED_STATUS: i32 = 0xffff;
+
+pub fn is_stopped_status(status: i32) -> bool {
+    status & 0xff == 0x7f
+}
@@ -0,0 +1,111 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec;
+use core::ptr::null_mut;
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use user_lib::*;
+
+const STACK_SIZE: usize = 4096 * 4;
+const TLS: usize = 0x1234_5000;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+static SEEN_PID: AtomicUsize = AtomicUsize::new(0);
+static SEEN_TLS: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(target_arch = "riscv64")]
+fn thread_pointer() -> usize {
+    let tp: usize;
+    unsafe { core::arch::asm!("mv {}, tp", out(reg) tp) };
+    tp
+}
+
+// only checked where the thread pointer is a plain register
+#[cfg(not(target_arch = "riscv64"))]
+fn thread_pointer() -> usize {
+    TLS
+}
+
+extern "C" fn thread_main(fd: usize) -> i32 {
+    SEEN_PID.store(getpid() as usize, Ordering::SeqCst);
+    SEEN_TLS.store(thread_pointer(), Ordering::SeqCst);
+    COUNTER.fetch_add(1, Ordering::SeqCst);
+    // the fd table is shared, so this closes the fd of the parent too
+    close(fd);
+    0
+}
+
+extern "C" fn child_main(_: usize) -> i32 {
+    COUNTER.fetch_add(1, Ordering::SeqCst);
+    7
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let pid = getpid() as usize;
+    let stack = vec![0u8; STACK_SIZE];
+    let stack_top = (stack.as_ptr() as usize + STACK_SIZE) & !0xf;
+    let mut fds = [0usize; 2];
+    assert_eq!(pipe(&mut fds), 0);
+
+    // a thread shares memory, fds and the pid with us
+    let ptid = AtomicU32::new(0);
+    let ctid = AtomicU32::new(u32::MAX);
+    let flags = CloneFlags::CLONE_VM
+        | CloneFlags::CLONE_FILES
+        | CloneFlags::CLONE_SIGHAND
+        | CloneFlags::CLONE_THREAD
+        | CloneFlags::CLONE_SETTLS
+        | CloneFlags::CLONE_PARENT_SETTID
+        | CloneFlags::CLONE_CHILD_CLEARTID;
+    let tid = clone(
+        thread_main,
+        stack_top,
+        flags.bits(),
+        fds[0],
+        ptid.as_ptr(),
+        TLS,
+        ctid.as_ptr(),
+    );
+    assert!(tid > 0);
+    assert_ne!(tid as usize, pid);
+    assert_eq!(ptid.load(Ordering::SeqCst), tid as u32);
+    // the kernel clears ctid when the thread exits
+    while ctid.load(Ordering::SeqCst) != 0 {
+        yield_();
+    }
+    assert_eq!(COUNTER.load(Ordering::SeqCst), 1);
+    assert_eq!(SEEN_PID.load(Ordering::SeqCst), pid);
+    assert_eq!(SEEN_TLS.load(Ordering::SeqCst), TLS);
+    assert_eq!(close(fds[0]), -EBADF);
+    assert_eq!(close(fds[1]), 0);
+    // threads are not waited for
+    let mut status = 0;
+    assert!(wait(&mut status) < 0);
+
+    // without CLONE_VM the child works on a copy of our memory
+    let child = clone(
+        child_main,
+        stack_top,
+        SIGCHLD as usize,
+        0,
+        null_mut(),
+        0,
+        null_mut(),
+    );
+    assert!(child > 0);
+    assert_eq!(waitpid(child as usize, &mut status), child);
+    assert_eq!(wexitstatus(status), 7);
+    assert_eq!(COUNTER.load(Ordering::SeqCst), 1);
+
+    // a thread group needs shared signal actions, which need shared memory
+    let bad = CloneFlags::CLONE_THREAD | CloneFlags::CLONE_VM;
+    let ret = clone(child_main, stack_top, bad.bits(), 0, null_mut(), 0, null_mut());
+    assert_eq!(ret, -EINVAL);
+    println!("clone passed!");
+    0
+}
@@ -0,0 +1,117 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::format;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::*;
+
+const MARKER: usize = 0x5eed_c0de;
+const PT_LOAD: u32 = 1;
+const PT_NOTE: u32 = 4;
+const NT_PRSTATUS: u32 = 1;
+
+static WORD: AtomicUsize = AtomicUsize::new(0);
+
+fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes(data[offset..offset + 2].try_into().unwrap())
+}
+fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+fn u64_at(data: &[u8], offset: usize) -> usize {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap()) as usize
+}
+
+fn read_core(pid: usize) -> Option<Vec<u8>> {
+    let fd = open(format!("core.{}\0", pid).as_str(), OpenFlags::RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let mut data = Vec::new();
+    let mut buf = [0u8; 512];
+    loop {
+        let len = read(fd as usize, &mut buf);
+        if len <= 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..len as usize]);
+    }
+    close(fd as usize);
+    Some(data)
+}
+
+/// Fork a child which dies of SIGSEGV, return its pid and wait status.
+fn crash() -> (usize, i32) {
+    let pid = fork();
+    if pid == 0 {
+        WORD.store(MARKER, Ordering::SeqCst);
+        unsafe {
+            core::ptr::null_mut::<u8>().write_volatile(0);
+        }
+        exit(0);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), SIGSEGV);
+    (pid as usize, status)
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // no core dump at all while RLIMIT_CORE is at its default of 0
+    let mut rlim = RLimit::default();
+    assert_eq!(getrlimit(RLIMIT_CORE, &mut rlim), 0);
+    assert_eq!(rlim.cur, 0);
+    let (pid, status) = crash();
+    assert!(!wcoredump(status));
+    assert!(read_core(pid).map_or(true, |core| core.is_empty()));
+
+    rlim.cur = rlim.max;
+    assert_eq!(setrlimit(RLIMIT_CORE, &rlim), 0);
+    let (pid, status) = crash();
+    assert!(wcoredump(status));
+    let core = read_core(pid).expect("no core file");
+    assert_eq!(&core[..4], b"\x7fELF");
+    // ET_CORE
+    assert_eq!(u16_at(&core, 16), 4);
+    let phoff = u64_at(&core, 32);
+    let phnum = u16_at(&core, 56) as usize;
+    let mut prstatus_seen = false;
+    let mut marker_seen = false;
+    for i in 0..phnum {
+        let ph = phoff + i * 56;
+        let offset = u64_at(&core, ph + 8);
+        let vaddr = u64_at(&core, ph + 16);
+        let filesz = u64_at(&core, ph + 32);
+        match u32_at(&core, ph) {
+            PT_NOTE => {
+                // the first note is the prstatus, its signal comes first
+                assert_eq!(u32_at(&core, offset + 8), NT_PRSTATUS);
+                assert_eq!(u32_at(&core, offset + 20), SIGSEGV as u32);
+                prstatus_seen = true;
+            }
+            PT_LOAD => {
+                let addr = WORD.as_ptr() as usize;
+                if (vaddr..vaddr + filesz).contains(&addr) {
+                    assert_eq!(u64_at(&core, offset + addr - vaddr), MARKER);
+                    marker_seen = true;
+                }
+            }
+            _ => {}
+        }
+    }
+    assert!(prstatus_seen && marker_seen);
+    // our copy never had the marker
+    assert_eq!(WORD.load(Ordering::SeqCst), 0);
+    // empty the core file, a later child may get the same pid
+    let name = format!("core.{}\0", pid);
+    close(open(name.as_str(), OpenFlags::CREATE | OpenFlags::WRONLY) as usize);
+    println!("coredump passed!");
+    0
+}
@@ -0,0 +1,66 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+fn cpu_ms() -> usize {
+    let mut tp = TimeSpec::default();
+    assert_eq!(clock_gettime(CLOCK_PROCESS_CPUTIME_ID, &mut tp), 0);
+    tp.to_ms()
+}
+
+fn spin(ms: isize) {
+    let start = get_time();
+    while get_time() - start < ms {}
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let before = cpu_ms();
+    spin(100);
+    // other tasks may run in between, so only a lower bound is reliable
+    assert!(cpu_ms() > before);
+
+    let mut usage = RUsage::default();
+    let pid = fork();
+    if pid == 0 {
+        spin(100);
+        for _ in 0..5 {
+            yield_();
+        }
+        exit(0);
+    }
+    assert_eq!(getrusage(RUSAGE_CHILDREN, &mut usage), 0);
+    assert_eq!(usage.utime.to_ms() + usage.stime.to_ms(), 0);
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+
+    // the child's usage is charged to the parent once it is reaped
+    assert_eq!(getrusage(RUSAGE_CHILDREN, &mut usage), 0);
+    assert!(usage.utime.to_ms() + usage.stime.to_ms() > 0);
+    assert!(usage.nvcsw >= 5);
+    let mut tms = Tms::default();
+    assert!(times(&mut tms) > 0);
+    assert!(tms.cutime + tms.cstime > 0);
+
+    // so is the page fault of a child killed for it
+    assert_eq!(usage.minflt, 0);
+    let pid = fork();
+    if pid == 0 {
+        unsafe { core::ptr::null_mut::<u8>().write_volatile(1) };
+        exit(0);
+    }
+    waitpid(pid as usize, &mut status);
+    assert!(wifsignaled(status) && wtermsig(status) == SIGSEGV);
+    assert_eq!(getrusage(RUSAGE_CHILDREN, &mut usage), 0);
+    assert_eq!(usage.minflt, 1);
+
+    assert_eq!(getrusage(RUSAGE_SELF, &mut usage), 0);
+    assert!(usage.utime.to_ms() > 0);
+    assert!(getrusage(1, &mut usage) < 0);
+    println!("cpu_time passed!");
+    0
+}
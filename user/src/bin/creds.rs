@@ -0,0 +1,51 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // everything starts as root
+    assert_eq!(getuid(), 0);
+    assert_eq!(geteuid(), 0);
+    assert_eq!(getgid(), 0);
+    assert_eq!(getegid(), 0);
+
+    let parent = getpid();
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(getppid(), parent);
+        // root drops every id at once and cannot get them back
+        assert_eq!(setgid(100), 0);
+        assert_eq!(setuid(1000), 0);
+        assert_eq!((getuid(), geteuid(), getgid(), getegid()), (1000, 1000, 100, 100));
+        assert!(setuid(0) < 0);
+        assert!(setgid(0) < 0);
+        assert_eq!(setuid(1000), 0);
+        // the parent belongs to root
+        assert!(kill(parent as usize, 0) < 0);
+        let mut rlim = RLimit::default();
+        assert!(prlimit(parent as usize, RLIMIT_NOFILE, None, Some(&mut rlim)) < 0);
+        let grandchild = fork();
+        if grandchild == 0 {
+            // ids are inherited
+            assert_eq!(getuid(), 1000);
+            loop {
+                yield_();
+            }
+        }
+        // processes of the same user can be signaled
+        assert_eq!(kill(grandchild as usize, SIGKILL), 0);
+        let mut status = 0;
+        waitpid(grandchild as usize, &mut status);
+        exit(0);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert!(wifexited(status) && wexitstatus(status) == 0);
+    println!("creds passed!");
+    0
+}
@@ -0,0 +1,296 @@
+#![no_std]
+#![no_main]
+#![allow(clippy::println_empty_string)]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+const LF: u8 = 0x0au8;
+const CR: u8 = 0x0du8;
+const DL: u8 = 0x7fu8;
+const BS: u8 = 0x08u8;
+const LINE_START: &str = "(dbg) ";
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use user_lib::console::getchar;
+use user_lib::*;
+
+// the breakpoint instruction, which may be shorter than the word patched
+#[cfg(target_arch = "riscv64")]
+const BREAK: &[u8] = &0x0010_0073u32.to_le_bytes();
+#[cfg(target_arch = "x86_64")]
+const BREAK: &[u8] = &[0xcc];
+#[cfg(target_arch = "aarch64")]
+const BREAK: &[u8] = &0xd420_0000u32.to_le_bytes();
+#[cfg(target_arch = "loongarch64")]
+const BREAK: &[u8] = &0x002a_0000u32.to_le_bytes();
+
+/// A breakpoint with the word it replaced. Once hit the word is put back
+/// until a single step has run it, or for good on archs without one.
+struct Breakpoint {
+    addr: usize,
+    word: usize,
+}
+
+struct Debugger {
+    pid: usize,
+    breakpoints: Vec<Breakpoint>,
+    // the breakpoint the program stopped at, to plant again after a step
+    hit: Option<usize>,
+}
+
+fn parse_hex(s: &str) -> Option<usize> {
+    let s = s.trim_start_matches("0x");
+    usize::from_str_radix(s, 16).ok()
+}
+
+impl Debugger {
+    /// Run `args` traced and stop it right after its exec.
+    fn start(args: &[String]) -> Option<Self> {
+        let mut args_addr: Vec<*const u8> = args.iter().map(|arg| arg.as_ptr()).collect();
+        args_addr.push(core::ptr::null::<u8>());
+        let pid = fork();
+        if pid == 0 {
+            ptrace(PTRACE_TRACEME, 0, 0, 0);
+            execvp(args[0].as_str(), args_addr.as_slice());
+            exit(127);
+        }
+        let mut debugger = Self {
+            pid: pid as usize,
+            breakpoints: Vec::new(),
+            hit: None,
+        };
+        match debugger.wait() {
+            Some(SIGTRAP) => Some(debugger),
+            _ => None,
+        }
+    }
+    /// Wait for the program to stop, returning the stop signal, or report
+    /// its end and return None.
+    fn wait(&mut self) -> Option<i32> {
+        let mut status = 0;
+        waitpid(self.pid, &mut status);
+        if wifstopped(status) {
+            Some(wstopsig(status))
+        } else {
+            if wifexited(status) {
+                println!("[exited with code {}]", wexitstatus(status));
+            } else {
+                println!("[killed by signal {}]", wtermsig(status));
+            }
+            None
+        }
+    }
+    /// Write the breakpoint instruction over the word at `addr`.
+    fn plant(&self, addr: usize, word: usize) -> bool {
+        let mut patched = word.to_ne_bytes();
+        patched[..BREAK.len()].copy_from_slice(BREAK);
+        let patched = usize::from_ne_bytes(patched);
+        ptrace(PTRACE_POKETEXT, self.pid, addr, patched) == 0
+    }
+    fn set_breakpoint(&mut self, addr: usize) {
+        let word = match ptrace_peek(self.pid, addr) {
+            Some(word) => word,
+            None => return println!("cannot access {:#x}", addr),
+        };
+        if !self.plant(addr, word) {
+            return println!("cannot write {:#x}", addr);
+        }
+        println!("breakpoint {} at {:#x}", self.breakpoints.len(), addr);
+        self.breakpoints.push(Breakpoint { addr, word });
+    }
+    /// Put back the word under a breakpoint the program trapped on, and
+    /// move it back to the start of the breakpoint.
+    fn hit_breakpoint(&mut self) {
+        let mut regs = UserRegs::default();
+        ptrace_getregs(self.pid, &mut regs);
+        // some archs report the trap after the instruction
+        let hit = self
+            .breakpoints
+            .iter()
+            .position(|bp| (bp.addr..=bp.addr + BREAK.len()).contains(&regs.pc()));
+        let i = match hit {
+            Some(i) => i,
+            None => return println!("trap at {:#x}", regs.pc()),
+        };
+        let bp = &self.breakpoints[i];
+        ptrace(PTRACE_POKETEXT, self.pid, bp.addr, bp.word);
+        regs.set_pc(bp.addr);
+        ptrace_setregs(self.pid, &regs);
+        println!("hit breakpoint at {:#x}", bp.addr);
+        self.hit = Some(i);
+    }
+    /// Wait for a single step to end like `wait`, and plant the breakpoint
+    /// it stepped over again.
+    fn wait_step(&mut self) -> Option<i32> {
+        let stop = self.wait();
+        if let (Some(i), Some(_)) = (self.hit.take(), stop) {
+            let bp = &self.breakpoints[i];
+            self.plant(bp.addr, bp.word);
+        }
+        stop
+    }
+    /// Report why the program stopped, returning false once it is gone.
+    fn stopped(&mut self, stop: Option<i32>) -> bool {
+        match stop {
+            Some(SIGTRAP) => {
+                self.hit_breakpoint();
+                true
+            }
+            Some(sig) => {
+                println!("stopped by signal {}, pass it on with c {}", sig, sig);
+                true
+            }
+            None => false,
+        }
+    }
+    /// Continue the program, stepping over the breakpoint it is at first.
+    /// Return false once it is gone.
+    fn cont(&mut self, mut sig: i32) -> bool {
+        if let Some(i) = self.hit {
+            if ptrace(PTRACE_SINGLESTEP, self.pid, 0, sig as usize) < 0 {
+                // without single-step the breakpoint cannot be put back
+                self.breakpoints.remove(i);
+                self.hit = None;
+            } else {
+                match self.wait_step() {
+                    Some(SIGTRAP) => sig = 0,
+                    stop => return self.stopped(stop),
+                }
+            }
+        }
+        ptrace(PTRACE_CONT, self.pid, 0, sig as usize);
+        let stop = self.wait();
+        self.stopped(stop)
+    }
+    /// Run one instruction, returning false once the program is gone.
+    fn step(&mut self) -> bool {
+        if ptrace(PTRACE_SINGLESTEP, self.pid, 0, 0) < 0 {
+            println!("single-step is not supported");
+            return true;
+        }
+        match self.wait_step() {
+            Some(SIGTRAP) => {
+                let mut regs = UserRegs::default();
+                ptrace_getregs(self.pid, &mut regs);
+                println!("pc {:#x}", regs.pc());
+                true
+            }
+            stop => self.stopped(stop),
+        }
+    }
+    fn regs(&self) {
+        let mut regs = UserRegs::default();
+        ptrace_getregs(self.pid, &mut regs);
+        for (name, value) in USER_REG_NAMES.iter().zip(regs.as_words()) {
+            println!("{:8} {:#018x}", name, value);
+        }
+    }
+    fn examine(&self, addr: usize, count: usize) {
+        let step = core::mem::size_of::<usize>();
+        for i in 0..count {
+            let addr = addr + i * step;
+            match ptrace_peek(self.pid, addr) {
+                Some(word) => println!("{:#x}: {:#018x}", addr, word),
+                None => return println!("cannot access {:#x}", addr),
+            }
+        }
+    }
+    fn set_pc(&self, pc: usize) {
+        let mut regs = UserRegs::default();
+        ptrace_getregs(self.pid, &mut regs);
+        regs.set_pc(pc);
+        ptrace_setregs(self.pid, &regs);
+    }
+    fn kill(&mut self) {
+        ptrace(PTRACE_KILL, self.pid, 0, 0);
+        self.wait();
+    }
+    /// Run one command, returning false once the program is gone.
+    fn command(&mut self, line: &str) -> bool {
+        let words: Vec<_> = line.split(' ').filter(|w| !w.is_empty()).collect();
+        let arg = |i: usize| words.get(i).and_then(|w| parse_hex(w));
+        match words.as_slice() {
+            [] => {}
+            ["b", _] => match arg(1) {
+                Some(addr) => self.set_breakpoint(addr),
+                None => println!("Usage: b ADDR"),
+            },
+            ["c"] => return self.cont(0),
+            ["c", sig] => match sig.parse() {
+                Ok(sig) => return self.cont(sig),
+                Err(_) => println!("Usage: c [SIGNAL]"),
+            },
+            ["s"] => return self.step(),
+            ["regs"] => self.regs(),
+            ["x", _] | ["x", _, _] => match (arg(1), words.get(2).map(|w| w.parse())) {
+                (Some(addr), None) => self.examine(addr, 1),
+                (Some(addr), Some(Ok(count))) => self.examine(addr, count),
+                _ => println!("Usage: x ADDR [COUNT]"),
+            },
+            ["set", "pc", _] => match arg(2) {
+                Some(pc) => self.set_pc(pc),
+                None => println!("Usage: set pc ADDR"),
+            },
+            ["q"] => {
+                self.kill();
+                return false;
+            }
+            _ => println!("commands: b ADDR, c [SIGNAL], s, regs, x ADDR [COUNT], set pc ADDR, q"),
+        }
+        true
+    }
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc < 2 {
+        println!("Usage: dbg PROGRAM [ARGS...]");
+        return -1;
+    }
+    let args: Vec<String> = argv[1..]
+        .iter()
+        .map(|arg| {
+            let mut arg = String::from(*arg);
+            arg.push('\0');
+            arg
+        })
+        .collect();
+    let mut debugger = match Debugger::start(&args) {
+        Some(debugger) => debugger,
+        None => {
+            println!("cannot run {}", argv[1]);
+            return -1;
+        }
+    };
+    let mut line = String::new();
+    print!("{}", LINE_START);
+    loop {
+        let c = getchar();
+        match c {
+            LF | CR => {
+                println!("");
+                if !debugger.command(line.as_str()) {
+                    return 0;
+                }
+                line.clear();
+                print!("{}", LINE_START);
+            }
+            BS | DL => {
+                if !line.is_empty() {
+                    print!("{}", BS as char);
+                    print!(" ");
+                    print!("{}", BS as char);
+                    line.pop();
+                }
+            }
+            _ => {
+                print!("{}", c as char);
+                line.push(c as char);
+            }
+        }
+    }
+}
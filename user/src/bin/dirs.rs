@@ -0,0 +1,106 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+fn cwd_is(expected: &str) {
+    let mut buf = [0u8; 64];
+    let len = getcwd(&mut buf);
+    assert_eq!(len as usize, expected.len() + 1);
+    assert_eq!(&buf[..expected.len()], expected.as_bytes());
+}
+
+fn read_file(path: &str) -> isize {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return fd;
+    }
+    let mut buf = [0u8; 16];
+    let len = read(fd as usize, &mut buf);
+    close(fd as usize);
+    assert_eq!(&buf[..len as usize], b"in a directory");
+    len
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    cwd_is("/");
+    assert_eq!(mkdir("dirs_test\0"), 0);
+    assert_eq!(mkdir("dirs_test/sub/\0"), 0);
+    assert_eq!(mkdir("dirs_test\0"), -EEXIST);
+    assert_eq!(mkdir("/\0"), -EEXIST);
+    assert_eq!(mkdir("dirs_test/..\0"), -EEXIST);
+    assert_eq!(mkdir("dirs_none/sub\0"), -ENOENT);
+    assert_eq!(
+        mkdir("dirs_test/a_name_longer_than_easyfs_allows\0"),
+        -ENAMETOOLONG
+    );
+
+    let fd = open(
+        "/dirs_test/sub/file\0",
+        OpenFlags::CREATE | OpenFlags::WRONLY,
+    );
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, b"in a directory"), 14);
+    close(fd as usize);
+    // not in the root, nor in the other directory
+    assert_eq!(open("file\0", OpenFlags::RDONLY), -ENOENT);
+    assert_eq!(open("dirs_test/file\0", OpenFlags::RDONLY), -ENOENT);
+    assert_eq!(read_file("dirs_test/./sub/../sub/file\0"), 14);
+    assert_eq!(read_file("/../dirs_test//sub/file\0"), 14);
+    assert_eq!(open("dirs_test/sub/file/\0", OpenFlags::RDONLY), -ENOTDIR);
+    assert_eq!(open("dirs_test/sub/file/..\0", OpenFlags::RDONLY), -ENOTDIR);
+    assert_eq!(mkdir("dirs_test/sub/file/x\0"), -ENOTDIR);
+    assert_eq!(open("dirs_test\0", OpenFlags::WRONLY), -EISDIR);
+
+    // relative paths start from the current directory
+    assert_eq!(chdir("dirs_test/sub\0"), 0);
+    cwd_is("/dirs_test/sub");
+    assert_eq!(read_file("file\0"), 14);
+    assert_eq!(chdir("..\0"), 0);
+    cwd_is("/dirs_test");
+    assert_eq!(read_file("sub/file\0"), 14);
+    assert_eq!(chdir("sub/file\0"), -ENOTDIR);
+    assert_eq!(chdir("none\0"), -ENOENT);
+    cwd_is("/dirs_test");
+    // which a child inherits, and changes for itself only
+    let pid = fork();
+    if pid == 0 {
+        cwd_is("/dirs_test");
+        assert_eq!(chdir("/\0"), 0);
+        exit(0);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert!(wifexited(status) && wexitstatus(status) == 0);
+    cwd_is("/dirs_test");
+    assert_eq!(chdir("/..\0"), 0);
+    cwd_is("/");
+    assert_eq!(chdir("..\0"), 0);
+    cwd_is("/");
+
+    // paths of *at syscalls start from a directory fd
+    let dirfd = open("dirs_test\0", OpenFlags::RDONLY);
+    assert!(dirfd > 0);
+    let mut stat = Stat::default();
+    assert_eq!(fstat(dirfd as usize, &mut stat), 0);
+    assert_eq!(stat.mode & S_IFMT, S_IFDIR);
+    assert_eq!(mkdirat(dirfd, "other\0"), 0);
+    assert_eq!(mkdirat(dirfd, "sub\0"), -EEXIST);
+    assert_eq!(chdir("/dirs_test/other\0"), 0);
+    assert_eq!(chdir("/\0"), 0);
+    close(dirfd as usize);
+    assert_eq!(mkdirat(1, "other\0"), -ENOTDIR);
+
+    assert_eq!(rmdir("dirs_test/sub\0"), -ENOTEMPTY);
+    assert_eq!(unlink("dirs_test/sub/file\0"), 0);
+    assert_eq!(rmdir("dirs_test/sub\0"), 0);
+    assert_eq!(rmdir("dirs_test/other\0"), 0);
+    assert_eq!(rmdir("dirs_test\0"), 0);
+    assert_eq!(chdir("dirs_test\0"), -ENOENT);
+    println!("dirs passed!");
+    0
+}
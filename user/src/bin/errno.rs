@@ -0,0 +1,73 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+/// No syscall has this number
+const SYSCALL_UNKNOWN: usize = 999;
+/// Nothing is mapped this low
+const BAD_ADDR: usize = 0x10;
+/// Longer than any path, without a nul
+static LONG_PATH: [u8; 5000] = [b'a'; 5000];
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // an unknown syscall fails instead of taking down the kernel
+    assert_eq!(syscall_raw(SYSCALL_UNKNOWN, [0; 6]), -ENOSYS);
+    assert_eq!(errno(syscall_raw(SYSCALL_UNKNOWN, [0; 6])), Some(ENOSYS));
+
+    assert_eq!(close(100), -EBADF);
+    assert_eq!(dup(100), -EBADF);
+    assert_eq!(open("no_such_file\0", OpenFlags::RDONLY), -ENOENT);
+    let fd = open("errno_file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    assert_eq!(read(fd as usize, &mut [0u8; 1]), -EBADF);
+    close(fd as usize);
+    let fd = open("errno_file\0", OpenFlags::RDONLY);
+    assert!(fd >= 0);
+    // not opened for writing
+    assert_eq!(write(fd as usize, b"x"), -EBADF);
+    close(fd as usize);
+    assert_eq!(
+        syscall_raw(SYSCALL_WRITE, [1, BAD_ADDR, 8, 0, 0, 0]),
+        -EFAULT
+    );
+    // paths and the strings of exec are checked as they are copied in
+    assert_eq!(
+        syscall_raw(SYSCALL_OPEN, [BAD_ADDR, 0, 0, 0, 0, 0]),
+        -EFAULT
+    );
+    let long_path = LONG_PATH.as_ptr() as usize;
+    assert_eq!(
+        syscall_raw(SYSCALL_OPEN, [long_path, 0, 0, 0, 0, 0]),
+        -ENAMETOOLONG
+    );
+    let args = [BAD_ADDR as *const u8, core::ptr::null()];
+    assert_eq!(exec("hello_world\0", &args), -EFAULT);
+    let mut fds = [0usize; 2];
+    assert_eq!(
+        syscall_raw(SYSCALL_PIPE, [BAD_ADDR, 0, 0, 0, 0, 0]),
+        -EFAULT
+    );
+    assert_eq!(pipe(&mut fds), 0);
+    close(fds[0]);
+    close(fds[1]);
+
+    let mut status = 0;
+    assert_eq!(wait(&mut status), -ECHILD);
+    assert_eq!(kill(usize::MAX >> 1, SIGUSR1), -ESRCH);
+    assert_eq!(kill(getpid() as usize, -1), -EINVAL);
+    let action = SignalAction::default();
+    assert_eq!(sigaction(SIGKILL, Some(&action), None), -EINVAL);
+
+    assert_eq!(syscall_result(getpid()), Ok(getpid() as usize));
+    assert_eq!(syscall_result(close(100)), Err(EBADF));
+    assert_eq!(errno(yield_()), None);
+    assert_eq!(strerror(ENOENT), "No such file or directory");
+    assert_eq!(strerror(4000), "Unknown error");
+    println!("errno passed!");
+    0
+}
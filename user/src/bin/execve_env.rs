@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+extern crate alloc;
+
+use alloc::string::String;
+use user_lib::*;
+
+fn run_child(f: fn() -> isize) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        exit(f() as i32);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert!(wifexited(status));
+    wexitstatus(status)
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc > 1 {
+        // run again by the parent below
+        assert_eq!(argv[1], "child");
+        assert_eq!(getenv("GREETING").as_deref(), Some("hello world"));
+        assert_eq!(getenv("UNSET"), None);
+        return 42;
+    }
+
+    setenv("GREETING", "hello");
+    setenv("GREETING", "hello world");
+    setenv("UNSET", "1");
+    unsetenv("UNSET");
+    assert_eq!(getenv("GREETING").as_deref(), Some("hello world"));
+
+    // found through PATH and started with the environment
+    setenv("PATH", "/nonexistent:/");
+    let status = run_child(|| {
+        let args = ["execve_env\0".as_ptr(), "child\0".as_ptr(), core::ptr::null()];
+        execvp("execve_env\0", &args)
+    });
+    assert_eq!(status, 42);
+
+    let args = [core::ptr::null::<u8>()];
+    assert_eq!(exec("no_such_app\0", &args), -ENOENT);
+    assert_eq!(execvp("no_such_app\0", &args), -ENOENT);
+
+    // a file which is not an executable
+    let fd = open("not_elf\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, b"just text");
+    close(fd as usize);
+    assert_eq!(exec("not_elf\0", &args), -ENOEXEC);
+
+    // arguments which do not fit in the new stack
+    let mut huge = String::from("HUGE=");
+    for _ in 0..8192 {
+        huge.push('x');
+    }
+    huge.push('\0');
+    let envs = [huge.as_ptr(), core::ptr::null()];
+    assert_eq!(execve("execve_env\0", &args, &envs), -E2BIG);
+
+    println!("execve_env passed!");
+    0
+}
@@ -4,12 +4,20 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{close, open, read, write, OpenFlags};
+use user_lib::{close, open, read, unlink, write, OpenFlags, ENOENT};
 
+/// Without arguments, write and read back `filea`, which is left for the
+/// cat test. With `clean`, remove it.
 #[no_mangle]
-pub fn main() -> i32 {
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
     let test_str = "Hello, world!";
     let filea = "filea\0";
+    if argc == 2 && argv[1] == "clean" {
+        assert_eq!(unlink(filea), 0);
+        assert_eq!(open(filea, OpenFlags::RDONLY), -ENOENT);
+        println!("file_test clean passed!");
+        return 0;
+    }
     let fd = open(filea, OpenFlags::CREATE | OpenFlags::WRONLY);
     assert!(fd > 0);
     let fd = fd as usize;
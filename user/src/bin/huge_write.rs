@@ -4,7 +4,7 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{close, get_time, open, write, OpenFlags};
+use user_lib::{close, get_time, open, unlink, write, OpenFlags};
 
 #[no_mangle]
 pub fn main() -> i32 {
@@ -23,6 +23,7 @@ pub fn main() -> i32 {
         write(f, &buffer);
     }
     close(f);
+    assert_eq!(unlink("testf\0"), 0);
     let time_ms = (get_time() - start) as usize;
     let speed_kbs = size_mb * 1000000 / time_ms;
     println!(
@@ -3,17 +3,19 @@
 
 extern crate user_lib;
 
-use user_lib::{exec, fork, wait, yield_};
+use user_lib::{execve, fork, wait, yield_, ECHILD};
 
 #[no_mangle]
 fn main() -> i32 {
     if fork() == 0 {
-        exec("user_shell\0", &[core::ptr::null::<u8>()]);
+        // the environment every process inherits from the shell
+        let envs = ["PATH=/\0".as_ptr(), core::ptr::null::<u8>()];
+        execve("user_shell\0", &[core::ptr::null::<u8>()], &envs);
     } else {
         loop {
             let mut exit_code: i32 = 0;
             let pid = wait(&mut exit_code);
-            if pid == -1 {
+            if pid == -ECHILD {
                 yield_();
                 continue;
             }
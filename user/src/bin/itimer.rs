@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+static mut ALRM_COUNT: usize = 0;
+static mut VTALRM_COUNT: usize = 0;
+static mut PROF_COUNT: usize = 0;
+
+fn on_alarm(sig: i32) {
+    unsafe {
+        match sig {
+            SIGALRM => ALRM_COUNT += 1,
+            SIGVTALRM => VTALRM_COUNT += 1,
+            SIGPROF => PROF_COUNT += 1,
+            _ => unreachable!(),
+        }
+    }
+}
+
+fn busy_until(count: fn() -> usize) {
+    let start = get_time();
+    while count() == 0 {
+        assert!(get_time() - start < 5000, "timer did not fire");
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut action = SignalAction::default();
+    action.handler = on_alarm as usize;
+    for sig in [SIGALRM, SIGVTALRM, SIGPROF] {
+        if sigaction(sig, Some(&action), None) < 0 {
+            panic!("Sigaction failed!");
+        }
+    }
+
+    // alarm reports the seconds left on the previous one
+    assert_eq!(alarm(10), 0);
+    assert_eq!(alarm(0), 10);
+
+    let timer = ITimerVal {
+        interval: TimeVal::default(),
+        value: TimeVal::from_ms(50),
+    };
+    setitimer(ITIMER_REAL, &timer, None);
+    busy_until(|| unsafe { ALRM_COUNT });
+    setitimer(ITIMER_VIRTUAL, &timer, None);
+    busy_until(|| unsafe { VTALRM_COUNT });
+
+    // a periodic profiling timer keeps firing until it is disarmed
+    let periodic = ITimerVal {
+        interval: TimeVal::from_ms(20),
+        value: TimeVal::from_ms(20),
+    };
+    setitimer(ITIMER_PROF, &periodic, None);
+    busy_until(|| unsafe { if PROF_COUNT >= 3 { 1 } else { 0 } });
+    let mut old = ITimerVal::default();
+    setitimer(ITIMER_PROF, &ITimerVal::default(), Some(&mut old));
+    assert_eq!(old.interval.usec, 20_000);
+    getitimer(ITIMER_PROF, &mut old);
+    assert_eq!(old.value.sec + old.value.usec, 0);
+
+    println!("itimer passed!");
+    0
+}
@@ -0,0 +1,136 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use user_lib::*;
+
+fn write_file(path: &str, data: &[u8]) {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, data), data.len() as isize);
+    close(fd as usize);
+}
+
+fn read_file(path: &str) -> Option<String> {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return None;
+    }
+    let mut buf = [0u8; 32];
+    let len = read(fd as usize, &mut buf);
+    close(fd as usize);
+    Some(String::from(
+        core::str::from_utf8(&buf[..len as usize]).unwrap(),
+    ))
+}
+
+fn nlink(fd: isize) -> u32 {
+    let mut stat = Stat::default();
+    assert_eq!(fstat(fd as usize, &mut stat), 0);
+    stat.nlink
+}
+
+/// Unlink, link and rename in the empty directory `dir`, and leave it
+/// empty again.
+fn names(dir: &str) {
+    let path = |name: &str| format!("{}/{}\0", dir, name);
+    let a = path("a");
+    let b = path("b");
+    write_file(&a, b"data");
+    let fd = open(&a, OpenFlags::RDONLY);
+    assert_eq!(nlink(fd), 1);
+
+    // a second name for the same file
+    assert_eq!(link(&a, &b), 0);
+    assert_eq!(nlink(fd), 2);
+    assert_eq!(link(&a, &b), -EEXIST);
+    assert_eq!(link(&path("none"), &path("c")), -ENOENT);
+    write_file(&b, b"changed");
+    assert_eq!(read_file(&a).unwrap(), "changed");
+    assert_eq!(unlink(&a), 0);
+    assert_eq!(unlink(&a), -ENOENT);
+    assert_eq!(nlink(fd), 1);
+    assert_eq!(read_file(&b).unwrap(), "changed");
+    close(fd as usize);
+
+    // the last name goes, but the file lives on while it is open
+    let fd = open(&b, OpenFlags::RDWR);
+    assert_eq!(unlink(&b), 0);
+    assert_eq!(read_file(&b), None);
+    assert_eq!(nlink(fd), 0);
+    let mut buf = [0u8; 7];
+    assert_eq!(read(fd as usize, &mut buf), 7);
+    assert_eq!(&buf, b"changed");
+    assert_eq!(write(fd as usize, b"!"), 1);
+    close(fd as usize);
+    // and a new file of the same name is a new one
+    write_file(&b, b"new");
+    assert_eq!(read_file(&b).unwrap(), "new");
+
+    // renames
+    let c = path("c");
+    assert_eq!(rename(&b, &c), 0);
+    assert_eq!(read_file(&b), None);
+    assert_eq!(read_file(&c).unwrap(), "new");
+    write_file(&a, b"other");
+    assert_eq!(
+        renameat2(AT_FDCWD, &a, AT_FDCWD, &c, RENAME_NOREPLACE),
+        -EEXIST
+    );
+    assert_eq!(rename(&a, &c), 0);
+    assert_eq!(read_file(&a), None);
+    assert_eq!(read_file(&c).unwrap(), "other");
+    assert_eq!(rename(&c, &c), 0);
+    assert_eq!(rename(&a, &b), -ENOENT);
+
+    // directories move with what is in them
+    let sub = path("sub");
+    let moved = path("moved");
+    assert_eq!(mkdir(&sub), 0);
+    assert_eq!(rename(&c, &format!("{}/sub/c\0", dir)), 0);
+    assert_eq!(rename(&sub, &moved), 0);
+    assert_eq!(read_file(&format!("{}/moved/c\0", dir)).unwrap(), "other");
+    assert_eq!(rename(&moved, &format!("{}/moved/in\0", dir)), -EINVAL);
+    assert_eq!(link(&moved, &sub), -EPERM);
+    write_file(&a, b"file");
+    assert_eq!(rename(&a, &moved), -EISDIR);
+    assert_eq!(rename(&moved, &a), -ENOTDIR);
+    assert_eq!(unlink(&moved), -EISDIR);
+    assert_eq!(rmdir(&a), -ENOTDIR);
+    assert_eq!(rmdir(&moved), -ENOTEMPTY);
+    assert_eq!(unlink(&format!("{}/moved/c\0", dir)), 0);
+    // an empty directory can be replaced
+    assert_eq!(mkdir(&sub), 0);
+    assert_eq!(rename(&sub, &moved), 0);
+    assert_eq!(rmdir(&sub), -ENOENT);
+    assert_eq!(rmdir(&moved), 0);
+    assert_eq!(unlink(&a), 0);
+    assert_eq!(rmdir(&format!("{}/.\0", dir)), -EINVAL);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(mkdir("links_test\0"), 0);
+    names("links_test");
+    names("/links_test/../links_test");
+    // tmpfs does the same as easyfs
+    assert_eq!(mount("links_test\0", "tmpfs\0"), 0);
+    names("links_test");
+    assert_eq!(umount("links_test\0"), 0);
+    assert_eq!(rmdir("links_test\0"), 0);
+    assert_eq!(rmdir("links_test\0"), -ENOENT);
+
+    // the packed apps keep their names
+    assert_eq!(unlink("hello_world\0"), -EPERM);
+    assert_eq!(link("hello_world\0", "links_hello\0"), -EPERM);
+    assert_eq!(rename("hello_world\0", "links_hello\0"), -EPERM);
+    assert_eq!(read_file("links_hello\0"), None);
+    assert_eq!(unlink("/dev/random\0"), -EPERM);
+    println!("links passed!");
+    0
+}
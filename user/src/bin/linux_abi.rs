@@ -0,0 +1,117 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+const PAGE_SIZE: usize = 0x1000;
+
+fn heap() {
+    let start = brk(0);
+    assert_eq!(brk(start + 3 * PAGE_SIZE), start + 3 * PAGE_SIZE);
+    let heap = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, 3 * PAGE_SIZE) };
+    assert!(heap.iter().all(|b| *b == 0));
+    heap.fill(0x5a);
+    // shrinking gives the pages back, growing again gets zeroed ones
+    assert_eq!(brk(start + PAGE_SIZE), start + PAGE_SIZE);
+    assert_eq!(brk(start + 2 * PAGE_SIZE), start + 2 * PAGE_SIZE);
+    let heap = unsafe { core::slice::from_raw_parts(start as *const u8, 2 * PAGE_SIZE) };
+    assert!(heap[..PAGE_SIZE].iter().all(|b| *b == 0x5a));
+    assert!(heap[PAGE_SIZE..].iter().all(|b| *b == 0));
+    // the heap cannot move below its start
+    assert_eq!(brk(start - PAGE_SIZE), start + 2 * PAGE_SIZE);
+    assert_eq!(brk(start), start);
+    // nor above user space
+    assert_eq!(brk(usize::MAX & !(PAGE_SIZE - 1)), start);
+}
+
+fn mappings() {
+    let len = 4 * PAGE_SIZE;
+    let flags = MAP_PRIVATE | MAP_ANONYMOUS;
+    let addr = mmap(0, len, PROT_READ | PROT_WRITE, flags);
+    assert!(addr > 0 && addr as usize % PAGE_SIZE == 0);
+    let addr = addr as usize;
+    let mem = unsafe { core::slice::from_raw_parts_mut(addr as *mut u8, len) };
+    assert!(mem.iter().all(|b| *b == 0));
+    mem.fill(1);
+    // a child gets a copy
+    let pid = fork();
+    if pid == 0 {
+        mem.fill(2);
+        exit(0);
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    assert!(mem.iter().all(|b| *b == 1));
+    // unmapping the middle leaves both ends mapped, and a fixed mapping
+    // fills the hole with zeroed pages
+    assert_eq!(munmap(addr + PAGE_SIZE, 2 * PAGE_SIZE), 0);
+    assert_eq!(mem[0], 1);
+    assert_eq!(mem[len - 1], 1);
+    let fixed = mmap(addr + PAGE_SIZE, PAGE_SIZE, PROT_READ, flags | MAP_FIXED);
+    assert_eq!(fixed as usize, addr + PAGE_SIZE);
+    assert_eq!(mem[PAGE_SIZE], 0);
+    // a syscall can tell that the rest of the hole is gone
+    let hole = IoVec {
+        base: (addr + 2 * PAGE_SIZE) as *const u8,
+        len: 1,
+    };
+    assert_eq!(writev(1, &[hole]), -EFAULT);
+    // a fixed mapping which cannot be made leaves the old one in place
+    let huge = 1 << 48;
+    assert_eq!(mmap(addr, huge, PROT_READ, flags | MAP_FIXED), -ENOMEM);
+    assert_eq!(mmap(0, PAGE_SIZE, PROT_READ, flags | MAP_FIXED), -ENOMEM);
+    assert_eq!(mem[0], 1);
+    assert_eq!(munmap(addr, len), 0);
+
+    assert_eq!(mmap(0, 0, PROT_READ, flags), -EINVAL);
+    assert_eq!(mmap(0, usize::MAX, PROT_READ, flags), -ENOMEM);
+    assert_eq!(mmap(0, len, PROT_READ, MAP_SHARED | MAP_ANONYMOUS), -EINVAL);
+    assert_eq!(mmap(0, len, PROT_READ, MAP_PRIVATE), -ENODEV);
+    assert_eq!(mmap(addr + 1, len, PROT_READ, flags | MAP_FIXED), -EINVAL);
+    assert_eq!(munmap(addr + 1, len), -EINVAL);
+}
+
+fn files() {
+    let mut stat = Stat::default();
+    assert_eq!(fstat(1, &mut stat), 0);
+    assert_eq!(stat.mode & S_IFMT, S_IFCHR);
+    assert!(isatty(0) && isatty(1));
+    assert_eq!(fstat(100, &mut stat), -EBADF);
+
+    let fd = open("linux_abi_file\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd >= 0);
+    let iov = [IoVec::new(b"hello, "), IoVec::new(b"world\n")];
+    assert_eq!(writev(fd as usize, &iov), 13);
+    assert_eq!(fstat(fd as usize, &mut stat), 0);
+    assert_eq!(stat.mode & S_IFMT, S_IFREG);
+    assert_eq!(stat.size, 13);
+    assert!(!isatty(fd as usize));
+    close(fd as usize);
+
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    assert_eq!(fstat(pipe_fd[0], &mut stat), 0);
+    assert_eq!(stat.mode & S_IFMT, S_IFIFO);
+    close(pipe_fd[0]);
+    close(pipe_fd[1]);
+
+    let mut cwd = [0u8; 16];
+    assert_eq!(getcwd(&mut cwd), 2);
+    assert_eq!(&cwd[..2], b"/\0");
+    assert_eq!(getcwd(&mut cwd[..1]), -ERANGE);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    heap();
+    mappings();
+    files();
+    let mut uts = UtsName::default();
+    assert_eq!(uname(&mut uts), 0);
+    assert_eq!(uts_str(&uts.sysname), "Linux");
+    println!("linux_abi passed!");
+    0
+}
@@ -0,0 +1,149 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::format;
+use user_lib::*;
+
+fn write_file(path: &str, data: &[u8]) {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    assert_eq!(write(fd as usize, data), data.len() as isize);
+    close(fd as usize);
+}
+
+fn file_is(path: &str, data: &[u8]) -> bool {
+    let fd = open(path, OpenFlags::RDONLY);
+    if fd < 0 {
+        return false;
+    }
+    let mut buf = [0u8; 32];
+    let len = read(fd as usize, &mut buf);
+    close(fd as usize);
+    &buf[..len as usize] == data
+}
+
+/// The text of the file at `path`, which fits in `buf`.
+fn read_text<'a>(path: &str, buf: &'a mut [u8]) -> &'a str {
+    let fd = open(path, OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let len = read(fd as usize, buf);
+    assert!(len > 0 && (len as usize) < buf.len());
+    close(fd as usize);
+    core::str::from_utf8(&buf[..len as usize]).unwrap()
+}
+
+/// procfs is at /proc from boot on, and follows the kernel.
+fn procfs() {
+    let mut buf = [0u8; 512];
+    assert!(read_text("/proc/meminfo\0", &mut buf).starts_with("MemTotal:"));
+    let mounts = read_text("/proc/mounts\0", &mut buf);
+    assert!(mounts.contains("devfs /dev devfs"));
+    assert!(mounts.contains("proc /proc proc"));
+    let pid = getpid();
+    let status = read_text(&format!("/proc/{}/status\0", pid), &mut buf);
+    assert!(status.contains(&format!("\nPid:\t{}\n", pid)));
+    assert!(status.contains("State:\tR"));
+    assert_eq!(open("/proc/99999/status\0", OpenFlags::RDONLY), -ENOENT);
+    assert_eq!(open("/proc/none\0", OpenFlags::RDONLY), -ENOENT);
+    assert_eq!(
+        open("/proc/none\0", OpenFlags::CREATE | OpenFlags::WRONLY),
+        -EPERM
+    );
+    assert_eq!(unlink("/proc/meminfo\0"), -EPERM);
+}
+
+/// devfs is at /dev from boot on, and has a fixed set of devices.
+fn devfs() {
+    let fd = open("/dev\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut stat = Stat::default();
+    assert_eq!(fstat(fd as usize, &mut stat), 0);
+    assert_eq!(stat.mode & S_IFMT, S_IFDIR);
+    close(fd as usize);
+    let fd = open("/dev/../dev/./urandom\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+    assert_eq!(open("/dev/none\0", OpenFlags::RDONLY), -ENOENT);
+    assert_eq!(
+        open("/dev/none\0", OpenFlags::CREATE | OpenFlags::WRONLY),
+        -EPERM
+    );
+    assert_eq!(mkdir("/dev\0"), -EEXIST);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    devfs();
+    procfs();
+    assert_eq!(mkdir("mount_test\0"), 0);
+    write_file("mount_test/below\0", b"easyfs");
+
+    assert_eq!(mount("mount_test\0", "tmpfs\0"), 0);
+    // the tmpfs hides what easyfs has there
+    assert_eq!(open("mount_test/below\0", OpenFlags::RDONLY), -ENOENT);
+    write_file("mount_test/file\0", b"tmpfs");
+    assert_eq!(mkdir("mount_test/sub\0"), 0);
+    write_file("mount_test/sub/file\0", b"in sub");
+    assert!(file_is("/mount_test/./sub/../file\0", b"tmpfs"));
+    // `..` of its root leads back to easyfs
+    assert_eq!(chdir("mount_test/sub\0"), 0);
+    assert!(file_is("../../mount_test/file\0", b"tmpfs"));
+    assert_eq!(chdir("/\0"), 0);
+
+    // names stay within one file system
+    assert_eq!(rename("mount_test/file\0", "mount_file\0"), -EXDEV);
+    assert_eq!(link("mount_test/file\0", "mount_file\0"), -EXDEV);
+    assert_eq!(rmdir("mount_test\0"), -EBUSY);
+
+    assert_eq!(mount("mount_test/file\0", "tmpfs\0"), -ENOTDIR);
+    assert_eq!(mount("mount_none\0", "tmpfs\0"), -ENOENT);
+    assert_eq!(mount("mount_test\0", "nofs\0"), -ENODEV);
+    // a tmpfs mounted over /proc hides the kernel's files
+    assert_eq!(mount("/proc\0", "tmpfs\0"), 0);
+    assert_eq!(open("/proc/meminfo\0", OpenFlags::RDONLY), -ENOENT);
+    assert_eq!(umount("/proc\0"), 0);
+    let fd = open("/proc/meminfo\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    close(fd as usize);
+
+    // a file system mounted inside keeps the outer one busy
+    assert_eq!(mount("mount_test/sub\0", "tmpfs\0"), 0);
+    assert_eq!(open("mount_test/sub/file\0", OpenFlags::RDONLY), -ENOENT);
+    assert_eq!(umount("mount_test\0"), -EBUSY);
+    assert_eq!(umount("mount_test/sub\0"), 0);
+    assert!(file_is("mount_test/sub/file\0", b"in sub"));
+
+    // only root mounts
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(setuid(1000), 0);
+        assert_eq!(mount("mount_test/sub\0", "tmpfs\0"), -EPERM);
+        assert_eq!(umount("mount_test\0"), -EPERM);
+        exit(0);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert!(wifexited(status) && wexitstatus(status) == 0);
+
+    // a file open in it stays usable after umount
+    let fd = open("mount_test/file\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    assert_eq!(umount("mount_test\0"), 0);
+    let mut buf = [0u8; 8];
+    assert_eq!(read(fd as usize, &mut buf), 5);
+    assert_eq!(&buf[..5], b"tmpfs");
+    close(fd as usize);
+    assert!(file_is("mount_test/below\0", b"easyfs"));
+    assert_eq!(open("mount_test/file\0", OpenFlags::RDONLY), -ENOENT);
+    assert_eq!(umount("mount_test\0"), -EINVAL);
+    assert_eq!(umount("/\0"), -EBUSY);
+    assert_eq!(unlink("mount_test/below\0"), 0);
+    assert_eq!(rmdir("mount_test\0"), 0);
+    println!("mount passed!");
+    0
+}
@@ -0,0 +1,157 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::*;
+
+static WORD: AtomicUsize = AtomicUsize::new(1);
+
+#[cfg(target_arch = "riscv64")]
+fn breakpoint() {
+    unsafe { core::arch::asm!("ebreak") };
+}
+#[cfg(target_arch = "x86_64")]
+fn breakpoint() {
+    unsafe { core::arch::asm!("int3") };
+}
+#[cfg(target_arch = "aarch64")]
+fn breakpoint() {
+    unsafe { core::arch::asm!("brk #0") };
+}
+#[cfg(target_arch = "loongarch64")]
+fn breakpoint() {
+    unsafe { core::arch::asm!("break 0") };
+}
+
+// the tracer moves the child here after its breakpoint
+extern "C" fn after_breakpoint() -> ! {
+    exit(3)
+}
+
+fn wait_stop(pid: usize) -> i32 {
+    let mut status = 0;
+    assert_eq!(waitpid(pid, &mut status), pid as isize);
+    assert!(wifstopped(status));
+    wstopsig(status)
+}
+
+fn peek_and_poke() {
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(ptrace(PTRACE_TRACEME, 0, 0, 0), 0);
+        kill(getpid() as usize, SIGUSR1);
+        // the tracer has changed the word and swallowed SIGUSR1
+        exit(WORD.load(Ordering::SeqCst) as i32);
+    }
+    let pid = pid as usize;
+    assert_eq!(wait_stop(pid), SIGUSR1);
+    let addr = WORD.as_ptr() as usize;
+    assert_eq!(ptrace_peek(pid, addr), Some(1));
+    assert_eq!(ptrace(PTRACE_POKEDATA, pid, addr, 42), 0);
+    assert_eq!(ptrace_peek(pid, addr), Some(42));
+    // our copy of the word is untouched
+    assert_eq!(WORD.load(Ordering::SeqCst), 1);
+    assert_eq!(ptrace_peek(pid, 0), None);
+    let mut regs = UserRegs::default();
+    assert_eq!(ptrace_getregs(pid, &mut regs), 0);
+    assert_ne!(regs.pc(), 0);
+    assert_ne!(regs.sp(), 0);
+    assert_eq!(ptrace(PTRACE_CONT, pid, 0, 0), 0);
+    let mut status = 0;
+    assert_eq!(waitpid(pid, &mut status), pid as isize);
+    assert!(wifexited(status));
+    assert_eq!(wexitstatus(status), 42);
+}
+
+fn breakpoint_stop() {
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(ptrace(PTRACE_TRACEME, 0, 0, 0), 0);
+        breakpoint();
+        // not reached, the tracer skips over to after_breakpoint
+        exit(1);
+    }
+    let pid = pid as usize;
+    assert_eq!(wait_stop(pid), SIGTRAP);
+    let mut regs = UserRegs::default();
+    assert_eq!(ptrace_getregs(pid, &mut regs), 0);
+    regs.set_pc(after_breakpoint as usize);
+    // enter the function the way a call would
+    if cfg!(target_arch = "x86_64") {
+        regs.set_sp((regs.sp() & !0xf) - 8);
+    }
+    assert_eq!(ptrace_setregs(pid, &regs), 0);
+    assert_eq!(ptrace(PTRACE_CONT, pid, 0, 0), 0);
+    let mut status = 0;
+    assert_eq!(waitpid(pid, &mut status), pid as isize);
+    assert!(wifexited(status));
+    assert_eq!(wexitstatus(status), 3);
+}
+
+fn single_step() {
+    let pid = fork();
+    if pid == 0 {
+        assert_eq!(ptrace(PTRACE_TRACEME, 0, 0, 0), 0);
+        kill(getpid() as usize, SIGUSR1);
+        exit(4);
+    }
+    let pid = pid as usize;
+    assert_eq!(wait_stop(pid), SIGUSR1);
+    if cfg!(any(target_arch = "aarch64", target_arch = "loongarch64")) {
+        assert_eq!(ptrace(PTRACE_SINGLESTEP, pid, 0, 0), -EIO);
+    } else {
+        // every step stops the child again one instruction further
+        let mut regs = UserRegs::default();
+        for _ in 0..16 {
+            assert_eq!(ptrace_getregs(pid, &mut regs), 0);
+            let pc = regs.pc();
+            assert_eq!(ptrace(PTRACE_SINGLESTEP, pid, 0, 0), 0);
+            assert_eq!(wait_stop(pid), SIGTRAP);
+            assert_eq!(ptrace_getregs(pid, &mut regs), 0);
+            assert_ne!(regs.pc(), pc);
+        }
+    }
+    // no breakpoint of the steps is left behind
+    assert_eq!(ptrace(PTRACE_CONT, pid, 0, 0), 0);
+    let mut status = 0;
+    assert_eq!(waitpid(pid, &mut status), pid as isize);
+    assert!(wifexited(status));
+    assert_eq!(wexitstatus(status), 4);
+}
+
+fn attach_detach() {
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            yield_();
+        }
+    }
+    let pid = pid as usize;
+    // requests other than attaching need a tracee of ours
+    assert_eq!(ptrace(PTRACE_CONT, pid, 0, 0), -ESRCH);
+    assert_eq!(ptrace(PTRACE_ATTACH, pid, 0, 0), 0);
+    assert_eq!(ptrace(PTRACE_ATTACH, pid, 0, 0), -EPERM);
+    assert_eq!(wait_stop(pid), SIGSTOP);
+    assert_eq!(ptrace(PTRACE_DETACH, pid, 0, 0), 0);
+    assert_eq!(ptrace(PTRACE_CONT, pid, 0, 0), -ESRCH);
+    kill(pid, SIGKILL);
+    let mut status = 0;
+    assert_eq!(waitpid(pid, &mut status), pid as isize);
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), SIGKILL);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    peek_and_poke();
+    breakpoint_stop();
+    single_step();
+    attach_detach();
+    // no one traces us
+    assert_eq!(ptrace(PTRACE_CONT, getpid() as usize, 0, 0), -ESRCH);
+    println!("ptrace passed!");
+    0
+}
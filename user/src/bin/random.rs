@@ -0,0 +1,64 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+const LEN: usize = 4096;
+
+/// Every bit is set in about half of `bytes`.
+fn looks_random(bytes: &[u8]) -> bool {
+    (0..8).all(|bit| {
+        let ones = bytes.iter().filter(|c| *c & (1 << bit) != 0).count();
+        ones > bytes.len() * 2 / 5 && ones < bytes.len() * 3 / 5
+    })
+}
+
+fn device(path: &str) {
+    let fd = open(path, OpenFlags::RDWR);
+    assert!(fd >= 0);
+    let fd = fd as usize;
+    let mut stat = Stat::default();
+    assert_eq!(fstat(fd, &mut stat), 0);
+    assert_eq!(stat.mode & S_IFMT, S_IFCHR);
+    let (mut a, mut b) = ([0u8; LEN], [0u8; LEN]);
+    assert_eq!(read(fd, &mut a), LEN as isize);
+    assert_eq!(read(fd, &mut b), LEN as isize);
+    assert_ne!(a[..], b[..]);
+    assert!(looks_random(&a) && looks_random(&b));
+    // writes are taken, and change nothing one could see
+    assert_eq!(write(fd, b"some entropy"), 12);
+    close(fd);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    device("/dev/urandom\0");
+    device("/dev/random\0");
+    let mut buf = [0u8; LEN];
+    assert_eq!(getrandom(&mut buf, GRND_INSECURE), LEN as isize);
+    assert!(looks_random(&buf));
+    // seeded by now, as /dev/random returned
+    assert_eq!(getrandom(&mut buf, GRND_NONBLOCK), LEN as isize);
+    assert!(looks_random(&buf));
+    // a child starts from the same state, yet draws other bytes
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let mut mine = [0u8; 32];
+    let pid = fork();
+    assert_eq!(getrandom(&mut mine, 0), 32);
+    if pid == 0 {
+        write(pipe_fd[1], &mine);
+        exit(0);
+    }
+    close(pipe_fd[1]);
+    let mut child = [0u8; 32];
+    assert_eq!(read(pipe_fd[0], &mut child), 32);
+    assert_ne!(mine, child);
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    println!("random passed!");
+    0
+}
@@ -0,0 +1,120 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+/// Run `f` in a child and return its wait status.
+fn in_child(f: fn() -> i32) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        exit(f());
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    status
+}
+
+fn nofile() -> i32 {
+    let mut rlim = RLimit::default();
+    getrlimit(RLIMIT_NOFILE, &mut rlim);
+    // only root can raise a hard limit again
+    assert_eq!(setuid(1000), 0);
+    let low = RLimit { cur: 4, max: 4 };
+    assert_eq!(setrlimit(RLIMIT_NOFILE, &low), 0);
+    assert!(setrlimit(RLIMIT_NOFILE, &rlim) < 0);
+    // 0, 1 and 2 are taken
+    assert_eq!(dup(1), 3);
+    assert!(dup(1) < 0);
+    let mut pipe_fd = [0usize; 2];
+    assert!(pipe(&mut pipe_fd) < 0);
+    0
+}
+
+fn nproc() -> i32 {
+    let rlim = RLimit { cur: 1, max: 1 };
+    assert_eq!(setrlimit(RLIMIT_NPROC, &rlim), 0);
+    assert!(fork() < 0);
+    0
+}
+
+fn fsize() -> i32 {
+    let mut ign = SignalAction::default();
+    ign.handler = SIG_IGN;
+    sigaction(SIGXFSZ, Some(&ign), None);
+    let rlim = RLimit { cur: 10, max: 10 };
+    assert_eq!(setrlimit(RLIMIT_FSIZE, &rlim), 0);
+    let fd = open("rlimit_fsize\0", OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    let buf = [b'x'; 16];
+    // the write stops at the limit, then nothing more can be written
+    assert_eq!(write(fd as usize, &buf), 10);
+    assert_eq!(write(fd as usize, &buf), 0);
+    close(fd as usize);
+    0
+}
+
+fn cpu() -> i32 {
+    let rlim = RLimit { cur: 1, max: 2 };
+    assert_eq!(setrlimit(RLIMIT_CPU, &rlim), 0);
+    loop {}
+}
+
+fn prlimit_child() {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(pipe(&mut pipe_fd), 0);
+    let pid = fork();
+    if pid == 0 {
+        close(pipe_fd[1]);
+        // wait until the parent has changed the limit
+        let mut buf = [0u8; 1];
+        read(pipe_fd[0], &mut buf);
+        let mut rlim = RLimit::default();
+        getrlimit(RLIMIT_NOFILE, &mut rlim);
+        exit(if rlim.cur == 5 && rlim.max == 8 { 0 } else { 1 });
+    }
+    close(pipe_fd[0]);
+    let new = RLimit { cur: 5, max: 8 };
+    let mut old = RLimit::default();
+    let mut own = RLimit::default();
+    getrlimit(RLIMIT_NOFILE, &mut own);
+    assert_eq!(
+        prlimit(pid as usize, RLIMIT_NOFILE, Some(&new), Some(&mut old)),
+        0
+    );
+    // the child inherited the limit of its parent
+    assert_eq!((old.cur, old.max), (own.cur, own.max));
+    write(pipe_fd[1], b"x");
+    close(pipe_fd[1]);
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert!(wifexited(status) && wexitstatus(status) == 0);
+    // 0 is the caller, and a missing process is an error
+    assert_eq!(prlimit(0, RLIMIT_NOFILE, None, Some(&mut old)), 0);
+    assert_eq!((old.cur, old.max), (own.cur, own.max));
+    assert!(prlimit(pid as usize, RLIMIT_NOFILE, None, Some(&mut old)) < 0);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let status = in_child(nofile);
+    assert!(wifexited(status) && wexitstatus(status) == 0);
+    let status = in_child(nproc);
+    assert!(wifexited(status) && wexitstatus(status) == 0);
+    let status = in_child(fsize);
+    assert!(wifexited(status) && wexitstatus(status) == 0);
+    // SIGXCPU terminates a task by default
+    let status = in_child(cpu);
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), SIGXCPU);
+    prlimit_child();
+
+    let mut rlim = RLimit::default();
+    assert_eq!(getrlimit(RLIMIT_NOFILE, &mut rlim), 0);
+    assert!(rlim.cur <= rlim.max && rlim.cur != RLIM_INFINITY);
+    assert!(getrlimit(64, &mut rlim) < 0);
+    println!("rlimit passed!");
+    0
+}
@@ -0,0 +1,171 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::*;
+
+static TRAPPED: AtomicUsize = AtomicUsize::new(0);
+
+const ALT_STACK_SIZE: usize = 8192;
+static mut ALT_STACK: [u8; ALT_STACK_SIZE] = [0; ALT_STACK_SIZE];
+
+/// The fields of the signal frame of the kernel in front of the saved
+/// context, which takes up the rest of it up to the top of the stack
+#[repr(C)]
+struct FrameHead {
+    info: SignalInfo,
+    mask: u64,
+    handling_sig: isize,
+    prev: usize,
+}
+
+extern "C" fn on_sigsys(_sig: i32, info: *const SignalInfo, _frame: usize) {
+    let info = unsafe { &*info };
+    assert_eq!(info.code, SYS_SECCOMP);
+    TRAPPED.store(info.value, Ordering::SeqCst);
+}
+
+extern "C" fn forge_frame(_sig: i32, _info: *const SignalInfo, frame: usize) {
+    // below user code, and in every privileged field a mode other than
+    // the user one: sstatus.SPP, cs and rflags.IOPL, spsr.M and prmd.PPLV
+    let forged = 0x3ffc;
+    let top = unsafe { ALT_STACK.as_ptr() as usize } + ALT_STACK_SIZE;
+    let start = frame + core::mem::size_of::<FrameHead>();
+    for addr in (start..top).step_by(core::mem::size_of::<usize>()) {
+        unsafe { *(addr as *mut usize) = forged };
+    }
+}
+
+/// Run `f` in a child, which a filter cannot outlive, and return its wait status.
+fn in_child(f: fn() -> i32) -> i32 {
+    let pid = fork();
+    if pid == 0 {
+        exit(f());
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    status
+}
+
+fn deny_errno() -> i32 {
+    let action = SECCOMP_RET_ERRNO | EPERM as usize;
+    assert_eq!(
+        seccomp_filter(SECCOMP_MODE_DENY_LIST, action, &[SYSCALL_CLONE]),
+        0
+    );
+    assert_eq!(fork(), -EPERM);
+    assert!(getpid() > 0);
+    0
+}
+
+fn allow_list() -> i32 {
+    let action = SECCOMP_RET_ERRNO | EPERM as usize;
+    let allowed = [SYSCALL_GETPID, SYSCALL_CLONE, SYSCALL_WAITPID, SYSCALL_OPEN];
+    assert_eq!(seccomp_filter(SECCOMP_MODE_ALLOW_LIST, action, &allowed), 0);
+    assert!(getpid() > 0);
+    assert_eq!(yield_(), -EPERM);
+    // seccomp is not on the list either, so no filter can be added, and
+    // children get the filter too
+    let action = SECCOMP_RET_ERRNO | EFAULT as usize;
+    assert_eq!(
+        seccomp_filter(SECCOMP_MODE_DENY_LIST, action, &[SYSCALL_OPEN]),
+        -EPERM
+    );
+    let status = in_child(|| {
+        assert_eq!(yield_(), -EPERM);
+        7
+    });
+    assert_eq!(wexitstatus(status), 7);
+    0
+}
+
+fn trap() -> i32 {
+    let mut action = SignalAction::default();
+    action.handler = on_sigsys as usize;
+    action.flags = SignalActionFlags::SA_SIGINFO;
+    assert_eq!(sigaction(SIGSYS, Some(&action), None), 0);
+    let denied = [SYSCALL_GETPPID];
+    assert_eq!(
+        seccomp_filter(SECCOMP_MODE_DENY_LIST, SECCOMP_RET_TRAP, &denied),
+        0
+    );
+    assert_eq!(getppid(), -ENOSYS);
+    assert_eq!(TRAPPED.load(Ordering::SeqCst), SYSCALL_GETPPID);
+    0
+}
+
+fn kill_process() -> i32 {
+    let action = SECCOMP_RET_KILL_PROCESS;
+    assert_eq!(
+        seccomp_filter(SECCOMP_MODE_DENY_LIST, action, &[SYSCALL_GETPID]),
+        0
+    );
+    getpid();
+    1
+}
+
+fn forged_sigreturn() -> i32 {
+    let action = SECCOMP_RET_ERRNO | EPERM as usize;
+    assert_eq!(
+        seccomp_filter(SECCOMP_MODE_DENY_LIST, action, &[SYSCALL_CLONE]),
+        0
+    );
+    let ss = SignalStack {
+        sp: unsafe { ALT_STACK.as_ptr() as usize },
+        flags: 0,
+        size: ALT_STACK_SIZE,
+    };
+    assert_eq!(sigaltstack(Some(&ss), None), 0);
+    // the frame is the first thing on the alternate stack
+    let mut action = SignalAction::default();
+    action.handler = forge_frame as usize;
+    action.flags = SignalActionFlags::SA_SIGINFO | SignalActionFlags::SA_ONSTACK;
+    assert_eq!(sigaction(SIGUSR1, Some(&action), None), 0);
+    // sigreturn is never filtered, but only takes the user registers from
+    // the frame, so the task goes on in user mode and faults on the pc
+    kill(getpid() as usize, SIGUSR1);
+    1
+}
+
+fn strictest_action() -> i32 {
+    // a later, milder filter does not save us
+    let kill = SECCOMP_RET_KILL_PROCESS;
+    let errno = SECCOMP_RET_ERRNO | EPERM as usize;
+    assert_eq!(
+        seccomp_filter(SECCOMP_MODE_DENY_LIST, kill, &[SYSCALL_YIELD]),
+        0
+    );
+    assert_eq!(
+        seccomp_filter(SECCOMP_MODE_DENY_LIST, errno, &[SYSCALL_YIELD]),
+        0
+    );
+    yield_();
+    1
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(seccomp_filter(2, SECCOMP_RET_TRAP, &[]), -EINVAL);
+    assert_eq!(
+        seccomp_filter(SECCOMP_MODE_DENY_LIST, 0x1234_0000, &[]),
+        -EINVAL
+    );
+    assert_eq!(in_child(deny_errno), 0);
+    assert_eq!(in_child(allow_list), 0);
+    assert_eq!(in_child(trap), 0);
+    for f in [kill_process, strictest_action] {
+        let status = in_child(f);
+        assert!(wifsignaled(status));
+        assert_eq!(wtermsig(status), SIGSYS);
+    }
+    let status = in_child(forged_sigreturn);
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), SIGSEGV);
+    // none of the filters of the children applies to us
+    assert!(yield_() == 0 && getppid() > 0);
+    println!("seccomp passed!");
+    0
+}
@@ -0,0 +1,101 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+fn create(path: &str, content: &[u8]) {
+    let fd = open(path, OpenFlags::CREATE | OpenFlags::WRONLY);
+    assert!(fd > 0);
+    write(fd as usize, content);
+    close(fd as usize);
+}
+
+#[cfg(target_arch = "riscv64")]
+const EM_MACHINE: u16 = 243;
+#[cfg(target_arch = "aarch64")]
+const EM_MACHINE: u16 = 183;
+#[cfg(target_arch = "x86_64")]
+const EM_MACHINE: u16 = 62;
+#[cfg(target_arch = "loongarch64")]
+const EM_MACHINE: u16 = 258;
+
+/// An ELF executable with nothing but its headers, loaded in one segment
+/// at `vaddr` which is `memsz` bytes long.
+fn tiny_elf(vaddr: u64, memsz: u64, entry: u64) -> [u8; 120] {
+    let mut elf = [0u8; 120];
+    elf[..8].copy_from_slice(b"\x7fELF\x02\x01\x01\x00");
+    let mut put = |offset: usize, bytes: &[u8]| {
+        elf[offset..offset + bytes.len()].copy_from_slice(bytes);
+    };
+    // file header: ET_EXEC, version, entry, program headers right after it
+    put(16, &2u16.to_le_bytes());
+    put(18, &EM_MACHINE.to_le_bytes());
+    put(20, &1u32.to_le_bytes());
+    put(24, &entry.to_le_bytes());
+    put(32, &64u64.to_le_bytes());
+    put(52, &64u16.to_le_bytes());
+    put(54, &56u16.to_le_bytes());
+    put(56, &1u16.to_le_bytes());
+    put(58, &64u16.to_le_bytes());
+    // one PT_LOAD, readable and executable, holding the whole file
+    put(64, &1u32.to_le_bytes());
+    put(68, &5u32.to_le_bytes());
+    put(80, &vaddr.to_le_bytes());
+    put(88, &vaddr.to_le_bytes());
+    put(96, &120u64.to_le_bytes());
+    put(104, &memsz.to_le_bytes());
+    put(112, &0x1000u64.to_le_bytes());
+    elf
+}
+
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc > 1 {
+        // run as the interpreter of the script below
+        assert_eq!(argc, 4);
+        assert_eq!(argv[0], "/shebang");
+        assert_eq!(argv[1], "--interp");
+        assert_eq!(argv[2], "shebang_script");
+        assert_eq!(argv[3], "extra");
+        return 7;
+    }
+
+    create("shebang_script\0", b"#! /shebang --interp \nignored by the kernel\n");
+    let pid = fork();
+    if pid == 0 {
+        let args = ["shebang_script\0".as_ptr(), "extra\0".as_ptr(), core::ptr::null()];
+        exec("shebang_script\0", &args);
+        exit(-1);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert!(wifexited(status));
+    assert_eq!(wexitstatus(status), 7);
+
+    // broken executables fail instead of bringing the kernel down
+    let args = [core::ptr::null::<u8>()];
+    create("shebang_empty\0", b"#!\n");
+    assert_eq!(exec("shebang_empty\0", &args), -ENOEXEC);
+    create("shebang_missing\0", b"#!/no_such_interpreter\n");
+    assert_eq!(exec("shebang_missing\0", &args), -ENOENT);
+    create("shebang_loop\0", b"#!/shebang_loop\n");
+    assert_eq!(exec("shebang_loop\0", &args), -ELOOP);
+    create("bad_elf\0", b"\x7fELF\x02\x01\x01 truncated header");
+    assert_eq!(exec("bad_elf\0", &args), -ENOEXEC);
+    // segments and entry points must be in user space
+    let kernel = 0xffff_ffc0_8020_0000;
+    create("kernel_elf\0", &tiny_elf(kernel, 0x1000, kernel));
+    assert_eq!(exec("kernel_elf\0", &args), -ENOEXEC);
+    create("null_elf\0", &tiny_elf(0, 0x1000, 0));
+    assert_eq!(exec("null_elf\0", &args), -ENOEXEC);
+    create("huge_elf\0", &tiny_elf(0x10000, 1 << 40, 0x10000));
+    assert_eq!(exec("huge_elf\0", &args), -ENOEXEC);
+    create("wild_entry_elf\0", &tiny_elf(0x10000, 0x1000, kernel));
+    assert_eq!(exec("wild_entry_elf\0", &args), -ENOEXEC);
+
+    println!("shebang passed!");
+    0
+}
@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+const ALT_STACK_SIZE: usize = 8192;
+static mut ALT_STACK: [u8; ALT_STACK_SIZE] = [0; ALT_STACK_SIZE];
+
+extern "C" fn on_segv(sig: i32, info: *const SignalInfo, _frame: usize) {
+    let info = unsafe { &*info };
+    assert_eq!(sig, SIGSEGV);
+    assert_eq!(info.code, SI_KERNEL);
+    let mut ss = SignalStack::default();
+    sigaltstack(None, Some(&mut ss));
+    assert!(ss.flags & SS_ONSTACK != 0);
+    println!("caught stack overflow at {:#x} on the alternate stack", info.addr);
+    exit(0);
+}
+
+#[allow(unconditional_recursion)]
+fn f(depth: usize) -> usize {
+    let buf = [depth; 16];
+    core::hint::black_box(&buf);
+    f(depth + 1) + buf[0]
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // stacks which wrap around or reach out of user space
+    let wrapping = SignalStack {
+        sp: usize::MAX - 0xfff,
+        flags: 0,
+        size: ALT_STACK_SIZE,
+    };
+    assert_eq!(sigaltstack(Some(&wrapping), None), -EINVAL);
+    let kernel = SignalStack {
+        sp: 0xffff_ffc0_0000_0000,
+        flags: 0,
+        size: ALT_STACK_SIZE,
+    };
+    assert_eq!(sigaltstack(Some(&kernel), None), -EFAULT);
+    let ss = SignalStack {
+        sp: unsafe { ALT_STACK.as_ptr() as usize },
+        flags: 0,
+        size: ALT_STACK_SIZE,
+    };
+    assert_eq!(sigaltstack(Some(&ss), None), 0);
+    let mut action = SignalAction::default();
+    action.handler = on_segv as usize;
+    action.flags = SignalActionFlags::SA_SIGINFO | SignalActionFlags::SA_ONSTACK;
+    if sigaction(SIGSEGV, Some(&action), None) < 0 {
+        panic!("Sigaction failed!");
+    }
+    println!("It should trigger segmentation fault and catch it!");
+    f(0);
+    -1
+}
@@ -0,0 +1,64 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+fn spin() -> ! {
+    loop {
+        yield_();
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut status: i32 = 0;
+
+    // SIGTERM terminates by default
+    let pid = fork();
+    if pid == 0 {
+        spin();
+    }
+    kill(pid as usize, SIGTERM);
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert!(wifsignaled(status));
+    assert_eq!(wtermsig(status), SIGTERM);
+
+    // an ignored SIGUSR1 and a default SIGCHLD/SIGWINCH do nothing
+    let pid = fork();
+    if pid == 0 {
+        let mut ignore = SignalAction::default();
+        ignore.handler = SIG_IGN;
+        sigaction(SIGUSR1, Some(&ignore), None);
+        kill(getpid() as usize, SIGUSR1);
+        kill(getpid() as usize, SIGCHLD);
+        kill(getpid() as usize, SIGWINCH);
+        yield_();
+        exit(7);
+    }
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert!(wifexited(status));
+    assert_eq!(wexitstatus(status), 7);
+
+    // SIGTSTP stops by default, SIGCONT resumes
+    let pid = fork();
+    if pid == 0 {
+        spin();
+    }
+    kill(pid as usize, SIGTSTP);
+    assert_eq!(
+        waitpid_options(pid, &mut status, WaitFlags::WUNTRACED),
+        pid
+    );
+    assert!(wifstopped(status));
+    assert_eq!(wstopsig(status), SIGTSTP);
+    kill(pid as usize, SIGCONT);
+    kill(pid as usize, SIGKILL);
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert_eq!(wtermsig(status), SIGKILL);
+
+    println!("sig_default passed!");
+    0
+}
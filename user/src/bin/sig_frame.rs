@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+static mut TRACE: [i32; 4] = [0; 4];
+static mut TRACE_LEN: usize = 0;
+
+fn record(sig: i32) {
+    unsafe {
+        TRACE[TRACE_LEN] = sig;
+        TRACE_LEN += 1;
+    }
+}
+
+extern "C" fn on_usr1(sig: i32, info: *const SignalInfo, _frame: usize) {
+    let info = unsafe { &*info };
+    assert_eq!(info.signo, sig);
+    assert_eq!(info.code, SI_USER);
+    assert_eq!(info.pid as isize, getpid());
+    record(sig);
+    // SIGUSR2 is not masked, so its handler runs on top of this one
+    assert_eq!(kill(getpid() as usize, SIGUSR2), 0);
+    record(sig);
+}
+
+extern "C" fn on_usr2(sig: i32) {
+    record(sig);
+    // return without calling sigreturn, the restorer does it
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut usr1 = SignalAction::default();
+    usr1.handler = on_usr1 as usize;
+    usr1.flags = SignalActionFlags::SA_SIGINFO | SignalActionFlags::SA_RESETHAND;
+    let mut usr2 = SignalAction::default();
+    usr2.handler = on_usr2 as usize;
+    if sigaction(SIGUSR1, Some(&usr1), None) < 0 || sigaction(SIGUSR2, Some(&usr2), None) < 0 {
+        panic!("Sigaction failed!");
+    }
+
+    // the return value of kill survives both handlers
+    assert_eq!(kill(getpid() as usize, SIGUSR1), 0);
+    unsafe {
+        assert_eq!(TRACE_LEN, 3);
+        assert_eq!(TRACE[..3], [SIGUSR1, SIGUSR2, SIGUSR1]);
+    }
+
+    // SA_RESETHAND restored the default action
+    let mut old = SignalAction::default();
+    sigaction(SIGUSR1, None, Some(&mut old));
+    assert_eq!(old.handler, 0);
+    println!("sig_frame passed!");
+    0
+}
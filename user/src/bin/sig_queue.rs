@@ -0,0 +1,61 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+static mut VALUES: [usize; 4] = [0; 4];
+static mut VALUE_COUNT: usize = 0;
+static mut USR1_COUNT: usize = 0;
+
+extern "C" fn on_rt(_sig: i32, info: *const SignalInfo, _frame: usize) {
+    let info = unsafe { &*info };
+    assert_eq!(info.code, SI_QUEUE);
+    unsafe {
+        VALUES[VALUE_COUNT] = info.value;
+        VALUE_COUNT += 1;
+    }
+}
+
+fn on_usr1() {
+    unsafe {
+        USR1_COUNT += 1;
+    }
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut rt = SignalAction::default();
+    rt.handler = on_rt as usize;
+    rt.flags = SignalActionFlags::SA_SIGINFO;
+    let mut usr1 = SignalAction::default();
+    usr1.handler = on_usr1 as usize;
+    if sigaction(SIGRTMIN, Some(&rt), None) < 0 || sigaction(SIGUSR1, Some(&usr1), None) < 0 {
+        panic!("Sigaction failed!");
+    }
+
+    // block both, then send several instances of each
+    let blocked = SignalFlags::SIGUSR1.bits() | (1 << SIGRTMIN);
+    sigprocmask(blocked, None);
+    let pid = getpid() as usize;
+    for value in 1..=3 {
+        assert_eq!(sigqueue(pid, SIGRTMIN, value), 0);
+    }
+    assert_eq!(kill(pid, SIGUSR1), 0);
+    assert_eq!(kill(pid, SIGUSR1), 0);
+    let mut old_mask = 0;
+    assert_eq!(sigprocmask(0, Some(&mut old_mask)), 0);
+    assert_eq!(old_mask, blocked);
+    yield_();
+
+    // real-time signals are all delivered in order, SIGUSR1 only once
+    unsafe {
+        assert_eq!(VALUE_COUNT, 3);
+        assert_eq!(VALUES[..3], [1, 2, 3]);
+        assert_eq!(USR1_COUNT, 1);
+    }
+    println!("sig_queue passed!");
+    0
+}
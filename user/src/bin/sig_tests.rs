@@ -73,11 +73,36 @@ fn user_sig_test_restore() {
 }
 
 fn kernel_sig_test_ignore() {
-    sigprocmask(SignalFlags::SIGSTOP.bits() as u32);
-    if kill(getpid() as usize, SignalFlags::SIGSTOP.bits()) < 0 {
+    // a blocked signal stays pending instead of killing us
+    sigprocmask(SignalFlags::SIGUSR1.bits(), None);
+    if kill(getpid() as usize, SIGUSR1) < 0 {
         println!("kill faild\n");
         exit(-1);
     }
+    // SIGKILL and SIGSTOP are left out of the mask
+    let unblockable = SignalFlags::SIGKILL | SignalFlags::SIGSTOP;
+    sigprocmask((unblockable | SignalFlags::SIGUSR1).bits(), None);
+    let mut mask = 0;
+    sigprocmask(SignalFlags::SIGUSR1.bits(), Some(&mut mask));
+    if mask != SignalFlags::SIGUSR1.bits() {
+        println!("SIGKILL or SIGSTOP was blocked\n");
+        exit(-1);
+    }
+    // and still kill a task which tried to block them
+    let pid = fork();
+    if pid == 0 {
+        sigprocmask(unblockable.bits(), None);
+        loop {
+            yield_();
+        }
+    }
+    kill(pid as usize, SIGKILL);
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if !wifsignaled(status) || wtermsig(status) != SIGKILL {
+        println!("SIGKILL was blocked\n");
+        exit(-1);
+    }
 }
 
 fn kernel_sig_test_stop_cont() {
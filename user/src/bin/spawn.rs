@@ -0,0 +1,70 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+static mut WRITTEN_BY_CHILD: usize = 0;
+
+extern "C" fn write_to_parent(value: usize) -> i32 {
+    unsafe {
+        WRITTEN_BY_CHILD = value;
+    }
+    5
+}
+
+extern "C" fn exec_hello(_arg: usize) -> i32 {
+    let args = ["hello_world\0".as_ptr(), core::ptr::null()];
+    exec("hello_world\0", &args);
+    -1
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    // the vfork child writes to the memory of the parent, which waits
+    let pid = vfork(write_to_parent, 1);
+    assert!(pid > 0);
+    assert_eq!(unsafe { WRITTEN_BY_CHILD }, 1);
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert_eq!(wexitstatus(status), 5);
+
+    // a vfork child which execs gives the memory back at once
+    let pid = vfork(exec_hello, 0);
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert_eq!(wexitstatus(status), 0);
+
+    // spawn with the output redirected to a file
+    let args = ["hello_world\0".as_ptr(), core::ptr::null()];
+    let actions = [SpawnAction::open(
+        1,
+        "spawn_out\0",
+        OpenFlags::CREATE | OpenFlags::WRONLY,
+    )];
+    let pid = posix_spawnp("hello_world\0", &args, &actions);
+    assert!(pid > 0);
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert_eq!(wexitstatus(status), 0);
+    let fd = open("spawn_out\0", OpenFlags::RDONLY);
+    assert!(fd > 0);
+    let mut buf = [0u8; 64];
+    let len = read(fd as usize, &mut buf) as usize;
+    close(fd as usize);
+    assert!(buf[..len].starts_with(b"Hello world"));
+
+    assert_eq!(posix_spawn("no_such_app\0", &args, &[]), -ENOENT);
+    // a file action which fails leaves no child behind
+    let bad = [SpawnAction::dup2(100, 1)];
+    assert!(posix_spawn("hello_world\0", &args, &bad) < 0);
+    assert!(wait(&mut status) < 0);
+    // the kernel takes up to 64 file actions
+    let pid = posix_spawn("hello_world\0", &args, &[SpawnAction::close(3); 64]);
+    assert!(pid > 0);
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    let many = [SpawnAction::close(3); 65];
+    assert_eq!(posix_spawn("hello_world\0", &args, &many), -EINVAL);
+    println!("spawn passed!");
+    0
+}
@@ -0,0 +1,40 @@
+#![no_std]
+#![no_main]
+
+extern crate alloc;
+
+#[macro_use]
+extern crate user_lib;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use user_lib::{execvp, exit, fork, strace, strerror, waitpid, wexitstatus, wifexited, wtermsig};
+
+/// Run `argv[1..]` with its syscalls printed to the kernel console, and
+/// exit with its exit code.
+#[no_mangle]
+pub fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc < 2 {
+        println!("usage: strace <program> [args...]");
+        return 1;
+    }
+    let args: Vec<String> = argv[1..].iter().map(|arg| format!("{}\0", arg)).collect();
+    let mut args_addr: Vec<*const u8> = args.iter().map(|arg| arg.as_ptr()).collect();
+    args_addr.push(core::ptr::null::<u8>());
+    let pid = fork();
+    if pid == 0 {
+        // tracing starts here, so the program is traced from its execve on
+        strace(true);
+        let err = execvp(args[0].as_str(), args_addr.as_slice());
+        println!("strace: cannot execute {}: {}", argv[1], strerror(-err));
+        exit(127);
+    }
+    let mut status = 0;
+    waitpid(pid as usize, &mut status);
+    if wifexited(status) {
+        wexitstatus(status)
+    } else {
+        128 + wtermsig(status)
+    }
+}
@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+fn system() {
+    let mut uts = UtsName::default();
+    assert_eq!(uname(&mut uts), 0);
+    let machine = if cfg!(target_arch = "riscv64") {
+        "riscv64"
+    } else if cfg!(target_arch = "aarch64") {
+        "aarch64"
+    } else if cfg!(target_arch = "x86_64") {
+        "x86_64"
+    } else {
+        "loongarch64"
+    };
+    assert_eq!(uts_str(&uts.machine), machine);
+    assert!(uts_str(&uts.version).starts_with("rCore-Tutorial-v3"));
+
+    let mut info = SysInfo::default();
+    assert_eq!(sysinfo(&mut info), 0);
+    assert_eq!(info.mem_unit, 4096);
+    assert!(info.freeram > 0 && info.freeram < info.totalram);
+    // initproc, usertests and this one at least
+    assert!(info.procs >= 2);
+    let procs = info.procs;
+    // a child counts while it lives and takes frames of its own
+    let pid = fork();
+    if pid == 0 {
+        sleep(100);
+        exit(0);
+    }
+    let mut with_child = SysInfo::default();
+    assert_eq!(sysinfo(&mut with_child), 0);
+    assert_eq!(with_child.procs, procs + 1);
+    assert!(with_child.freeram < info.freeram);
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert_eq!(sysinfo(&mut info), 0);
+    assert_eq!(info.procs, procs);
+    assert!(info.uptime >= 0);
+}
+
+fn random() {
+    let (mut a, mut b) = ([0u8; 64], [0u8; 64]);
+    assert_eq!(getrandom(&mut a, 0), 64);
+    assert_eq!(getrandom(&mut b, GRND_NONBLOCK | GRND_RANDOM), 64);
+    assert_ne!(a, b);
+    assert!(a.iter().any(|c| *c != 0));
+    // lengths which are not a multiple of the word size
+    let mut odd = [0u8; 13];
+    assert_eq!(getrandom(&mut odd, 0), 13);
+    assert_eq!(getrandom(&mut odd[..0], 0), 0);
+    assert_eq!(getrandom(&mut a, 0x100), -EINVAL);
+    let bad = unsafe { core::slice::from_raw_parts_mut(8 as *mut u8, 8) };
+    assert_eq!(getrandom(bad, 0), -EFAULT);
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    system();
+    random();
+    println!("sysinfo passed!");
+    0
+}
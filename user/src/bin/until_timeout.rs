@@ -4,7 +4,20 @@
 #[macro_use]
 extern crate user_lib;
 
-use user_lib::{exec, fork, get_time, kill, waitpid, waitpid_nb, SignalFlags};
+use user_lib::{
+    exec, fork, get_time, kill, setitimer, sigaction, waitpid, ITimerVal, SignalAction,
+    TimeVal, ITIMER_REAL, SIGALRM, SIGINT,
+};
+
+static mut CHILD_PID: usize = 0;
+static mut TIMEOUT_MS: isize = 0;
+
+fn on_alarm() {
+    unsafe {
+        println!("child has run for {}ms, kill it!", TIMEOUT_MS);
+        kill(CHILD_PID, SIGINT);
+    }
+}
 
 #[no_mangle]
 pub fn main(argc: usize, argv: &[&str]) -> i32 {
@@ -19,28 +32,29 @@ pub fn main(argc: usize, argv: &[&str]) -> i32 {
             return -4;
         }
     } else {
+        unsafe {
+            CHILD_PID = pid;
+            TIMEOUT_MS = timeout_ms;
+        }
+        let mut action = SignalAction::default();
+        action.handler = on_alarm as usize;
+        sigaction(SIGALRM, Some(&action), None);
+        let timer = ITimerVal {
+            interval: TimeVal::default(),
+            value: TimeVal::from_ms(timeout_ms as usize),
+        };
+        setitimer(ITIMER_REAL, &timer, None);
         let start_time = get_time();
-        let mut child_exited = false;
         let mut exit_code: i32 = 0;
-        loop {
-            if get_time() - start_time > timeout_ms {
-                break;
-            }
-            if waitpid_nb(pid, &mut exit_code) as usize == pid {
-                child_exited = true;
-                println!(
-                    "child exited in {}ms, exit_code = {}",
-                    get_time() - start_time,
-                    exit_code,
-                );
-            }
-        }
-        if !child_exited {
-            println!("child has run for {}ms, kill it!", timeout_ms);
-            kill(pid, SignalFlags::SIGINT.bits());
-            assert_eq!(waitpid(pid, &mut exit_code) as usize, pid);
-            println!("exit code of the child is {}", exit_code);
-        }
+        // waitpid fails with EINTR when the alarm goes off, so wait again
+        while waitpid(pid, &mut exit_code) as usize != pid {}
+        // cancel the alarm if the child was quick enough
+        setitimer(ITIMER_REAL, &ITimerVal::default(), None);
+        println!(
+            "child exited in {}ms, exit_code = {}",
+            get_time() - start_time,
+            exit_code,
+        );
     }
     0
 }
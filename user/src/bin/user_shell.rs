@@ -16,7 +16,9 @@ const LINE_START: &str = ">> ";
 use alloc::string::String;
 use alloc::vec::Vec;
 use user_lib::console::getchar;
-use user_lib::{close, dup, exec, fork, open, pipe, waitpid, OpenFlags};
+use user_lib::{
+    close, getenv, pipe, posix_spawnp, setenv, strerror, waitpid, OpenFlags, SpawnAction,
+};
 
 #[derive(Debug)]
 struct ProcessArguments {
@@ -33,8 +35,11 @@ impl ProcessArguments {
             .iter()
             .filter(|&arg| !arg.is_empty())
             .map(|&arg| {
-                let mut string = String::new();
-                string.push_str(arg);
+                // expand $NAME to the value of an environment variable
+                let mut string = match arg.strip_prefix('$') {
+                    Some(name) => getenv(name).unwrap_or_default(),
+                    None => String::from(arg),
+                };
                 string.push('\0');
                 string
             })
@@ -84,6 +89,14 @@ pub fn main() -> i32 {
         match c {
             LF | CR => {
                 println!("");
+                if let Some(assignment) = line.strip_prefix("export ") {
+                    // export NAME=VALUE to every command run from now on
+                    match assignment.trim().split_once('=') {
+                        Some((name, value)) if !name.is_empty() => setenv(name, value),
+                        _ => println!("Usage: export NAME=VALUE"),
+                    }
+                    line.clear();
+                }
                 if !line.is_empty() {
                     let splited: Vec<_> = line.as_str().split('|').collect();
                     let process_arguments_list: Vec<_> = splited
@@ -122,62 +135,45 @@ pub fn main() -> i32 {
                         }
                         let mut children: Vec<_> = Vec::new();
                         for (i, process_argument) in process_arguments_list.iter().enumerate() {
-                            let pid = fork();
-                            if pid == 0 {
-                                let input = &process_argument.input;
-                                let output = &process_argument.output;
-                                let args_copy = &process_argument.args_copy;
-                                let args_addr = &process_argument.args_addr;
-                                // redirect input
-                                if !input.is_empty() {
-                                    let input_fd = open(input.as_str(), OpenFlags::RDONLY);
-                                    if input_fd == -1 {
-                                        println!("Error when opening file {}", input);
-                                        return -4;
-                                    }
-                                    let input_fd = input_fd as usize;
-                                    close(0);
-                                    assert_eq!(dup(input_fd), 0);
-                                    close(input_fd);
-                                }
-                                // redirect output
-                                if !output.is_empty() {
-                                    let output_fd = open(
-                                        output.as_str(),
-                                        OpenFlags::CREATE | OpenFlags::WRONLY,
-                                    );
-                                    if output_fd == -1 {
-                                        println!("Error when opening file {}", output);
-                                        return -4;
-                                    }
-                                    let output_fd = output_fd as usize;
-                                    close(1);
-                                    assert_eq!(dup(output_fd), 1);
-                                    close(output_fd);
-                                }
-                                // receive input from the previous process
-                                if i > 0 {
-                                    close(0);
-                                    let read_end = pipes_fd.get(i - 1).unwrap()[0];
-                                    assert_eq!(dup(read_end), 0);
-                                }
-                                // send output to the next process
-                                if i < process_arguments_list.len() - 1 {
-                                    close(1);
-                                    let write_end = pipes_fd.get(i).unwrap()[1];
-                                    assert_eq!(dup(write_end), 1);
-                                }
-                                // close all pipe ends inherited from the parent process
-                                for pipe_fd in pipes_fd.iter() {
-                                    close(pipe_fd[0]);
-                                    close(pipe_fd[1]);
-                                }
-                                // execute new application
-                                if exec(args_copy[0].as_str(), args_addr.as_slice()) == -1 {
-                                    println!("Error when executing!");
-                                    return -4;
-                                }
-                                unreachable!();
+                            let input = &process_argument.input;
+                            let output = &process_argument.output;
+                            let args_copy = &process_argument.args_copy;
+                            let args_addr = &process_argument.args_addr;
+                            let mut actions = Vec::new();
+                            // redirect input
+                            if !input.is_empty() {
+                                actions.push(SpawnAction::open(0, input, OpenFlags::RDONLY));
+                            }
+                            // redirect output
+                            if !output.is_empty() {
+                                actions.push(SpawnAction::open(
+                                    1,
+                                    output,
+                                    OpenFlags::CREATE | OpenFlags::WRONLY,
+                                ));
+                            }
+                            // receive input from the previous process
+                            if i > 0 {
+                                actions.push(SpawnAction::dup2(pipes_fd[i - 1][0], 0));
+                            }
+                            // send output to the next process
+                            if i < process_arguments_list.len() - 1 {
+                                actions.push(SpawnAction::dup2(pipes_fd[i][1], 1));
+                            }
+                            // close all pipe ends inherited from the parent process
+                            for pipe_fd in pipes_fd.iter() {
+                                actions.push(SpawnAction::close(pipe_fd[0]));
+                                actions.push(SpawnAction::close(pipe_fd[1]));
+                            }
+                            // execute new application
+                            let pid = posix_spawnp(
+                                args_copy[0].as_str(),
+                                args_addr.as_slice(),
+                                actions.as_slice(),
+                            );
+                            if pid < 0 {
+                                let name = args_copy[0].trim_end_matches('\0');
+                                println!("Error when executing {}: {}", name, strerror(-pid));
                             } else {
                                 children.push(pid);
                             }
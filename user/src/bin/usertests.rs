@@ -7,12 +7,13 @@ extern crate user_lib;
 extern crate alloc;
 
 // not in SUCC_TESTS & FAIL_TESTS
-// count_lines, infloop, user_shell, usertests
+// count_lines, dbg, infloop, user_shell, usertests
 
 // item of TESTS : app_name(argv_0), argv_1, argv_2, argv_3, exit_code
 static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
     ("filetest_simple\0", "\0", "\0", "\0", 0),
     ("cat\0", "filea\0", "\0", "\0", 0),
+    ("filetest_simple\0", "clean\0", "\0", "\0", 0),
     ("cmdline_args\0", "1\0", "2\0", "\0", 0),
     ("exit\0", "\0", "\0", "\0", 0),
     ("fantastic_text\0", "\0", "\0", "\0", 0),
@@ -31,6 +32,31 @@ static SUCC_TESTS: &[(&str, &str, &str, &str, i32)] = &[
     ("sig_simple\0", "\0", "\0", "\0", 0),
     ("sig_simple2\0", "\0", "\0", "\0", 0),
     ("sig_tests\0", "\0", "\0", "\0", 0),
+    ("sig_frame\0", "\0", "\0", "\0", 0),
+    ("sig_default\0", "\0", "\0", "\0", 0),
+    ("sig_queue\0", "\0", "\0", "\0", 0),
+    ("sig_altstack\0", "\0", "\0", "\0", 0),
+    ("itimer\0", "\0", "\0", "\0", 0),
+    ("cpu_time\0", "\0", "\0", "\0", 0),
+    ("rlimit\0", "\0", "\0", "\0", 0),
+    ("creds\0", "\0", "\0", "\0", 0),
+    ("execve_env\0", "\0", "\0", "\0", 0),
+    ("shebang\0", "\0", "\0", "\0", 0),
+    ("spawn\0", "\0", "\0", "\0", 0),
+    ("clone\0", "\0", "\0", "\0", 0),
+    ("ptrace\0", "\0", "\0", "\0", 0),
+    ("coredump\0", "\0", "\0", "\0", 0),
+    ("seccomp\0", "\0", "\0", "\0", 0),
+    ("errno\0", "\0", "\0", "\0", 0),
+    ("linux_abi\0", "\0", "\0", "\0", 0),
+    ("strace\0", "hello_world\0", "\0", "\0", 0),
+    ("vdso\0", "\0", "\0", "\0", 0),
+    ("sysinfo\0", "\0", "\0", "\0", 0),
+    ("random\0", "\0", "\0", "\0", 0),
+    ("dirs\0", "\0", "\0", "\0", 0),
+    ("mount\0", "\0", "\0", "\0", 0),
+    ("links\0", "\0", "\0", "\0", 0),
+    ("wait_status\0", "\0", "\0", "\0", 0),
     ("yield\0", "\0", "\0", "\0", 0),
 ];
 
@@ -40,7 +66,7 @@ static FAIL_TESTS: &[(&str, &str, &str, &str, i32)] = &[
 ];
 
 use alloc::{string::ToString, vec::Vec};
-use user_lib::{exec, fork, waitpid};
+use user_lib::{posix_spawn, waitpid, wexitstatus, wifexited, wtermsig};
 
 fn run_tests(tests: &[(&str, &str, &str, &str, i32)]) -> i32 {
     let mut pass_num = 0;
@@ -78,14 +104,20 @@ fn run_tests(tests: &[(&str, &str, &str, &str, i32)]) -> i32 {
             arr[3] = core::ptr::null::<u8>();
         }
 
-        let pid = fork();
-        if pid == 0 {
-            exec(test.0, &arr[..]);
-            panic!("unreachable!");
+        let pid = posix_spawn(test.0, &arr[..], &[]);
+        if pid < 0 {
+            println!("Usertests: Failed to start {}", test.0);
+            failed.push(test.0.to_string());
         } else {
             let mut exit_code: i32 = Default::default();
             let wait_pid = waitpid(pid as usize, &mut exit_code);
             assert_eq!(pid, wait_pid);
+            // exit(-1) is reported as -1, a kill by SIGSEGV as -11
+            exit_code = if wifexited(exit_code) {
+                wexitstatus(exit_code) as i8 as i32
+            } else {
+                -wtermsig(exit_code)
+            };
             if exit_code == test.4 {
                 // summary apps with  exit_code
                 pass_num = pass_num + 1;
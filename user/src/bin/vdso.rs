@@ -0,0 +1,45 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+#[no_mangle]
+pub fn main() -> i32 {
+    assert_eq!(getauxval(AT_PAGESZ), 4096);
+    assert_eq!(getauxval(0x7fff), 0);
+    let vdso = getauxval(AT_SYSINFO_EHDR);
+    assert_ne!(vdso, 0);
+
+    // the time read from the vDSO agrees with the kernel clock
+    let (mut before, mut after) = (TimeSpec::default(), TimeSpec::default());
+    for _ in 0..100 {
+        assert_eq!(clock_gettime(CLOCK_MONOTONIC, &mut before), 0);
+        let now = get_time() as usize;
+        assert_eq!(clock_gettime(CLOCK_MONOTONIC, &mut after), 0);
+        // both round down to milliseconds on their own
+        assert!(before.to_ms() <= now + 1 && now <= after.to_ms() + 1);
+    }
+    // and it goes on across timer interrupts without going back
+    let start = get_time();
+    let mut last = start;
+    while last < start + 50 {
+        let now = get_time();
+        assert!(now >= last);
+        last = now;
+    }
+
+    // the page is read-only
+    let pid = fork();
+    if pid == 0 {
+        unsafe { (vdso as *mut u8).write_volatile(0) };
+        exit(0);
+    }
+    let mut status = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert!(!wifexited(status) && wtermsig(status) == SIGSEGV);
+    println!("vdso passed!");
+    0
+}
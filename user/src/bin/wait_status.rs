@@ -0,0 +1,76 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::*;
+
+static mut SIGCHLD_COUNT: usize = 0;
+
+fn on_sigchld() {
+    unsafe {
+        SIGCHLD_COUNT += 1;
+    }
+    sigreturn();
+}
+
+#[no_mangle]
+pub fn main() -> i32 {
+    let mut new = SignalAction::default();
+    new.handler = on_sigchld as usize;
+    if sigaction(SIGCHLD, Some(&new), None) < 0 {
+        panic!("Sigaction failed!");
+    }
+
+    // normal exit
+    let pid = fork();
+    if pid == 0 {
+        exit(3);
+    }
+    let mut status: i32 = 0;
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert!(wifexited(status) && !wifsignaled(status));
+    assert_eq!(wexitstatus(status), 3);
+
+    // killed by a signal, and WNOHANG while it is still running
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            yield_();
+        }
+    }
+    assert_eq!(waitpid_nb(pid as usize, &mut status), 0);
+    assert_eq!(kill(pid as usize, SIGKILL), 0);
+    assert_eq!(waitpid(pid as usize, &mut status), pid);
+    assert!(wifsignaled(status) && !wifexited(status));
+    assert_eq!(wtermsig(status), SIGKILL);
+
+    // stopped and continued children are reported only when asked
+    let pid = fork();
+    if pid == 0 {
+        loop {
+            yield_();
+        }
+    }
+    assert_eq!(kill(pid as usize, SIGSTOP), 0);
+    assert_eq!(
+        waitpid_options(pid, &mut status, WaitFlags::WUNTRACED),
+        pid
+    );
+    assert!(wifstopped(status));
+    assert_eq!(wstopsig(status), SIGSTOP);
+    assert_eq!(kill(pid as usize, SIGCONT), 0);
+    assert_eq!(
+        waitpid_options(pid, &mut status, WaitFlags::WCONTINUED),
+        pid
+    );
+    assert!(wifcontinued(status));
+    kill(pid as usize, SIGKILL);
+    waitpid(pid as usize, &mut status);
+
+    yield_();
+    assert!(unsafe { SIGCHLD_COUNT } > 0);
+    println!("wait_status passed!");
+    0
+}
@@ -8,14 +8,28 @@
 pub mod console;
 mod lang_items;
 mod syscall;
+mod vdso;
 
 extern crate alloc;
 #[macro_use]
 extern crate bitflags;
 
+use alloc::string::String;
 use alloc::vec::Vec;
 use buddy_system_allocator::LockedHeap;
 use syscall::*;
+// syscall numbers, to name syscalls in seccomp filters
+pub use syscall::{
+    SYSCALL_BRK, SYSCALL_CLOCK_GETTIME, SYSCALL_CLONE, SYSCALL_CLOSE, SYSCALL_DUP, SYSCALL_EXECVE,
+    SYSCALL_EXIT, SYSCALL_FSTAT, SYSCALL_GETCWD, SYSCALL_GETEGID, SYSCALL_GETEUID, SYSCALL_GETGID,
+    SYSCALL_GETITIMER, SYSCALL_GETPID, SYSCALL_GETPPID, SYSCALL_GETRLIMIT, SYSCALL_GETRUSAGE,
+    SYSCALL_GETTID, SYSCALL_GETUID, SYSCALL_GET_TIME, SYSCALL_IOCTL, SYSCALL_KILL, SYSCALL_MMAP,
+    SYSCALL_MUNMAP, SYSCALL_OPEN, SYSCALL_PIPE, SYSCALL_PTRACE, SYSCALL_READ, SYSCALL_SECCOMP,
+    SYSCALL_SETGID, SYSCALL_SETITIMER, SYSCALL_SETRLIMIT, SYSCALL_SETUID, SYSCALL_SIGACTION,
+    SYSCALL_SIGALTSTACK, SYSCALL_SIGPROCMASK, SYSCALL_SIGQUEUE, SYSCALL_SIGRETURN, SYSCALL_SPAWN,
+    SYSCALL_TIMES, SYSCALL_UNAME, SYSCALL_VFORK, SYSCALL_WAITPID, SYSCALL_WRITE, SYSCALL_WRITEV,
+    SYSCALL_YIELD,
+};
 
 const USER_HEAP_SIZE: usize = 32768;
 
@@ -29,26 +43,97 @@ pub fn handle_alloc_error(layout: core::alloc::Layout) -> ! {
     panic!("Heap allocation error, layout = {:?}", layout);
 }
 
+/// Environment of the process, each entry is "NAME=VALUE\0"
+static mut ENVIRON: Vec<String> = Vec::new();
+
+/// The auxiliary vector from the kernel, (key, value) pairs ended by AT_NULL
+static mut AUXV: usize = 0;
+/// Address of the vDSO page, 0 if there is none
+static mut VDSO: usize = 0;
+
+// keys of the auxiliary vector, same values as Linux
+pub const AT_NULL: usize = 0;
+pub const AT_PAGESZ: usize = 6;
+pub const AT_SYSINFO_EHDR: usize = 33;
+
+/// The value of `key` in the auxiliary vector, 0 if the kernel did not
+/// pass it.
+pub fn getauxval(key: usize) -> usize {
+    let mut entry = unsafe { AUXV };
+    loop {
+        let (k, value) = unsafe {
+            (
+                (entry as *const usize).read_volatile(),
+                (entry as *const usize).add(1).read_volatile(),
+            )
+        };
+        match k {
+            AT_NULL => return 0,
+            k if k == key => return value,
+            _ => entry += 2 * core::mem::size_of::<usize>(),
+        }
+    }
+}
+
+/// The null-terminated string which `ptrs[i]` points to.
+fn c_str_at(ptrs: usize, i: usize) -> &'static str {
+    let str_start =
+        unsafe { ((ptrs + i * core::mem::size_of::<usize>()) as *const usize).read_volatile() };
+    let len = (0usize..)
+        .find(|i| unsafe { ((str_start + *i) as *const u8).read_volatile() == 0 })
+        .unwrap();
+    core::str::from_utf8(unsafe { core::slice::from_raw_parts(str_start as *const u8, len) })
+        .unwrap()
+}
+
+/// ELF note which tells the kernel that a program uses this syscall ABI,
+/// it runs any other ELF file as a Linux program
+#[repr(C, align(4))]
+struct AbiNote {
+    namesz: u32,
+    descsz: u32,
+    note_type: u32,
+    name: [u8; 8],
+    version: u32,
+}
+
+#[used]
+#[link_section = ".note.rcore.abi"]
+static ABI_NOTE: AbiNote = AbiNote {
+    namesz: 6,
+    descsz: 4,
+    note_type: 1,
+    name: *b"rcore\0\0\0",
+    version: 1,
+};
+
 #[no_mangle]
 #[link_section = ".text.entry"]
-pub extern "C" fn _start(argc: usize, argv: usize) -> ! {
+pub extern "C" fn _start(argc: usize, argv: usize, envp: usize) -> ! {
     unsafe {
         HEAP.lock()
             .init(HEAP_SPACE.as_ptr() as usize, USER_HEAP_SIZE);
     }
     let mut v: Vec<&'static str> = Vec::new();
     for i in 0..argc {
-        let str_start =
-            unsafe { ((argv + i * core::mem::size_of::<usize>()) as *const usize).read_volatile() };
-        let len = (0usize..)
-            .find(|i| unsafe { ((str_start + *i) as *const u8).read_volatile() == 0 })
-            .unwrap();
-        v.push(
-            core::str::from_utf8(unsafe {
-                core::slice::from_raw_parts(str_start as *const u8, len)
-            })
-            .unwrap(),
-        );
+        v.push(c_str_at(argv, i));
+    }
+    // envp ends with a null pointer
+    let mut i = 0;
+    while unsafe { ((envp + i * core::mem::size_of::<usize>()) as *const usize).read_volatile() }
+        != 0
+    {
+        let mut env = String::from(c_str_at(envp, i));
+        env.push('\0');
+        unsafe {
+            ENVIRON.push(env);
+        }
+        i += 1;
+    }
+    // the auxiliary vector follows the null pointer at the end of envp
+    unsafe {
+        AUXV = envp + (i + 1) * core::mem::size_of::<usize>();
+        VDSO = getauxval(AT_SYSINFO_EHDR);
     }
     exit(main(argc, v.as_slice()));
 }
@@ -90,62 +175,784 @@ pub fn write(fd: usize, buf: &[u8]) -> isize {
 pub fn exit(exit_code: i32) -> ! {
     sys_exit(exit_code);
 }
+
+/// A buffer of `writev`
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct IoVec {
+    pub base: *const u8,
+    pub len: usize,
+}
+
+impl IoVec {
+    pub fn new(buf: &[u8]) -> Self {
+        Self {
+            base: buf.as_ptr(),
+            len: buf.len(),
+        }
+    }
+}
+
+/// Write the buffers of `iov` in turn, return the total written.
+pub fn writev(fd: usize, iov: &[IoVec]) -> isize {
+    sys_writev(fd, iov)
+}
+
+// file types in `Stat::mode`
+pub const S_IFMT: u32 = 0o170000;
+pub const S_IFIFO: u32 = 0o010000;
+pub const S_IFCHR: u32 = 0o020000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+
+/// Result of `fstat`, same layout as Linux `struct stat`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u64,
+    pub __pad: u64,
+    pub size: i64,
+    pub blksize: i32,
+    pub __pad2: i32,
+    pub blocks: i64,
+    pub atime: [i64; 2],
+    pub mtime: [i64; 2],
+    pub ctime: [i64; 2],
+    pub __unused: [u32; 2],
+}
+
+pub fn fstat(fd: usize, stat: &mut Stat) -> isize {
+    sys_fstat(fd, stat)
+}
+
+/// Window size of a terminal
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WinSize {
+    pub row: u16,
+    pub col: u16,
+    pub xpixel: u16,
+    pub ypixel: u16,
+}
+
+pub const TIOCGWINSZ: usize = 0x5413;
+
+/// Whether `fd` is a terminal.
+pub fn isatty(fd: usize) -> bool {
+    let mut size = WinSize::default();
+    sys_ioctl(fd, TIOCGWINSZ, &mut size as *mut _ as usize) == 0
+}
+
+/// Write the current directory to `buf`, null-terminated. Return its
+/// length with the null byte.
+pub fn getcwd(buf: &mut [u8]) -> isize {
+    sys_getcwd(buf)
+}
+
+/// `dirfd` of the *at syscalls for the current directory
+pub const AT_FDCWD: isize = -100;
+
+/// Make the directory `path`, relative to the directory open as `dirfd`.
+pub fn mkdirat(dirfd: isize, path: &str) -> isize {
+    sys_mkdirat(dirfd, path, 0o755)
+}
+pub fn mkdir(path: &str) -> isize {
+    mkdirat(AT_FDCWD, path)
+}
+pub fn chdir(path: &str) -> isize {
+    sys_chdir(path)
+}
+/// `unlinkat` removes an empty directory instead of a file
+pub const AT_REMOVEDIR: u32 = 0x200;
+/// `renameat2` fails with EEXIST instead of replacing the new path
+pub const RENAME_NOREPLACE: u32 = 1;
+pub fn unlinkat(dirfd: isize, path: &str, flags: u32) -> isize {
+    sys_unlinkat(dirfd, path, flags)
+}
+pub fn unlink(path: &str) -> isize {
+    unlinkat(AT_FDCWD, path, 0)
+}
+pub fn rmdir(path: &str) -> isize {
+    sys_rmdir(path)
+}
+/// Give the file `old_path` the name `new_path` too.
+pub fn link(old_path: &str, new_path: &str) -> isize {
+    sys_linkat(AT_FDCWD, old_path, AT_FDCWD, new_path, 0)
+}
+pub fn renameat2(
+    old_dirfd: isize,
+    old_path: &str,
+    new_dirfd: isize,
+    new_path: &str,
+    flags: u32,
+) -> isize {
+    sys_renameat2(old_dirfd, old_path, new_dirfd, new_path, flags)
+}
+pub fn rename(old_path: &str, new_path: &str) -> isize {
+    renameat2(AT_FDCWD, old_path, AT_FDCWD, new_path, 0)
+}
+/// Mount a new file system of type `fs_type`, such as "tmpfs\0", on the
+/// directory `target`.
+pub fn mount(target: &str, fs_type: &str) -> isize {
+    sys_mount("none\0", target, fs_type, 0)
+}
+pub fn umount(target: &str) -> isize {
+    sys_umount2(target, 0)
+}
+
+/// Names of the system, each null-terminated
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct UtsName {
+    pub sysname: [u8; 65],
+    pub nodename: [u8; 65],
+    pub release: [u8; 65],
+    pub version: [u8; 65],
+    pub machine: [u8; 65],
+    pub domainname: [u8; 65],
+}
+
+impl Default for UtsName {
+    fn default() -> Self {
+        Self {
+            sysname: [0; 65],
+            nodename: [0; 65],
+            release: [0; 65],
+            version: [0; 65],
+            machine: [0; 65],
+            domainname: [0; 65],
+        }
+    }
+}
+
+/// The part of a utsname field before its null byte.
+pub fn uts_str(field: &[u8; 65]) -> &str {
+    let len = field.iter().position(|c| *c == 0).unwrap_or(field.len());
+    core::str::from_utf8(&field[..len]).unwrap()
+}
+
+pub fn uname(buf: &mut UtsName) -> isize {
+    sys_uname(buf)
+}
+
+/// Uptime, memory and process count of the system
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SysInfo {
+    /// seconds since boot
+    pub uptime: isize,
+    pub loads: [usize; 3],
+    /// memory in units of `mem_unit` bytes
+    pub totalram: usize,
+    pub freeram: usize,
+    pub sharedram: usize,
+    pub bufferram: usize,
+    pub totalswap: usize,
+    pub freeswap: usize,
+    pub procs: u16,
+    pub pad: u16,
+    pub totalhigh: usize,
+    pub freehigh: usize,
+    pub mem_unit: u32,
+}
+
+pub fn sysinfo(info: &mut SysInfo) -> isize {
+    sys_sysinfo(info)
+}
+
+// getrandom flags
+pub const GRND_NONBLOCK: u32 = 1;
+pub const GRND_RANDOM: u32 = 2;
+pub const GRND_INSECURE: u32 = 4;
+
+/// Fill `buf` with random bytes. Return how many were written.
+pub fn getrandom(buf: &mut [u8], flags: u32) -> isize {
+    sys_getrandom(buf, flags)
+}
+
+/// Move the end of the heap, 0 just asks for it. Return the new end, which
+/// is the old one if it could not be moved.
+pub fn brk(addr: usize) -> usize {
+    sys_brk(addr) as usize
+}
+
+// mmap protection and flags
+pub const PROT_READ: usize = 1;
+pub const PROT_WRITE: usize = 2;
+pub const PROT_EXEC: usize = 4;
+pub const MAP_SHARED: usize = 1;
+pub const MAP_PRIVATE: usize = 2;
+pub const MAP_FIXED: usize = 0x10;
+pub const MAP_ANONYMOUS: usize = 0x20;
+
+/// Map `len` bytes of zeroed memory, only anonymous private mappings are
+/// supported. Return the address, or -errno.
+pub fn mmap(addr: usize, len: usize, prot: usize, flags: usize) -> isize {
+    sys_mmap(addr, len, prot, flags)
+}
+pub fn munmap(addr: usize, len: usize) -> isize {
+    sys_munmap(addr, len)
+}
 pub fn yield_() -> isize {
     sys_yield()
 }
+/// Milliseconds since boot, read from the vDSO without a syscall if the
+/// kernel mapped one.
 pub fn get_time() -> isize {
-    sys_get_time()
+    match unsafe { VDSO } {
+        0 => sys_get_time(),
+        base => (vdso::time_us(base) / 1000) as isize,
+    }
 }
 pub fn getpid() -> isize {
     sys_getpid()
 }
+pub fn gettid() -> isize {
+    sys_gettid()
+}
+pub fn getppid() -> isize {
+    sys_getppid()
+}
+pub fn getuid() -> isize {
+    sys_getuid()
+}
+pub fn geteuid() -> isize {
+    sys_geteuid()
+}
+pub fn getgid() -> isize {
+    sys_getgid()
+}
+pub fn getegid() -> isize {
+    sys_getegid()
+}
+pub fn setuid(uid: usize) -> isize {
+    sys_setuid(uid)
+}
+pub fn setgid(gid: usize) -> isize {
+    sys_setgid(gid)
+}
 pub fn fork() -> isize {
     sys_fork()
 }
+/// Like `fork`, but the child runs `f(arg)` in the memory of the parent,
+/// which is suspended until the child calls `exec` or exits. The child
+/// exits with the return value of `f`. Return the pid of the child.
+pub fn vfork(f: extern "C" fn(usize) -> i32, arg: usize) -> isize {
+    sys_vfork(f, arg)
+}
+
+bitflags! {
+    pub struct CloneFlags: usize {
+        const CLONE_VM = 0x100;
+        const CLONE_FS = 0x200;
+        const CLONE_FILES = 0x400;
+        const CLONE_SIGHAND = 0x800;
+        const CLONE_VFORK = 0x4000;
+        const CLONE_THREAD = 0x10000;
+        const CLONE_SETTLS = 0x80000;
+        const CLONE_PARENT_SETTID = 0x100000;
+        const CLONE_CHILD_CLEARTID = 0x200000;
+    }
+}
+
+/// Start a child which runs `f(arg)` on the stack whose top is `stack` and
+/// exits with its return value. `flags` are `CloneFlags` ored with the
+/// signal sent to the parent when the child exits. The other arguments are
+/// only used with CLONE_PARENT_SETTID, CLONE_SETTLS and CLONE_CHILD_CLEARTID.
+/// Return the tid of the child.
+pub fn clone(
+    f: extern "C" fn(usize) -> i32,
+    stack: usize,
+    flags: usize,
+    arg: usize,
+    ptid: *mut u32,
+    tls: usize,
+    ctid: *mut u32,
+) -> isize {
+    sys_clone(f, stack, flags, arg, ptid, tls, ctid)
+}
+pub const EPERM: isize = 1;
+pub const ENOENT: isize = 2;
+pub const ESRCH: isize = 3;
+pub const EINTR: isize = 4;
+pub const EIO: isize = 5;
+pub const E2BIG: isize = 7;
+pub const ENOEXEC: isize = 8;
+pub const EBADF: isize = 9;
+pub const ECHILD: isize = 10;
+pub const EAGAIN: isize = 11;
+pub const ENOMEM: isize = 12;
+pub const EFAULT: isize = 14;
+pub const EBUSY: isize = 16;
+pub const EEXIST: isize = 17;
+pub const EXDEV: isize = 18;
+pub const ENODEV: isize = 19;
+pub const ENOTDIR: isize = 20;
+pub const EISDIR: isize = 21;
+pub const EINVAL: isize = 22;
+pub const EMFILE: isize = 24;
+pub const ENOTTY: isize = 25;
+pub const ENOSPC: isize = 28;
+pub const ERANGE: isize = 34;
+pub const ENAMETOOLONG: isize = 36;
+pub const ENOSYS: isize = 38;
+pub const ENOTEMPTY: isize = 39;
+pub const ELOOP: isize = 40;
+/// Return values in `[-MAX_ERRNO, -1]` are errors, like on Linux
+const MAX_ERRNO: isize = 4095;
+
+/// Split the return value of a syscall into its result or the errno it
+/// failed with.
+pub fn syscall_result(ret: isize) -> Result<usize, isize> {
+    if (-MAX_ERRNO..0).contains(&ret) {
+        Err(-ret)
+    } else {
+        Ok(ret as usize)
+    }
+}
+/// The errno a syscall failed with, or None if it succeeded.
+pub fn errno(ret: isize) -> Option<isize> {
+    syscall_result(ret).err()
+}
+/// A short description of `errno`.
+pub fn strerror(errno: isize) -> &'static str {
+    match errno {
+        EPERM => "Operation not permitted",
+        ENOENT => "No such file or directory",
+        ESRCH => "No such process",
+        EINTR => "Interrupted system call",
+        EIO => "I/O error",
+        E2BIG => "Argument list too long",
+        ENOEXEC => "Exec format error",
+        EBADF => "Bad file descriptor",
+        ECHILD => "No child processes",
+        EAGAIN => "Resource temporarily unavailable",
+        ENOMEM => "Out of memory",
+        EFAULT => "Bad address",
+        EBUSY => "Device or resource busy",
+        EEXIST => "File exists",
+        EXDEV => "Invalid cross-device link",
+        ENODEV => "No such device",
+        ENOTDIR => "Not a directory",
+        EISDIR => "Is a directory",
+        EINVAL => "Invalid argument",
+        EMFILE => "Too many open files",
+        ENOTTY => "Not a tty",
+        ENOSPC => "No space left on device",
+        ERANGE => "Result not representable",
+        ENAMETOOLONG => "File name too long",
+        ENOSYS => "Function not implemented",
+        ENOTEMPTY => "Directory not empty",
+        ELOOP => "Too many levels of symbolic links",
+        _ => "Unknown error",
+    }
+}
+/// Make syscall `id` with up to six arguments, for ids user_lib has no
+/// wrapper for.
+pub fn syscall_raw(id: usize, args: [usize; 6]) -> isize {
+    sys_raw(id, args)
+}
+
+/// Run `path` with the environment of the current process.
 pub fn exec(path: &str, args: &[*const u8]) -> isize {
-    sys_exec(path, args)
+    sys_execve(path, args, environ().as_slice())
 }
-pub fn wait(exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(-1, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
+/// Run `path` with the environment `envs`, "NAME=VALUE\0" strings ended
+/// by a null pointer like `args`.
+pub fn execve(path: &str, args: &[*const u8], envs: &[*const u8]) -> isize {
+    sys_execve(path, args, envs)
+}
+/// Like `exec`, but a `file` without '/' is searched in the directories
+/// listed in PATH, or in "/" if PATH is not set.
+pub fn execvp(file: &str, args: &[*const u8]) -> isize {
+    search_path(file, |path| exec(path, args))
+}
+/// Call `run` with `file` in each directory of PATH until it finds one.
+fn search_path(file: &str, mut run: impl FnMut(&str) -> isize) -> isize {
+    let name = file.trim_end_matches('\0');
+    if name.contains('/') {
+        return run(file);
+    }
+    let path = getenv("PATH").unwrap_or_else(|| String::from("/"));
+    let mut ret = -ENOENT;
+    for dir in path.split(':').filter(|dir| !dir.is_empty()) {
+        let mut candidate = String::from(dir.trim_end_matches('/'));
+        candidate.push('/');
+        candidate.push_str(name);
+        candidate.push('\0');
+        ret = run(candidate.as_str());
+        // a file which exists but cannot run ends the search
+        if ret != -ENOENT {
+            break;
         }
     }
+    ret
 }
 
-pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
-    loop {
-        match sys_waitpid(pid as isize, exit_code as *mut _) {
-            -2 => {
-                yield_();
-            }
-            // -1 or a real pid
-            exit_pid => return exit_pid,
+pub const SPAWN_OPEN: usize = 0;
+pub const SPAWN_CLOSE: usize = 1;
+pub const SPAWN_DUP2: usize = 2;
+
+/// A change to the fd table of a spawned child
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SpawnAction {
+    pub op: usize,
+    pub fd: usize,
+    pub arg: usize,
+    pub path: *const u8,
+}
+
+impl SpawnAction {
+    /// Open `path`, which ends with '\0', as `fd` of the child.
+    pub fn open(fd: usize, path: &str, flags: OpenFlags) -> Self {
+        Self {
+            op: SPAWN_OPEN,
+            fd,
+            arg: flags.bits() as usize,
+            path: path.as_ptr(),
+        }
+    }
+    pub fn close(fd: usize) -> Self {
+        Self {
+            op: SPAWN_CLOSE,
+            fd,
+            arg: 0,
+            path: core::ptr::null(),
         }
     }
+    /// Make `newfd` of the child a copy of its `fd`.
+    pub fn dup2(fd: usize, newfd: usize) -> Self {
+        Self {
+            op: SPAWN_DUP2,
+            fd,
+            arg: newfd,
+            path: core::ptr::null(),
+        }
+    }
+}
+
+#[repr(C)]
+pub struct SpawnAttr {
+    pub envs: *const *const u8,
+    pub actions: *const SpawnAction,
+    pub actions_len: usize,
+}
+
+/// Start `path` in a new child process which has the environment of this
+/// one and its fds changed by `actions`. Return the pid of the child.
+pub fn posix_spawn(path: &str, args: &[*const u8], actions: &[SpawnAction]) -> isize {
+    let envs = environ();
+    let attr = SpawnAttr {
+        envs: envs.as_ptr(),
+        actions: actions.as_ptr(),
+        actions_len: actions.len(),
+    };
+    sys_spawn(path, args, &attr)
+}
+/// Like `posix_spawn`, but search PATH like `execvp`.
+pub fn posix_spawnp(file: &str, args: &[*const u8], actions: &[SpawnAction]) -> isize {
+    search_path(file, |path| posix_spawn(path, args, actions))
 }
 
+/// Pointers to the "NAME=VALUE\0" entries of the environment, ended by a
+/// null pointer. They are valid until the environment is changed.
+pub fn environ() -> Vec<*const u8> {
+    let mut envs: Vec<*const u8> = unsafe { ENVIRON.iter().map(|env| env.as_ptr()).collect() };
+    envs.push(core::ptr::null());
+    envs
+}
+fn find_env(name: &str) -> Option<usize> {
+    unsafe {
+        ENVIRON.iter().position(|env| {
+            env.strip_prefix(name)
+                .map_or(false, |rest| rest.starts_with('='))
+        })
+    }
+}
+pub fn getenv(name: &str) -> Option<String> {
+    find_env(name).map(|i| unsafe {
+        String::from(ENVIRON[i][name.len() + 1..].trim_end_matches('\0'))
+    })
+}
+/// Set `name` to `value`, replacing any old value.
+pub fn setenv(name: &str, value: &str) {
+    let mut env = String::from(name);
+    env.push('=');
+    env.push_str(value);
+    env.push('\0');
+    unsafe {
+        match find_env(name) {
+            Some(i) => ENVIRON[i] = env,
+            None => ENVIRON.push(env),
+        }
+    }
+}
+pub fn unsetenv(name: &str) {
+    if let Some(i) = find_env(name) {
+        unsafe {
+            ENVIRON.remove(i);
+        }
+    }
+}
+bitflags! {
+    pub struct WaitFlags: u32 {
+        const WNOHANG = 1;
+        const WUNTRACED = 1 << 1;
+        const WCONTINUED = 1 << 3;
+    }
+}
+
+/// Wait for any child to exit, the wait status is written to `exit_code`.
+pub fn wait(exit_code: &mut i32) -> isize {
+    sys_waitpid(-1, exit_code as *mut _, 0)
+}
+
+pub fn waitpid(pid: usize, exit_code: &mut i32) -> isize {
+    sys_waitpid(pid as isize, exit_code as *mut _, 0)
+}
+
+/// Return 0 at once if the child is still running.
 pub fn waitpid_nb(pid: usize, exit_code: &mut i32) -> isize {
-    sys_waitpid(pid as isize, exit_code as *mut _)
+    sys_waitpid(pid as isize, exit_code as *mut _, WaitFlags::WNOHANG.bits)
+}
+
+pub fn waitpid_options(pid: isize, exit_code: &mut i32, options: WaitFlags) -> isize {
+    sys_waitpid(pid, exit_code as *mut _, options.bits)
+}
+
+pub fn wifexited(status: i32) -> bool {
+    wtermsig(status) == 0
+}
+pub fn wexitstatus(status: i32) -> i32 {
+    (status >> 8) & 0xff
+}
+pub fn wifsignaled(status: i32) -> bool {
+    let sig = wtermsig(status);
+    sig != 0 && sig != 0x7f
+}
+pub fn wtermsig(status: i32) -> i32 {
+    status & 0x7f
+}
+/// Whether a task killed by a signal left a core file `core.<pid>`.
+pub fn wcoredump(status: i32) -> bool {
+    wifsignaled(status) && status & 0x80 != 0
+}
+pub fn wifstopped(status: i32) -> bool {
+    status & 0xff == 0x7f
+}
+pub fn wstopsig(status: i32) -> i32 {
+    wexitstatus(status)
+}
+pub fn wifcontinued(status: i32) -> bool {
+    status == 0xffff
 }
 
 pub fn sleep(period_ms: usize) {
-    let start = sys_get_time();
-    while sys_get_time() < start + period_ms as isize {
+    let start = get_time();
+    while get_time() < start + period_ms as isize {
         sys_yield();
     }
 }
 
+bitflags! {
+    pub struct SignalActionFlags: u32 {
+        const SA_SIGINFO = 4;
+        const SA_ONSTACK = 0x0800_0000;
+        const SA_RESTART = 0x1000_0000;
+        const SA_NODEFER = 0x4000_0000;
+        const SA_RESETHAND = 0x8000_0000;
+    }
+}
+
+pub const ITIMER_REAL: usize = 0;
+pub const ITIMER_VIRTUAL: usize = 1;
+pub const ITIMER_PROF: usize = 2;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+impl TimeVal {
+    pub fn from_ms(ms: usize) -> Self {
+        Self {
+            sec: ms / 1000,
+            usec: ms % 1000 * 1000,
+        }
+    }
+    pub fn to_ms(&self) -> usize {
+        self.sec * 1000 + self.usec / 1000
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ITimerVal {
+    pub interval: TimeVal,
+    pub value: TimeVal,
+}
+
+/// Deliver SIGALRM after `seconds`, or cancel the alarm if 0. Return the
+/// seconds left on the old alarm, rounded up.
+pub fn alarm(seconds: usize) -> isize {
+    let new = ITimerVal {
+        interval: TimeVal::default(),
+        value: TimeVal {
+            sec: seconds,
+            usec: 0,
+        },
+    };
+    let mut old = ITimerVal::default();
+    let ret = setitimer(ITIMER_REAL, &new, Some(&mut old));
+    if ret < 0 {
+        return ret;
+    }
+    (old.value.sec + (old.value.usec > 0) as usize) as isize
+}
+pub fn getitimer(which: usize, curr_value: &mut ITimerVal) -> isize {
+    sys_getitimer(which, curr_value)
+}
+pub fn setitimer(
+    which: usize,
+    new_value: &ITimerVal,
+    old_value: Option<&mut ITimerVal>,
+) -> isize {
+    sys_setitimer(
+        which,
+        new_value,
+        old_value.map_or(core::ptr::null_mut(), |v| v),
+    )
+}
+
+pub const CLOCK_REALTIME: usize = 0;
+pub const CLOCK_MONOTONIC: usize = 1;
+pub const CLOCK_PROCESS_CPUTIME_ID: usize = 2;
+pub const CLOCK_THREAD_CPUTIME_ID: usize = 3;
+
+pub const RUSAGE_SELF: isize = 0;
+pub const RUSAGE_CHILDREN: isize = -1;
+
+/// Clock ticks per second counted by `times`
+pub const CLK_TCK: usize = 100;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    pub fn to_ms(&self) -> usize {
+        self.sec * 1000 + self.nsec / 1_000_000
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tms {
+    pub utime: usize,
+    pub stime: usize,
+    pub cutime: usize,
+    pub cstime: usize,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RUsage {
+    pub utime: TimeVal,
+    pub stime: TimeVal,
+    pub maxrss: usize,
+    pub ixrss: usize,
+    pub idrss: usize,
+    pub isrss: usize,
+    pub minflt: usize,
+    pub majflt: usize,
+    pub nswap: usize,
+    pub inblock: usize,
+    pub oublock: usize,
+    pub msgsnd: usize,
+    pub msgrcv: usize,
+    pub nsignals: usize,
+    pub nvcsw: usize,
+    pub nivcsw: usize,
+}
+
+/// Return the clock ticks since boot.
+pub fn times(tms: &mut Tms) -> isize {
+    sys_times(tms)
+}
+pub fn getrusage(who: isize, usage: &mut RUsage) -> isize {
+    sys_getrusage(who, usage)
+}
+pub fn clock_gettime(clock_id: usize, tp: &mut TimeSpec) -> isize {
+    sys_clock_gettime(clock_id, tp)
+}
+
+pub const RLIMIT_CPU: usize = 0;
+pub const RLIMIT_FSIZE: usize = 1;
+pub const RLIMIT_STACK: usize = 3;
+pub const RLIMIT_CORE: usize = 4;
+pub const RLIMIT_NPROC: usize = 6;
+pub const RLIMIT_NOFILE: usize = 7;
+pub const RLIMIT_AS: usize = 9;
+pub const RLIM_INFINITY: usize = usize::MAX;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RLimit {
+    pub cur: usize,
+    pub max: usize,
+}
+
+impl Default for RLimit {
+    fn default() -> Self {
+        Self {
+            cur: RLIM_INFINITY,
+            max: RLIM_INFINITY,
+        }
+    }
+}
+
+pub fn getrlimit(resource: usize, rlim: &mut RLimit) -> isize {
+    sys_getrlimit(resource, rlim)
+}
+pub fn setrlimit(resource: usize, rlim: &RLimit) -> isize {
+    sys_setrlimit(resource, rlim)
+}
+/// Get and set a resource limit of process `pid`, or of the caller if 0.
+pub fn prlimit(
+    pid: usize,
+    resource: usize,
+    new_rlim: Option<&RLimit>,
+    old_rlim: Option<&mut RLimit>,
+) -> isize {
+    sys_prlimit(
+        pid,
+        resource,
+        new_rlim.map_or(core::ptr::null(), |r| r),
+        old_rlim.map_or(core::ptr::null_mut(), |r| r),
+    )
+}
+
 /// Action for a signal
 #[repr(C, align(16))]
 #[derive(Debug, Clone, Copy)]
 pub struct SignalAction {
     pub handler: usize,
     pub mask: SignalFlags,
+    pub flags: SignalActionFlags,
+    /// `sigaction` fills in a restorer calling `sigreturn` if it is 0
+    pub restorer: usize,
 }
 
 impl Default for SignalAction {
@@ -153,10 +960,47 @@ impl Default for SignalAction {
         Self {
             handler: 0,
             mask: SignalFlags::empty(),
+            flags: SignalActionFlags::empty(),
+            restorer: 0,
         }
     }
 }
 
+/// Passed as the second argument of handlers installed with SA_SIGINFO
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignalInfo {
+    pub signo: i32,
+    pub errno: i32,
+    pub code: i32,
+    pub pid: i32,
+    pub addr: usize,
+    /// value sent with `sigqueue`
+    pub value: usize,
+}
+
+/// Alternate signal stack for `sigaltstack`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignalStack {
+    pub sp: usize,
+    pub flags: i32,
+    pub size: usize,
+}
+
+pub const SS_ONSTACK: i32 = 1;
+pub const SS_DISABLE: i32 = 2;
+pub const MINSIGSTKSZ: usize = 2048;
+
+/// `SignalAction::handler` for the default action
+pub const SIG_DFL: usize = 0;
+/// `SignalAction::handler` to ignore the signal
+pub const SIG_IGN: usize = 1;
+
+pub const SI_USER: i32 = 0;
+pub const SI_QUEUE: i32 = -1;
+pub const SI_KERNEL: i32 = 0x80;
+
 pub const SIGDEF: i32 = 0; // Default signal handling
 pub const SIGHUP: i32 = 1;
 pub const SIGINT: i32 = 2;
@@ -190,8 +1034,11 @@ pub const SIGIO: i32 = 29;
 pub const SIGPWR: i32 = 30;
 pub const SIGSYS: i32 = 31;
 
+pub const SIGRTMIN: i32 = 32;
+pub const SIGRTMAX: i32 = 63;
+
 bitflags! {
-    pub struct SignalFlags: i32 {
+    pub struct SignalFlags: u64 {
         const SIGDEF = 1; // Default signal handling
         const SIGHUP = 1 << 1;
         const SIGINT = 1 << 2;
@@ -224,6 +1071,8 @@ bitflags! {
         const SIGIO = 1 << 29;
         const SIGPWR = 1 << 30;
         const SIGSYS = 1 << 31;
+        // SIGRTMIN..=SIGRTMAX
+        const SIGRT = 0xffff_ffff_0000_0000;
     }
 }
 
@@ -231,22 +1080,247 @@ pub fn kill(pid: usize, signum: i32) -> isize {
     sys_kill(pid, signum)
 }
 
+/// Handlers return here when they do not call `sigreturn` themselves.
+fn sig_restorer() {
+    sys_sigreturn();
+}
+
 pub fn sigaction(
     signum: i32,
     action: Option<&SignalAction>,
     old_action: Option<&mut SignalAction>,
 ) -> isize {
+    let action = action.map(|a| {
+        let mut a = *a;
+        if a.restorer == 0 {
+            a.restorer = sig_restorer as usize;
+        }
+        a
+    });
     sys_sigaction(
         signum,
-        action.map_or(core::ptr::null(), |a| a),
+        action.as_ref().map_or(core::ptr::null(), |a| a),
         old_action.map_or(core::ptr::null_mut(), |a| a),
     )
 }
 
-pub fn sigprocmask(mask: u32) -> isize {
-    sys_sigprocmask(mask)
+/// Set the signal mask, and report the old one in `old_mask` if given.
+pub fn sigprocmask(mask: u64, old_mask: Option<&mut u64>) -> isize {
+    sys_sigprocmask(mask, old_mask.map_or(core::ptr::null_mut(), |m| m))
+}
+
+/// Send a real-time or standard signal carrying `value` in its siginfo.
+pub fn sigqueue(pid: usize, signum: i32, value: usize) -> isize {
+    sys_sigqueue(pid, signum, value)
+}
+
+pub fn sigaltstack(ss: Option<&SignalStack>, old_ss: Option<&mut SignalStack>) -> isize {
+    sys_sigaltstack(
+        ss.map_or(core::ptr::null(), |s| s),
+        old_ss.map_or(core::ptr::null_mut(), |s| s),
+    )
 }
 
 pub fn sigreturn() -> isize {
     sys_sigreturn()
 }
+
+pub const PTRACE_TRACEME: usize = 0;
+pub const PTRACE_PEEKTEXT: usize = 1;
+pub const PTRACE_PEEKDATA: usize = 2;
+pub const PTRACE_POKETEXT: usize = 4;
+pub const PTRACE_POKEDATA: usize = 5;
+pub const PTRACE_CONT: usize = 7;
+pub const PTRACE_KILL: usize = 8;
+pub const PTRACE_SINGLESTEP: usize = 9;
+pub const PTRACE_GETREGS: usize = 12;
+pub const PTRACE_SETREGS: usize = 13;
+pub const PTRACE_ATTACH: usize = 16;
+pub const PTRACE_DETACH: usize = 17;
+
+/// Registers of a traced process, the Linux `user_regs_struct` of the arch
+#[cfg(target_arch = "riscv64")]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserRegs {
+    pub pc: usize,
+    // x1 to x31
+    pub x: [usize; 31],
+}
+
+#[cfg(target_arch = "x86_64")]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserRegs {
+    pub r15: usize,
+    pub r14: usize,
+    pub r13: usize,
+    pub r12: usize,
+    pub rbp: usize,
+    pub rbx: usize,
+    pub r11: usize,
+    pub r10: usize,
+    pub r9: usize,
+    pub r8: usize,
+    pub rax: usize,
+    pub rcx: usize,
+    pub rdx: usize,
+    pub rsi: usize,
+    pub rdi: usize,
+    pub orig_rax: usize,
+    pub rip: usize,
+    pub cs: usize,
+    pub eflags: usize,
+    pub rsp: usize,
+    pub ss: usize,
+    pub fs_base: usize,
+    pub gs_base: usize,
+    pub ds: usize,
+    pub es: usize,
+    pub fs: usize,
+    pub gs: usize,
+}
+
+#[cfg(target_arch = "aarch64")]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserRegs {
+    pub regs: [usize; 31],
+    pub sp: usize,
+    pub pc: usize,
+    pub pstate: usize,
+}
+
+#[cfg(target_arch = "loongarch64")]
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct UserRegs {
+    pub regs: [usize; 32],
+    pub orig_a0: usize,
+    pub csr_era: usize,
+    pub csr_badv: usize,
+    pub reserved: [usize; 10],
+}
+
+/// Names of the words of `UserRegs`, in order
+#[cfg(target_arch = "riscv64")]
+pub const USER_REG_NAMES: [&str; 32] = [
+    "pc", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4", "a5",
+    "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4", "t5",
+    "t6",
+];
+#[cfg(target_arch = "x86_64")]
+pub const USER_REG_NAMES: [&str; 27] = [
+    "r15", "r14", "r13", "r12", "rbp", "rbx", "r11", "r10", "r9", "r8", "rax", "rcx", "rdx", "rsi",
+    "rdi", "orig_rax", "rip", "cs", "eflags", "rsp", "ss", "fs_base", "gs_base", "ds", "es", "fs",
+    "gs",
+];
+#[cfg(target_arch = "aarch64")]
+pub const USER_REG_NAMES: [&str; 34] = [
+    "x0", "x1", "x2", "x3", "x4", "x5", "x6", "x7", "x8", "x9", "x10", "x11", "x12", "x13", "x14",
+    "x15", "x16", "x17", "x18", "x19", "x20", "x21", "x22", "x23", "x24", "x25", "x26", "x27",
+    "x28", "x29", "x30", "sp", "pc", "pstate",
+];
+#[cfg(target_arch = "loongarch64")]
+pub const USER_REG_NAMES: [&str; 35] = [
+    "zero", "ra", "tp", "sp", "a0", "a1", "a2", "a3", "a4", "a5", "a6", "a7", "t0", "t1", "t2",
+    "t3", "t4", "t5", "t6", "t7", "t8", "u0", "fp", "s0", "s1", "s2", "s3", "s4", "s5", "s6", "s7",
+    "s8", "orig_a0", "era", "badv",
+];
+
+// indices of the program counter and the stack pointer among the words
+#[cfg(target_arch = "riscv64")]
+const PC_SP_WORDS: (usize, usize) = (0, 2);
+#[cfg(target_arch = "x86_64")]
+const PC_SP_WORDS: (usize, usize) = (16, 19);
+#[cfg(target_arch = "aarch64")]
+const PC_SP_WORDS: (usize, usize) = (32, 31);
+#[cfg(target_arch = "loongarch64")]
+const PC_SP_WORDS: (usize, usize) = (33, 3);
+
+impl UserRegs {
+    /// The registers named by `USER_REG_NAMES`, in order.
+    pub fn as_words(&self) -> &[usize] {
+        let len = USER_REG_NAMES.len();
+        unsafe { core::slice::from_raw_parts(self as *const Self as *const usize, len) }
+    }
+    pub fn as_words_mut(&mut self) -> &mut [usize] {
+        let len = USER_REG_NAMES.len();
+        unsafe { core::slice::from_raw_parts_mut(self as *mut Self as *mut usize, len) }
+    }
+    /// The program counter, whatever the arch calls it.
+    pub fn pc(&self) -> usize {
+        self.as_words()[PC_SP_WORDS.0]
+    }
+    pub fn set_pc(&mut self, pc: usize) {
+        self.as_words_mut()[PC_SP_WORDS.0] = pc;
+    }
+    /// The stack pointer, whatever the arch calls it.
+    pub fn sp(&self) -> usize {
+        self.as_words()[PC_SP_WORDS.1]
+    }
+    pub fn set_sp(&mut self, sp: usize) {
+        self.as_words_mut()[PC_SP_WORDS.1] = sp;
+    }
+}
+
+/// The raw `ptrace`: the tracee `pid` has to be stopped, which `waitpid`
+/// reports, for every request but PTRACE_TRACEME, PTRACE_ATTACH and PTRACE_KILL.
+pub fn ptrace(request: usize, pid: usize, addr: usize, data: usize) -> isize {
+    sys_ptrace(request, pid, addr, data)
+}
+/// Read the word at `addr` of tracee `pid`.
+pub fn ptrace_peek(pid: usize, addr: usize) -> Option<usize> {
+    let mut word = 0usize;
+    let ret = sys_ptrace(PTRACE_PEEKDATA, pid, addr, &mut word as *mut usize as usize);
+    (ret == 0).then_some(word)
+}
+pub fn ptrace_getregs(pid: usize, regs: &mut UserRegs) -> isize {
+    sys_ptrace(PTRACE_GETREGS, pid, 0, regs as *mut UserRegs as usize)
+}
+pub fn ptrace_setregs(pid: usize, regs: &UserRegs) -> isize {
+    sys_ptrace(PTRACE_SETREGS, pid, 0, regs as *const UserRegs as usize)
+}
+
+pub const SECCOMP_SET_MODE_FILTER: usize = 1;
+/// Only the listed syscalls are let through
+pub const SECCOMP_MODE_ALLOW_LIST: usize = 0;
+/// The listed syscalls are turned down
+pub const SECCOMP_MODE_DENY_LIST: usize = 1;
+/// Kill the process with SIGSYS
+pub const SECCOMP_RET_KILL_PROCESS: usize = 0x8000_0000;
+/// Send SIGSYS, the syscall number is in `SignalInfo::value`, and fail with ENOSYS
+pub const SECCOMP_RET_TRAP: usize = 0x0003_0000;
+/// Fail with the errno in the low 16 bits, like `SECCOMP_RET_ERRNO | EPERM as usize`
+pub const SECCOMP_RET_ERRNO: usize = 0x0005_0000;
+/// `SignalInfo::code` of a SIGSYS sent by SECCOMP_RET_TRAP
+pub const SYS_SECCOMP: i32 = 1;
+
+#[repr(C)]
+pub struct SeccompFilterSpec {
+    pub mode: usize,
+    pub action: usize,
+    pub syscalls: *const usize,
+    pub syscalls_len: usize,
+}
+
+/// Filter the syscalls of this process and of all processes it starts from
+/// now on: with SECCOMP_MODE_ALLOW_LIST only `syscalls` are let through, with
+/// SECCOMP_MODE_DENY_LIST `syscalls` are turned down with `action`. A filter
+/// cannot be removed, exit and sigreturn are never filtered.
+pub fn seccomp_filter(mode: usize, action: usize, syscalls: &[usize]) -> isize {
+    let spec = SeccompFilterSpec {
+        mode,
+        action,
+        syscalls: syscalls.as_ptr(),
+        syscalls_len: syscalls.len(),
+    };
+    sys_seccomp(SECCOMP_SET_MODE_FILTER, 0, &spec)
+}
+
+/// Print the syscalls of this process and of the processes it starts from
+/// now on to the kernel console, or stop if `enable` is false. Return 1 if
+/// they were printed before, else 0.
+pub fn strace(enable: bool) -> isize {
+    sys_strace(enable as usize)
+}
@@ -1,27 +1,78 @@
 use core::arch::asm;
 
-use crate::SignalAction;
-
-const SYSCALL_DUP: usize = 24;
-const SYSCALL_OPEN: usize = 56;
-const SYSCALL_CLOSE: usize = 57;
-const SYSCALL_PIPE: usize = 59;
-const SYSCALL_READ: usize = 63;
-const SYSCALL_WRITE: usize = 64;
-const SYSCALL_EXIT: usize = 93;
-const SYSCALL_YIELD: usize = 124;
-const SYSCALL_KILL: usize = 129;
-const SYSCALL_SIGACTION: usize = 134;
-const SYSCALL_SIGPROCMASK: usize = 135;
-const SYSCALL_SIGRETURN: usize = 139;
-const SYSCALL_GET_TIME: usize = 169;
-const SYSCALL_GETPID: usize = 172;
-const SYSCALL_FORK: usize = 220;
-const SYSCALL_EXEC: usize = 221;
-const SYSCALL_WAITPID: usize = 260;
+use crate::{
+    ITimerVal, IoVec, RLimit, RUsage, SeccompFilterSpec, SignalAction, SignalStack, SpawnAttr,
+    Stat, SysInfo, TimeSpec, Tms, UtsName, SIGCHLD,
+};
 
-#[cfg(target_arch = "riscv64")]
+pub const SYSCALL_GETCWD: usize = 17;
+pub const SYSCALL_DUP: usize = 24;
+pub const SYSCALL_IOCTL: usize = 29;
+pub const SYSCALL_MKDIRAT: usize = 34;
+pub const SYSCALL_UNLINKAT: usize = 35;
+pub const SYSCALL_LINKAT: usize = 37;
+pub const SYSCALL_UMOUNT2: usize = 39;
+pub const SYSCALL_MOUNT: usize = 40;
+pub const SYSCALL_CHDIR: usize = 49;
+pub const SYSCALL_OPEN: usize = 56;
+pub const SYSCALL_CLOSE: usize = 57;
+pub const SYSCALL_PIPE: usize = 59;
+pub const SYSCALL_READ: usize = 63;
+pub const SYSCALL_WRITE: usize = 64;
+pub const SYSCALL_WRITEV: usize = 66;
+pub const SYSCALL_FSTAT: usize = 80;
+pub const SYSCALL_EXIT: usize = 93;
+pub const SYSCALL_GETITIMER: usize = 102;
+pub const SYSCALL_SETITIMER: usize = 103;
+pub const SYSCALL_CLOCK_GETTIME: usize = 113;
+pub const SYSCALL_PTRACE: usize = 117;
+pub const SYSCALL_YIELD: usize = 124;
+pub const SYSCALL_KILL: usize = 129;
+pub const SYSCALL_SIGALTSTACK: usize = 132;
+pub const SYSCALL_SIGACTION: usize = 134;
+pub const SYSCALL_SIGPROCMASK: usize = 135;
+pub const SYSCALL_SIGQUEUE: usize = 138;
+pub const SYSCALL_SIGRETURN: usize = 139;
+pub const SYSCALL_SETGID: usize = 144;
+pub const SYSCALL_SETUID: usize = 146;
+pub const SYSCALL_TIMES: usize = 153;
+pub const SYSCALL_UNAME: usize = 160;
+pub const SYSCALL_GETRLIMIT: usize = 163;
+pub const SYSCALL_SETRLIMIT: usize = 164;
+pub const SYSCALL_GETRUSAGE: usize = 165;
+pub const SYSCALL_GET_TIME: usize = 169;
+pub const SYSCALL_GETPID: usize = 172;
+pub const SYSCALL_GETPPID: usize = 173;
+pub const SYSCALL_GETUID: usize = 174;
+pub const SYSCALL_GETEUID: usize = 175;
+pub const SYSCALL_GETGID: usize = 176;
+pub const SYSCALL_GETEGID: usize = 177;
+pub const SYSCALL_GETTID: usize = 178;
+pub const SYSCALL_SYSINFO: usize = 179;
+pub const SYSCALL_BRK: usize = 214;
+pub const SYSCALL_MUNMAP: usize = 215;
+pub const SYSCALL_CLONE: usize = 220;
+pub const SYSCALL_EXECVE: usize = 221;
+pub const SYSCALL_MMAP: usize = 222;
+pub const SYSCALL_WAITPID: usize = 260;
+pub const SYSCALL_PRLIMIT: usize = 261;
+pub const SYSCALL_RENAMEAT2: usize = 276;
+pub const SYSCALL_SECCOMP: usize = 277;
+pub const SYSCALL_GETRANDOM: usize = 278;
+pub const SYSCALL_VFORK: usize = 1001;
+pub const SYSCALL_SPAWN: usize = 1002;
+pub const SYSCALL_STRACE: usize = 1003;
+pub const SYSCALL_RMDIR: usize = 1004;
+
+/// A syscall with three arguments, the other argument registers are 0.
 fn syscall(id: usize, args: [usize; 3]) -> isize {
+    syscall6(id, [args[0], args[1], args[2], 0, 0, 0])
+}
+
+// Each arch passes the id and up to six arguments in registers like Linux,
+// the result comes back in the register of the first argument
+#[cfg(target_arch = "riscv64")]
+fn syscall6(id: usize, args: [usize; 6]) -> isize {
     let mut ret: isize;
     unsafe {
         asm!(
@@ -29,6 +80,9 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
             inlateout("x10") args[0] => ret,
             in("x11") args[1],
             in("x12") args[2],
+            in("x13") args[3],
+            in("x14") args[4],
+            in("x15") args[5],
             in("x17") id
         );
     }
@@ -36,7 +90,7 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
 }
 
 #[cfg(target_arch = "aarch64")]
-fn syscall(id: usize, args: [usize; 3]) -> isize {
+fn syscall6(id: usize, args: [usize; 6]) -> isize {
     let mut ret: isize;
     unsafe {
         asm!(
@@ -44,6 +98,9 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
             inlateout("x0") args[0] => ret,
             in("x1") args[1],
             in("x2") args[2],
+            in("x3") args[3],
+            in("x4") args[4],
+            in("x5") args[5],
             in("x8") id
         );
     }
@@ -51,28 +108,28 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
 }
 
 #[cfg(target_arch = "x86_64")]
-fn syscall(id: usize, args: [usize; 3]) -> isize {
+fn syscall6(id: usize, args: [usize; 6]) -> isize {
     let mut ret: isize;
     unsafe {
         asm!(
-            "
-                push r11
-                push rcx
-                syscall
-                pop  rcx
-                pop  r11
-            ",
+            "syscall",
             in("rdi") args[0],
             in("rsi") args[1],
             in("rdx") args[2],
-            inlateout("rax") id => ret
+            in("r10") args[3],
+            in("r8") args[4],
+            in("r9") args[5],
+            inlateout("rax") id => ret,
+            // syscall saves the user rip and rflags in them
+            out("rcx") _,
+            out("r11") _,
         );
     }
     ret
 }
 
 #[cfg(target_arch = "loongarch64")]
-fn syscall(id: usize, args: [usize; 3]) -> isize {
+fn syscall6(id: usize, args: [usize; 6]) -> isize {
     let mut ret: isize;
     unsafe {
         asm!(
@@ -80,6 +137,9 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
             inlateout("$r4") args[0] => ret,
             in("$r5") args[1],
             in("$r6") args[2],
+            in("$r7") args[3],
+            in("$r8") args[4],
+            in("$r9") args[5],
             in("$r11") id
         );
     }
@@ -94,6 +154,85 @@ pub fn sys_open(path: &str, flags: u32) -> isize {
     syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0])
 }
 
+pub fn sys_mkdirat(dirfd: isize, path: &str, mode: u32) -> isize {
+    syscall(
+        SYSCALL_MKDIRAT,
+        [dirfd as usize, path.as_ptr() as usize, mode as usize],
+    )
+}
+
+pub fn sys_unlinkat(dirfd: isize, path: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_UNLINKAT,
+        [dirfd as usize, path.as_ptr() as usize, flags as usize],
+    )
+}
+
+pub fn sys_linkat(
+    old_dirfd: isize,
+    old_path: &str,
+    new_dirfd: isize,
+    new_path: &str,
+    flags: u32,
+) -> isize {
+    syscall6(
+        SYSCALL_LINKAT,
+        [
+            old_dirfd as usize,
+            old_path.as_ptr() as usize,
+            new_dirfd as usize,
+            new_path.as_ptr() as usize,
+            flags as usize,
+            0,
+        ],
+    )
+}
+
+pub fn sys_renameat2(
+    old_dirfd: isize,
+    old_path: &str,
+    new_dirfd: isize,
+    new_path: &str,
+    flags: u32,
+) -> isize {
+    syscall6(
+        SYSCALL_RENAMEAT2,
+        [
+            old_dirfd as usize,
+            old_path.as_ptr() as usize,
+            new_dirfd as usize,
+            new_path.as_ptr() as usize,
+            flags as usize,
+            0,
+        ],
+    )
+}
+
+pub fn sys_chdir(path: &str) -> isize {
+    syscall(SYSCALL_CHDIR, [path.as_ptr() as usize, 0, 0])
+}
+
+pub fn sys_mount(source: &str, target: &str, fs_type: &str, flags: usize) -> isize {
+    syscall6(
+        SYSCALL_MOUNT,
+        [
+            source.as_ptr() as usize,
+            target.as_ptr() as usize,
+            fs_type.as_ptr() as usize,
+            flags,
+            0,
+            0,
+        ],
+    )
+}
+
+pub fn sys_umount2(target: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_UMOUNT2,
+        [target.as_ptr() as usize, flags as usize, 0],
+    )
+}
+
 pub fn sys_close(fd: usize) -> isize {
     syscall(SYSCALL_CLOSE, [fd, 0, 0])
 }
@@ -113,6 +252,49 @@ pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()])
 }
 
+pub fn sys_writev(fd: usize, iov: &[IoVec]) -> isize {
+    syscall(SYSCALL_WRITEV, [fd, iov.as_ptr() as usize, iov.len()])
+}
+
+pub fn sys_fstat(fd: usize, stat: *mut Stat) -> isize {
+    syscall(SYSCALL_FSTAT, [fd, stat as usize, 0])
+}
+
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> isize {
+    syscall(SYSCALL_IOCTL, [fd, request, arg])
+}
+
+pub fn sys_getcwd(buf: &mut [u8]) -> isize {
+    syscall(SYSCALL_GETCWD, [buf.as_mut_ptr() as usize, buf.len(), 0])
+}
+
+pub fn sys_uname(buf: *mut UtsName) -> isize {
+    syscall(SYSCALL_UNAME, [buf as usize, 0, 0])
+}
+
+pub fn sys_sysinfo(info: &mut SysInfo) -> isize {
+    syscall(SYSCALL_SYSINFO, [info as *mut SysInfo as usize, 0, 0])
+}
+
+pub fn sys_getrandom(buf: &mut [u8], flags: u32) -> isize {
+    syscall(
+        SYSCALL_GETRANDOM,
+        [buf.as_mut_ptr() as usize, buf.len(), flags as usize],
+    )
+}
+
+pub fn sys_brk(brk: usize) -> isize {
+    syscall(SYSCALL_BRK, [brk, 0, 0])
+}
+
+pub fn sys_mmap(addr: usize, len: usize, prot: usize, flags: usize) -> isize {
+    syscall6(SYSCALL_MMAP, [addr, len, prot, flags, usize::MAX, 0])
+}
+
+pub fn sys_munmap(addr: usize, len: usize) -> isize {
+    syscall(SYSCALL_MUNMAP, [addr, len, 0])
+}
+
 pub fn sys_exit(exit_code: i32) -> ! {
     syscall(SYSCALL_EXIT, [exit_code as usize, 0, 0]);
     panic!("sys_exit never returns!");
@@ -122,6 +304,11 @@ pub fn sys_yield() -> isize {
     syscall(SYSCALL_YIELD, [0, 0, 0])
 }
 
+/// Syscall `id` as is, even one the kernel does not know.
+pub fn sys_raw(id: usize, args: [usize; 6]) -> isize {
+    syscall6(id, args)
+}
+
 pub fn sys_kill(pid: usize, signal: i32) -> isize {
     syscall(SYSCALL_KILL, [pid, signal as usize, 0])
 }
@@ -135,18 +322,25 @@ pub fn sys_getpid() -> isize {
 }
 
 pub fn sys_fork() -> isize {
-    syscall(SYSCALL_FORK, [0, 0, 0])
+    syscall(SYSCALL_CLONE, [SIGCHLD as usize, 0, 0])
 }
 
-pub fn sys_exec(path: &str, args: &[*const u8]) -> isize {
+pub fn sys_execve(path: &str, args: &[*const u8], envs: &[*const u8]) -> isize {
     syscall(
-        SYSCALL_EXEC,
-        [path.as_ptr() as usize, args.as_ptr() as usize, 0],
+        SYSCALL_EXECVE,
+        [
+            path.as_ptr() as usize,
+            args.as_ptr() as usize,
+            envs.as_ptr() as usize,
+        ],
     )
 }
 
-pub fn sys_waitpid(pid: isize, exit_code: *mut i32) -> isize {
-    syscall(SYSCALL_WAITPID, [pid as usize, exit_code as usize, 0])
+pub fn sys_waitpid(pid: isize, exit_code: *mut i32, options: u32) -> isize {
+    syscall(
+        SYSCALL_WAITPID,
+        [pid as usize, exit_code as usize, options as usize],
+    )
 }
 
 pub fn sys_sigaction(
@@ -170,10 +364,359 @@ pub fn sys_sigaction(
     */
 }
 
-pub fn sys_sigprocmask(mask: u32) -> isize {
-    syscall(SYSCALL_SIGPROCMASK, [mask as usize, 0, 0])
+pub fn sys_sigprocmask(mask: u64, old_mask: *mut u64) -> isize {
+    syscall(SYSCALL_SIGPROCMASK, [mask as usize, old_mask as usize, 0])
+}
+
+pub fn sys_sigqueue(pid: usize, signum: i32, value: usize) -> isize {
+    syscall(SYSCALL_SIGQUEUE, [pid, signum as usize, value])
+}
+
+pub fn sys_sigaltstack(ss: *const SignalStack, old_ss: *mut SignalStack) -> isize {
+    syscall(SYSCALL_SIGALTSTACK, [ss as usize, old_ss as usize, 0])
 }
 
 pub fn sys_sigreturn() -> isize {
     syscall(SYSCALL_SIGRETURN, [0, 0, 0])
 }
+
+pub fn sys_getitimer(which: usize, curr_value: *mut ITimerVal) -> isize {
+    syscall(SYSCALL_GETITIMER, [which, curr_value as usize, 0])
+}
+
+pub fn sys_setitimer(
+    which: usize,
+    new_value: *const ITimerVal,
+    old_value: *mut ITimerVal,
+) -> isize {
+    syscall(
+        SYSCALL_SETITIMER,
+        [which, new_value as usize, old_value as usize],
+    )
+}
+
+pub fn sys_clock_gettime(clock_id: usize, tp: *mut TimeSpec) -> isize {
+    syscall(SYSCALL_CLOCK_GETTIME, [clock_id, tp as usize, 0])
+}
+
+pub fn sys_times(tms: *mut Tms) -> isize {
+    syscall(SYSCALL_TIMES, [tms as usize, 0, 0])
+}
+
+pub fn sys_getrusage(who: isize, usage: *mut RUsage) -> isize {
+    syscall(SYSCALL_GETRUSAGE, [who as usize, usage as usize, 0])
+}
+
+pub fn sys_getrlimit(resource: usize, rlim: *mut RLimit) -> isize {
+    syscall(SYSCALL_GETRLIMIT, [resource, rlim as usize, 0])
+}
+
+pub fn sys_setrlimit(resource: usize, rlim: *const RLimit) -> isize {
+    syscall(SYSCALL_SETRLIMIT, [resource, rlim as usize, 0])
+}
+
+pub fn sys_prlimit(
+    pid: usize,
+    resource: usize,
+    new_rlim: *const RLimit,
+    old_rlim: *mut RLimit,
+) -> isize {
+    syscall6(
+        SYSCALL_PRLIMIT,
+        [pid, resource, new_rlim as usize, old_rlim as usize, 0, 0],
+    )
+}
+
+pub fn sys_seccomp(operation: usize, flags: usize, args: &SeccompFilterSpec) -> isize {
+    syscall(
+        SYSCALL_SECCOMP,
+        [operation, flags, args as *const SeccompFilterSpec as usize],
+    )
+}
+
+pub fn sys_strace(enable: usize) -> isize {
+    syscall(SYSCALL_STRACE, [enable, 0, 0])
+}
+
+pub fn sys_rmdir(path: &str) -> isize {
+    syscall(SYSCALL_RMDIR, [path.as_ptr() as usize, 0, 0])
+}
+
+pub fn sys_gettid() -> isize {
+    syscall(SYSCALL_GETTID, [0, 0, 0])
+}
+
+pub fn sys_getppid() -> isize {
+    syscall(SYSCALL_GETPPID, [0, 0, 0])
+}
+
+pub fn sys_getuid() -> isize {
+    syscall(SYSCALL_GETUID, [0, 0, 0])
+}
+
+pub fn sys_geteuid() -> isize {
+    syscall(SYSCALL_GETEUID, [0, 0, 0])
+}
+
+pub fn sys_getgid() -> isize {
+    syscall(SYSCALL_GETGID, [0, 0, 0])
+}
+
+pub fn sys_getegid() -> isize {
+    syscall(SYSCALL_GETEGID, [0, 0, 0])
+}
+
+pub fn sys_setuid(uid: usize) -> isize {
+    syscall(SYSCALL_SETUID, [uid, 0, 0])
+}
+
+pub fn sys_setgid(gid: usize) -> isize {
+    syscall(SYSCALL_SETGID, [gid, 0, 0])
+}
+
+pub fn sys_ptrace(request: usize, pid: usize, addr: usize, data: usize) -> isize {
+    syscall6(SYSCALL_PTRACE, [request, pid, addr, data, 0, 0])
+}
+
+pub fn sys_spawn(path: &str, args: &[*const u8], attr: &SpawnAttr) -> isize {
+    syscall(
+        SYSCALL_SPAWN,
+        [
+            path.as_ptr() as usize,
+            args.as_ptr() as usize,
+            attr as *const SpawnAttr as usize,
+        ],
+    )
+}
+
+// The child of clone may start on a new stack, so it cannot return to Rust
+// code. It calls `f(arg)` and exits with its return value in the same asm.
+
+#[cfg(target_arch = "riscv64")]
+pub fn sys_clone(
+    f: extern "C" fn(usize) -> i32,
+    stack: usize,
+    flags: usize,
+    arg: usize,
+    ptid: *mut u32,
+    tls: usize,
+    ctid: *mut u32,
+) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            "bnez a0, 2f",
+            "mv a0, {arg}",
+            "jalr {f}",
+            "li a7, 93",
+            "ecall",
+            "2:",
+            f = in(reg) f,
+            arg = in(reg) arg,
+            inlateout("x10") flags => ret,
+            in("x11") stack,
+            in("x12") ptid,
+            in("x13") tls,
+            in("x14") ctid,
+            in("x17") SYSCALL_CLONE
+        );
+    }
+    ret
+}
+
+#[cfg(target_arch = "aarch64")]
+pub fn sys_clone(
+    f: extern "C" fn(usize) -> i32,
+    stack: usize,
+    flags: usize,
+    arg: usize,
+    ptid: *mut u32,
+    tls: usize,
+    ctid: *mut u32,
+) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "svc #0",
+            "cbnz x0, 2f",
+            "mov x0, {arg}",
+            "blr {f}",
+            "mov x8, #93",
+            "svc #0",
+            "2:",
+            f = in(reg) f,
+            arg = in(reg) arg,
+            inlateout("x0") flags => ret,
+            in("x1") stack,
+            in("x2") ptid,
+            in("x3") tls,
+            in("x4") ctid,
+            in("x8") SYSCALL_CLONE
+        );
+    }
+    ret
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_clone(
+    f: extern "C" fn(usize) -> i32,
+    stack: usize,
+    flags: usize,
+    arg: usize,
+    ptid: *mut u32,
+    tls: usize,
+    ctid: *mut u32,
+) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "syscall",
+            "test rax, rax",
+            "jnz 2f",
+            "mov rdi, {arg}",
+            "call {f}",
+            "mov edi, eax",
+            "mov eax, 93",
+            "syscall",
+            "2:",
+            f = in(reg) f,
+            arg = in(reg) arg,
+            in("rdi") flags,
+            in("rsi") stack,
+            in("rdx") ptid,
+            in("r10") tls,
+            in("r8") ctid,
+            inlateout("rax") SYSCALL_CLONE => ret,
+            out("rcx") _,
+            out("r11") _,
+        );
+    }
+    ret
+}
+
+#[cfg(target_arch = "loongarch64")]
+pub fn sys_clone(
+    f: extern "C" fn(usize) -> i32,
+    stack: usize,
+    flags: usize,
+    arg: usize,
+    ptid: *mut u32,
+    tls: usize,
+    ctid: *mut u32,
+) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "syscall 0",
+            "bnez $a0, 2f",
+            "move $a0, {arg}",
+            "jirl $ra, {f}, 0",
+            "li.w $a7, 93",
+            "syscall 0",
+            "2:",
+            f = in(reg) f,
+            arg = in(reg) arg,
+            inlateout("$r4") flags => ret,
+            in("$r5") stack,
+            in("$r6") ptid,
+            in("$r7") tls,
+            in("$r8") ctid,
+            in("$r11") SYSCALL_CLONE
+        );
+    }
+    ret
+}
+
+// The child of vfork runs on the stack of its parent until it execs or
+// exits, so it cannot return to Rust code, whose frames the parent still
+// has to return through. It calls `f(arg)` below the parent's sp and exits
+// with its return value in the same asm.
+
+#[cfg(target_arch = "riscv64")]
+pub fn sys_vfork(f: extern "C" fn(usize) -> i32, arg: usize) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            "bnez a0, 2f",
+            "mv a0, {arg}",
+            "jalr {f}",
+            "li a7, 93",
+            "ecall",
+            "2:",
+            f = in(reg) f,
+            arg = in(reg) arg,
+            out("x10") ret,
+            in("x17") SYSCALL_VFORK
+        );
+    }
+    ret
+}
+
+#[cfg(target_arch = "aarch64")]
+pub fn sys_vfork(f: extern "C" fn(usize) -> i32, arg: usize) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "svc #0",
+            "cbnz x0, 2f",
+            "mov x0, {arg}",
+            "blr {f}",
+            "mov x8, #93",
+            "svc #0",
+            "2:",
+            f = in(reg) f,
+            arg = in(reg) arg,
+            out("x0") ret,
+            in("x8") SYSCALL_VFORK
+        );
+    }
+    ret
+}
+
+#[cfg(target_arch = "x86_64")]
+pub fn sys_vfork(f: extern "C" fn(usize) -> i32, arg: usize) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "syscall",
+            "test rax, rax",
+            "jnz 2f",
+            // keep clear of the red zone of the parent
+            "sub rsp, 128",
+            "call {f}",
+            "mov edi, eax",
+            "mov eax, 93",
+            "syscall",
+            "2:",
+            f = in(reg) f,
+            in("rdi") arg,
+            inlateout("rax") SYSCALL_VFORK => ret,
+            out("rcx") _,
+            out("r11") _,
+        );
+    }
+    ret
+}
+
+#[cfg(target_arch = "loongarch64")]
+pub fn sys_vfork(f: extern "C" fn(usize) -> i32, arg: usize) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "syscall 0",
+            "bnez $a0, 2f",
+            "move $a0, {arg}",
+            "jirl $ra, {f}, 0",
+            "li.w $a7, 93",
+            "syscall 0",
+            "2:",
+            f = in(reg) f,
+            arg = in(reg) arg,
+            out("$r4") ret,
+            in("$r11") SYSCALL_VFORK
+        );
+    }
+    ret
+}
@@ -0,0 +1,47 @@
+//! Reading the time from the vDSO page of the kernel, without a syscall.
+
+use core::ptr::read_volatile;
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+
+/// The start of the vDSO page, same layout as in the kernel
+#[repr(C)]
+struct VdsoData {
+    // odd while the kernel updates the fields below
+    seq: AtomicUsize,
+    // time counter ticks per second
+    freq: usize,
+    // the time counter at the last update
+    counter: usize,
+    // the kernel clock in microseconds at the last update
+    offset_us: usize,
+    // offset in the page of the code which returns the time counter
+    read_counter: usize,
+}
+
+/// The kernel clock in microseconds, read from the vDSO at `base`.
+pub fn time_us(base: usize) -> usize {
+    let data = unsafe { &*(base as *const VdsoData) };
+    let read_counter: extern "C" fn() -> usize =
+        unsafe { core::mem::transmute(base + read_volatile(&data.read_counter)) };
+    loop {
+        let seq = data.seq.load(Ordering::Acquire);
+        if seq % 2 == 1 {
+            core::hint::spin_loop();
+            continue;
+        }
+        let (freq, counter, offset_us) = unsafe {
+            (
+                read_volatile(&data.freq),
+                read_volatile(&data.counter),
+                read_volatile(&data.offset_us),
+            )
+        };
+        let now = read_counter();
+        fence(Ordering::Acquire);
+        // the kernel updated the fields while they were read
+        if data.seq.load(Ordering::Relaxed) != seq {
+            continue;
+        }
+        return offset_us + now.saturating_sub(counter) * 1_000_000 / freq;
+    }
+}